@@ -8,6 +8,38 @@ use crate::types::DataValue;
 #[derive(PartialEq, Clone)]
 pub struct DataChunk {
     pub arrays: Arc<[ArrayImpl]>,
+    /// The change kind of each row, `None` if all rows are inserts.
+    ops: Option<Arc<[RowKind]>>,
+}
+
+/// The kind of change a row describes in a changelog stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RowKind {
+    Insert,
+    Delete,
+    /// The previous content of an updated row.
+    UpdateBefore,
+    /// The new content of an updated row.
+    UpdateAfter,
+}
+
+impl RowKind {
+    /// Returns true if the row retracts a previously emitted row.
+    pub fn is_retract(self) -> bool {
+        matches!(self, RowKind::Delete | RowKind::UpdateBefore)
+    }
+}
+
+impl fmt::Display for RowKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            RowKind::Insert => "+I",
+            RowKind::Delete => "-D",
+            RowKind::UpdateBefore => "-U",
+            RowKind::UpdateAfter => "+U",
+        };
+        write!(f, "{}", s)
+    }
 }
 
 
@@ -23,7 +55,7 @@ impl FromIterator<ArrayImpl> for DataChunk {
             arrays.iter().map(|a| a.len()).all(|l| l == cardinality),
             "all arrays must have the same length"
         );
-        DataChunk { arrays }
+        DataChunk { arrays, ops: None }
     }
 }
 
@@ -33,12 +65,14 @@ impl DataChunk {
         DataChunk {
             arrays: [ArrayImpl::Int32([item].into_iter().collect())]
                 .into_iter().collect(),
+            ops: None,
         }
     }
 
     pub fn no_column() -> Self {
         DataChunk {
             arrays: Arc::new([]),
+            ops: None,
         }
     }
 
@@ -56,9 +90,22 @@ impl DataChunk {
         DataChunk::from_iter([ArrayBuilderImpl::from(ArrayBuilderImpl::Utf8(string_builder)).finish()])
     }
 
+    /// Creates a chunk of string columns from the rows, which have `columns` strings each.
+    pub fn from_str_rows(columns: usize, rows: Vec<Vec<String>>) -> Self {
+        let mut builders: Vec<_> = (0..columns).map(|_| Utf8ArrayBuilder::with_capacity(rows.len())).collect();
+        for row in &rows {
+            for (builder, item) in builders.iter_mut().zip(row) {
+                builder.push(Some(item.as_str()));
+            }
+        }
+        DataChunk::from_iter(builders.into_iter()
+            .map(|builder| ArrayBuilderImpl::Utf8(builder).finish()))
+    }
+
     pub fn from_array(array: ArrayImpl) -> Self {
         DataChunk {
             arrays: [array].into_iter().collect(),
+            ops: None,
         }
     }
 
@@ -85,6 +132,51 @@ impl DataChunk {
     pub fn arrays(&self) -> &[ArrayImpl] {
         &self.arrays
     }
+
+    /// Sets the change kind of each row.
+    pub fn with_ops(self, ops: impl IntoIterator<Item = RowKind>) -> Self {
+        let ops: Arc<[RowKind]> = ops.into_iter().collect();
+        assert_eq!(ops.len(), self.cardinality(), "ops must have the same length as the chunk");
+        let ops = if ops.iter().all(|op| *op == RowKind::Insert) { None } else { Some(ops) };
+        DataChunk { ops, ..self }
+    }
+
+    /// Returns the change kind of the row.
+    pub fn op(&self, idx: usize) -> RowKind {
+        self.ops.as_ref().map_or(RowKind::Insert, |ops| ops[idx])
+    }
+
+    /// Returns an iterator over the change kinds of all rows.
+    pub fn ops(&self) -> impl Iterator<Item = RowKind> + '_ {
+        (0..self.cardinality()).map(|i| self.op(i))
+    }
+
+    /// Returns true if all rows are inserts.
+    pub fn is_append_only(&self) -> bool {
+        self.ops.is_none()
+    }
+
+    /// Returns the rows in the given `range`.
+    pub fn slice(&self, range: std::ops::Range<usize>) -> Self {
+        let arrays = self.arrays.iter()
+            .map(|a| a.slice(range.clone()))
+            .collect();
+        let ops = self.ops.as_ref().map(|ops| ops[range].into());
+        DataChunk { arrays, ops }
+    }
+
+    /// Returns the rows of which the corresponding `visibility` is true.
+    pub fn filter(&self, visibility: &[bool]) -> Self {
+        let arrays = self.arrays.iter()
+            .map(|a| a.filter(visibility.iter().copied()))
+            .collect();
+        let ops = self.ops.as_ref().map(|ops| ops.iter()
+            .zip(visibility)
+            .filter(|(_, visible)| **visible)
+            .map(|(op, _)| *op)
+            .collect());
+        DataChunk { arrays, ops }
+    }
 }
 
 pub struct RowRef<'a> {
@@ -117,13 +209,25 @@ impl RowRef<'_> {
 
 #[cfg(test)]
 mod tests {
-    use crate::array::DataChunk;
+    use crate::array::{DataChunk, RowKind};
 
     #[test]
     fn test_collect() {
         let chunk = DataChunk::single_str("sd");
         println!("{}", chunk);
     }
+
+    #[test]
+    fn test_ops() {
+        let chunk = DataChunk::from_strs(vec!["a".into(), "b".into(), "c".into()]);
+        let chunk = chunk.with_ops([RowKind::UpdateBefore, RowKind::UpdateAfter, RowKind::Insert]);
+        assert!(!chunk.is_append_only());
+        assert_eq!(chunk.slice(1..3).ops().collect::<Vec<_>>(),
+                   vec![RowKind::UpdateAfter, RowKind::Insert]);
+        assert_eq!(chunk.filter(&[true, false, true]).ops().collect::<Vec<_>>(),
+                   vec![RowKind::UpdateBefore, RowKind::Insert]);
+        assert!(chunk.filter(&[false, true, true]).with_ops([RowKind::Insert; 2]).is_append_only());
+    }
 }
 
 
@@ -134,8 +238,12 @@ impl fmt::Display for DataChunk {
         let mut table = Table::new();
         table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
         for i in 0..self.cardinality() {
-            let row = self.arrays.iter().map(|a| a.get(i).to_string()).collect();
-            table.add_row(row);
+            let mut row: Vec<_> = self.arrays.iter().map(|a| a.get(i).to_string()).collect();
+            // show the change kinds only if there are changes other than inserts
+            if !self.is_append_only() {
+                row.insert(0, self.op(i).to_string());
+            }
+            table.add_row(row.into());
         }
         write!(f, "{}", table)
     }
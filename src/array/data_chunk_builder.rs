@@ -1,8 +1,9 @@
-use crate::array::{ArrayBuilderImpl, DataChunk};
+use crate::array::{ArrayBuilderImpl, DataChunk, RowKind};
 use crate::types::{DataType, DataValue};
 
 pub struct DataChunkBuilder {
     array_builders: Vec<ArrayBuilderImpl>,
+    ops: Vec<RowKind>,
     size: usize,
     capacity: usize,
 }
@@ -18,12 +19,19 @@ impl DataChunkBuilder {
             .collect();
         DataChunkBuilder {
             array_builders,
+            ops: Vec::with_capacity(capacity),
             size: 0,
             capacity,
         }
     }
 
     pub fn push_row(&mut self, row: impl IntoIterator<Item = DataValue>) -> Option<DataChunk> {
+        self.push_row_with_op(RowKind::Insert, row)
+    }
+
+    pub fn push_row_with_op(&mut self, op: RowKind, row: impl IntoIterator<Item = DataValue>)
+        -> Option<DataChunk> {
+        self.ops.push(op);
         self.array_builders
             .iter_mut()
             .zip(row)
@@ -49,7 +57,8 @@ impl DataChunkBuilder {
                         builder.reserve(capacity);
                         chunk
                     })
-                    .collect(),
+                    .collect::<DataChunk>()
+                    .with_ops(std::mem::replace(&mut self.ops, Vec::with_capacity(capacity))),
             ),
         }
     }
@@ -3,10 +3,11 @@ pub mod iter;
 pub mod utf8_array;
 pub mod data_chunk;
 mod data_chunk_builder;
+mod ops;
 
 use crate::array::iter::ArrayIter;
 use crate::array::utf8_array::{Utf8Array, Utf8ArrayBuilder};
-use crate::types::{ConvertError, DataType, DataTypeKind, DataValue};
+use crate::types::{parse_bool, timestamp, ConvertError, DataType, DataTypeKind, DataValue};
 pub use self::primitive_array::*;
 pub use self::data_chunk::*;
 pub use self::data_chunk_builder::*;
@@ -44,10 +45,31 @@ pub trait Array: Sized + Send + Sync + 'static {
     fn is_empty(&self) -> bool {
         self.len() == 0
     }
+
+    /// Returns the elements in the given `range`.
+    fn slice(&self, range: std::ops::Range<usize>) -> Self {
+        let mut builder = Self::Builder::with_capacity(range.len());
+        for i in range {
+            builder.push(self.get(i));
+        }
+        builder.finish()
+    }
+
+    /// Returns the elements of which the corresponding `visibility` is true.
+    fn filter(&self, visibility: impl Iterator<Item = bool>) -> Self {
+        let mut builder = Self::Builder::with_capacity(self.len());
+        for (value, visible) in self.iter().zip(visibility) {
+            if visible {
+                builder.push(value);
+            }
+        }
+        builder.finish()
+    }
 }
 
 pub type BoolArray = PrimitiveArray<bool>;
 pub type I32Array = PrimitiveArray<i32>;
+pub type I64Array = PrimitiveArray<i64>;
 pub type F64Array = PrimitiveArray<f64>;
 
 #[derive(Clone, PartialEq)]
@@ -55,10 +77,13 @@ pub enum ArrayImpl {
     Bool(BoolArray),
     Int32(I32Array),
     Utf8(Utf8Array),
+    Timestamp(I64Array),
+    Interval(I64Array),
 }
 
 pub type BoolArrayBuilder = PrimitiveArrayBuilder<bool>;
 pub type I32ArrayBuilder = PrimitiveArrayBuilder<i32>;
+pub type I64ArrayBuilder = PrimitiveArrayBuilder<i64>;
 pub type F64ArrayBuilder = PrimitiveArrayBuilder<f64>;
 
 /// Embeds all types of array builders in `array` module.
@@ -66,6 +91,8 @@ pub enum ArrayBuilderImpl {
     Bool(BoolArrayBuilder),
     Int32(I32ArrayBuilder),
     Utf8(Utf8ArrayBuilder),
+    Timestamp(I64ArrayBuilder),
+    Interval(I64ArrayBuilder),
 }
 
 #[derive(Debug, Clone)]
@@ -116,11 +143,14 @@ impl ArrayBuilderImpl {
 
     pub fn with_capacity(capacity: usize, ty: &DataType) -> Self {
         match ty.kind() {
-            DataTypeKind::Bool => Self::Bool(BoolArrayBuilder::with_capacity(capacity)),
+            // a NULL without specific type is stored as a boolean array of nulls
+            DataTypeKind::Null | DataTypeKind::Bool => Self::Bool(BoolArrayBuilder::with_capacity(capacity)),
             DataTypeKind::Int32 => Self::Int32(I32ArrayBuilder::with_capacity(capacity)),
             DataTypeKind::String => {
                 Self::Utf8(Utf8ArrayBuilder::with_capacity(capacity))
             }
+            DataTypeKind::Timestamp => Self::Timestamp(I64ArrayBuilder::with_capacity(capacity)),
+            DataTypeKind::Interval => Self::Interval(I64ArrayBuilder::with_capacity(capacity)),
             _ => panic!("unsupported data type"),
         }
     }
@@ -135,20 +165,34 @@ impl ArrayBuilderImpl {
             (Self::Bool(a), DataValue::Bool(v)) => a.push(Some(v)),
             (Self::Int32(a), DataValue::Int32(v)) => a.push(Some(v)),
             (Self::Utf8(a), DataValue::String(v)) => a.push(Some(v)),
+            (Self::Timestamp(a), DataValue::Timestamp(v)) => a.push(Some(v)),
+            (Self::Interval(a), DataValue::Interval(v)) => a.push(Some(v)),
             (Self::Bool(a), DataValue::Null) => a.push(None),
             (Self::Int32(a), DataValue::Null) => a.push(None),
             (Self::Utf8(a), DataValue::Null) => a.push(None),
+            (Self::Timestamp(a), DataValue::Null) => a.push(None),
+            (Self::Interval(a), DataValue::Null) => a.push(None),
             _ => panic!("failed to push value: type mismatch"),
         }
     }
 
+    /// Pushes a value parsed from a string, where an empty string is NULL.
     pub fn push_str(&mut self, s: &str) -> Result<(), ConvertError> {
         let null = s.is_empty();
         match self {
             Self::Bool(a) if null => a.push(None),
             Self::Int32(a) if null => a.push(None),
             Self::Utf8(a) if null => a.push(None),
-            _ => panic!("failed to push value: type mismatch"),
+            Self::Timestamp(a) if null => a.push(None),
+            Self::Interval(a) if null => a.push(None),
+            Self::Bool(a) => a.push(Some(&parse_bool(s)?)),
+            Self::Int32(a) => {
+                let v = s.trim().parse::<i32>().map_err(|e| ConvertError::ParseInt(s.into(), e))?;
+                a.push(Some(&v))
+            }
+            Self::Utf8(a) => a.push(Some(s)),
+            Self::Timestamp(a) => a.push(Some(&timestamp::parse_timestamp(s)?)),
+            Self::Interval(a) => a.push(Some(&timestamp::parse_interval(s, None)?)),
         }
         Ok(())
     }
@@ -158,6 +202,8 @@ impl ArrayBuilderImpl {
             (Self::Bool(builder), ArrayImpl::Bool(array)) => builder.append(array),
             (Self::Int32(builder), ArrayImpl::Int32(arr)) => builder.append(arr),
             (Self::Utf8(builder), ArrayImpl::Utf8(arr)) => builder.append(arr),
+            (Self::Timestamp(builder), ArrayImpl::Timestamp(arr)) => builder.append(arr),
+            (Self::Interval(builder), ArrayImpl::Interval(arr)) => builder.append(arr),
             _ => panic!("failed to push value: type mismatch"),
         }
     }
@@ -167,6 +213,8 @@ impl ArrayBuilderImpl {
             ArrayBuilderImpl::Bool(a) => ArrayImpl::Bool(a.finish()),
             ArrayBuilderImpl::Int32(a) => ArrayImpl::Int32(a.finish()),
             ArrayBuilderImpl::Utf8(a) => ArrayImpl::Utf8(a.finish()),
+            ArrayBuilderImpl::Timestamp(a) => ArrayImpl::Timestamp(a.finish()),
+            ArrayBuilderImpl::Interval(a) => ArrayImpl::Interval(a.finish()),
         }
     }
 
@@ -182,6 +230,8 @@ impl ArrayBuilderImpl {
             ArrayBuilderImpl::Utf8(a) => {
                 ArrayImpl::Utf8(a.take().into())
             }
+            ArrayBuilderImpl::Timestamp(a) => ArrayImpl::Timestamp(a.take()),
+            ArrayBuilderImpl::Interval(a) => ArrayImpl::Interval(a.take()),
         }
     }
 
@@ -196,6 +246,8 @@ impl ArrayBuilderImpl {
             ArrayBuilderImpl::Utf8(a) => {
                 a.reserve(capacity)
             }
+            ArrayBuilderImpl::Timestamp(a) => a.reserve(capacity),
+            ArrayBuilderImpl::Interval(a) => a.reserve(capacity),
         }
     }
 }
@@ -214,7 +266,15 @@ impl ArrayImpl {
             ArrayImpl::Utf8(a) => match a.get(idx) {
                 Some(val) => DataValue::String(val.to_string()),
                 None => DataValue::Null,
-            }
+            },
+            ArrayImpl::Timestamp(a) => match a.get(idx) {
+                Some(val) => DataValue::Timestamp(*val),
+                None => DataValue::Null,
+            },
+            ArrayImpl::Interval(a) => match a.get(idx) {
+                Some(val) => DataValue::Interval(*val),
+                None => DataValue::Null,
+            },
         }
     }
 
@@ -223,12 +283,31 @@ impl ArrayImpl {
             ArrayImpl::Bool(a) => a.len(),
             ArrayImpl::Int32(a) => a.len(),
             ArrayImpl::Utf8(a) => a.len(),
+            ArrayImpl::Timestamp(a) => a.len(),
+            ArrayImpl::Interval(a) => a.len(),
         }
     }
 
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
+
+    /// Creates an array of `len` nulls.
+    pub fn new_null(ty: &DataType, len: usize) -> Self {
+        let mut builder = ArrayBuilderImpl::with_capacity(len, ty);
+        builder.push_n(len, &DataValue::Null);
+        builder.finish()
+    }
+
+    pub fn data_type(&self) -> DataType {
+        match self {
+            ArrayImpl::Bool(_) => DataType::new_nullable(DataTypeKind::Bool),
+            ArrayImpl::Int32(_) => DataType::new_nullable(DataTypeKind::Int32),
+            ArrayImpl::Utf8(_) => DataType::new_nullable(DataTypeKind::String),
+            ArrayImpl::Timestamp(_) => DataType::new_nullable(DataTypeKind::Timestamp),
+            ArrayImpl::Interval(_) => DataType::new_nullable(DataTypeKind::Interval),
+        }
+    }
 }
 
 impl From<&DataValue> for ArrayImpl {
@@ -239,6 +318,8 @@ impl From<&DataValue> for ArrayImpl {
             &DataValue::Bool(v) => Self::Bool([v].into_iter().collect()),
             &DataValue::Int32(v) => Self::Int32([v].into_iter().collect()),
             DataValue::String(v) => Self::Utf8([Some(v)].into_iter().collect()),
+            &DataValue::Timestamp(v) => Self::Timestamp([v].into_iter().collect()),
+            &DataValue::Interval(v) => Self::Interval([v].into_iter().collect()),
         }
     }
 }
@@ -0,0 +1,260 @@
+//! Vectorized operations over [`ArrayImpl`].
+
+use sqlparser::ast::{BinaryOperator, UnaryOperator};
+use crate::array::{Array, ArrayBuilder, ArrayImpl, BoolArray, I32ArrayBuilder};
+use crate::types::ConvertError;
+
+/// Applies `f` on every pair of non-null values, a null on either side yields a null.
+fn binary_op<A, B, O, F>(a: &A, b: &B, f: F) -> O
+where
+    A: Array,
+    B: Array,
+    O: Array,
+    O::Item: Sized,
+    F: Fn(&A::Item, &B::Item) -> O::Item,
+{
+    assert_eq!(a.len(), b.len(), "arrays must have the same length");
+    let mut builder = O::Builder::with_capacity(a.len());
+    for (a, b) in a.iter().zip(b.iter()) {
+        match (a, b) {
+            (Some(a), Some(b)) => builder.push(Some(&f(a, b))),
+            _ => builder.push(None),
+        }
+    }
+    builder.finish()
+}
+
+/// Like [`binary_op`], but `f` may fail.
+fn try_binary_op<A, B, O, F>(a: &A, b: &B, f: F) -> Result<O, ConvertError>
+where
+    A: Array,
+    B: Array,
+    O: Array,
+    O::Item: Sized,
+    F: Fn(&A::Item, &B::Item) -> Result<O::Item, ConvertError>,
+{
+    assert_eq!(a.len(), b.len(), "arrays must have the same length");
+    let mut builder = O::Builder::with_capacity(a.len());
+    for (a, b) in a.iter().zip(b.iter()) {
+        match (a, b) {
+            (Some(a), Some(b)) => builder.push(Some(&f(a, b)?)),
+            _ => builder.push(None),
+        }
+    }
+    Ok(builder.finish())
+}
+
+macro_rules! impl_arith {
+    // `$time_f` computes a timestamp or interval with an interval, if given
+    ($name:ident, $op:literal, $f:expr $(, $time_f:expr)?) => {
+        pub fn $name(&self, other: &Self) -> Result<Self, ConvertError> {
+            Ok(match (self, other) {
+                (Self::Int32(a), Self::Int32(b)) => Self::Int32(try_binary_op(a, b, $f)?),
+                $(
+                (Self::Timestamp(a), Self::Interval(b)) => Self::Timestamp(try_binary_op(a, b, $time_f)?),
+                (Self::Interval(a), Self::Interval(b)) => Self::Interval(try_binary_op(a, b, $time_f)?),
+                )?
+                _ => return Err(ConvertError::NoBinaryOp(
+                    $op.into(),
+                    self.data_type().kind(),
+                    other.data_type().kind())),
+            })
+        }
+    };
+}
+
+macro_rules! impl_cmp {
+    ($name:ident, $op:tt) => {
+        pub fn $name(&self, other: &Self) -> Result<Self, ConvertError> {
+            Ok(match (self, other) {
+                (Self::Bool(a), Self::Bool(b)) => Self::Bool(binary_op(a, b, |a, b| a $op b)),
+                (Self::Int32(a), Self::Int32(b)) => Self::Bool(binary_op(a, b, |a, b| a $op b)),
+                (Self::Utf8(a), Self::Utf8(b)) => Self::Bool(binary_op(a, b, |a, b| a $op b)),
+                (Self::Timestamp(a), Self::Timestamp(b)) => Self::Bool(binary_op(a, b, |a, b| a $op b)),
+                (Self::Interval(a), Self::Interval(b)) => Self::Bool(binary_op(a, b, |a, b| a $op b)),
+                _ => return Err(ConvertError::NoBinaryOp(
+                    stringify!($op).into(),
+                    self.data_type().kind(),
+                    other.data_type().kind())),
+            })
+        }
+    };
+}
+
+impl ArrayImpl {
+
+    pub fn binary_op(&self, op: &BinaryOperator, other: &ArrayImpl) -> Result<ArrayImpl, ConvertError> {
+        use BinaryOperator::*;
+        match op {
+            Plus => self.add(other),
+            Minus => self.sub(other),
+            Multiply => self.mul(other),
+            Divide => self.div(other),
+            Modulo => self.rem(other),
+            Eq => self.eq(other),
+            NotEq => self.ne(other),
+            Gt => self.gt(other),
+            Lt => self.lt(other),
+            GtEq => self.ge(other),
+            LtEq => self.le(other),
+            And => self.and(other),
+            Or => self.or(other),
+            _ => Err(ConvertError::NoBinaryOp(
+                op.to_string(),
+                self.data_type().kind(),
+                other.data_type().kind())),
+        }
+    }
+
+    pub fn unary_op(&self, op: &UnaryOperator) -> Result<ArrayImpl, ConvertError> {
+        use UnaryOperator::*;
+        match op {
+            Plus => Ok(self.clone()),
+            Minus => self.neg(),
+            Not => self.not(),
+            _ => Err(ConvertError::NoUnaryOp(op.to_string(), self.data_type().kind())),
+        }
+    }
+
+    impl_arith!(add, "+", |a: &i32, b: &i32| a.checked_add(*b).ok_or(ConvertError::Overflow),
+                |a: &i64, b: &i64| a.checked_add(*b).ok_or(ConvertError::Overflow));
+    impl_arith!(sub, "-", |a: &i32, b: &i32| a.checked_sub(*b).ok_or(ConvertError::Overflow),
+                |a: &i64, b: &i64| a.checked_sub(*b).ok_or(ConvertError::Overflow));
+    impl_arith!(mul, "*", |a: &i32, b: &i32| a.checked_mul(*b).ok_or(ConvertError::Overflow));
+    impl_arith!(div, "/", |a: &i32, b: &i32| match b {
+        0 => Err(ConvertError::DivisionByZero),
+        _ => a.checked_div(*b).ok_or(ConvertError::Overflow),
+    });
+    impl_arith!(rem, "%", |a: &i32, b: &i32| match b {
+        0 => Err(ConvertError::DivisionByZero),
+        _ => a.checked_rem(*b).ok_or(ConvertError::Overflow),
+    });
+
+    pub fn neg(&self) -> Result<Self, ConvertError> {
+        let Self::Int32(a) = self else {
+            return Err(ConvertError::NoUnaryOp("-".into(), self.data_type().kind()));
+        };
+        let mut builder = I32ArrayBuilder::with_capacity(a.len());
+        for v in a.iter() {
+            match v {
+                Some(v) => builder.push(Some(&v.checked_neg().ok_or(ConvertError::Overflow)?)),
+                None => builder.push(None),
+            }
+        }
+        Ok(Self::Int32(builder.finish()))
+    }
+
+    impl_cmp!(eq, ==);
+    impl_cmp!(ne, !=);
+    impl_cmp!(gt, >);
+    impl_cmp!(lt, <);
+    impl_cmp!(ge, >=);
+    impl_cmp!(le, <=);
+
+    /// Logical `AND` with three-valued logic: `false AND NULL` is `false`.
+    pub fn and(&self, other: &Self) -> Result<Self, ConvertError> {
+        let (Self::Bool(a), Self::Bool(b)) = (self, other) else {
+            return Err(ConvertError::NoBinaryOp(
+                "and".into(), self.data_type().kind(), other.data_type().kind()));
+        };
+        let array: BoolArray = a.iter().zip(b.iter())
+            .map(|(a, b)| match (a.copied(), b.copied()) {
+                (Some(false), _) | (_, Some(false)) => Some(false),
+                (Some(true), Some(true)) => Some(true),
+                _ => None,
+            })
+            .collect();
+        Ok(Self::Bool(array))
+    }
+
+    /// Logical `OR` with three-valued logic: `true OR NULL` is `true`.
+    pub fn or(&self, other: &Self) -> Result<Self, ConvertError> {
+        let (Self::Bool(a), Self::Bool(b)) = (self, other) else {
+            return Err(ConvertError::NoBinaryOp(
+                "or".into(), self.data_type().kind(), other.data_type().kind()));
+        };
+        let array: BoolArray = a.iter().zip(b.iter())
+            .map(|(a, b)| match (a.copied(), b.copied()) {
+                (Some(true), _) | (_, Some(true)) => Some(true),
+                (Some(false), Some(false)) => Some(false),
+                _ => None,
+            })
+            .collect();
+        Ok(Self::Bool(array))
+    }
+
+    pub fn not(&self) -> Result<Self, ConvertError> {
+        let Self::Bool(a) = self else {
+            return Err(ConvertError::NoUnaryOp("not".into(), self.data_type().kind()));
+        };
+        Ok(Self::Bool(a.iter().map(|v| v.map(|v| !v)).collect()))
+    }
+
+    /// Returns the rows in the given `range`.
+    pub fn slice(&self, range: std::ops::Range<usize>) -> Self {
+        match self {
+            Self::Bool(a) => Self::Bool(a.slice(range)),
+            Self::Int32(a) => Self::Int32(a.slice(range)),
+            Self::Utf8(a) => Self::Utf8(a.slice(range)),
+            Self::Timestamp(a) => Self::Timestamp(a.slice(range)),
+            Self::Interval(a) => Self::Interval(a.slice(range)),
+        }
+    }
+
+    /// Returns the rows of which the corresponding `visibility` is true.
+    pub fn filter(&self, visibility: impl Iterator<Item = bool>) -> Self {
+        match self {
+            Self::Bool(a) => Self::Bool(a.filter(visibility)),
+            Self::Int32(a) => Self::Int32(a.filter(visibility)),
+            Self::Utf8(a) => Self::Utf8(a.filter(visibility)),
+            Self::Timestamp(a) => Self::Timestamp(a.filter(visibility)),
+            Self::Interval(a) => Self::Interval(a.filter(visibility)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::array::I32Array;
+    use crate::types::DataValue;
+
+    fn values(array: &ArrayImpl) -> Vec<DataValue> {
+        (0..array.len()).map(|i| array.get(i)).collect()
+    }
+
+    #[test]
+    fn test_cmp_and_logic() {
+        let a = ArrayImpl::Int32([Some(1), None, Some(3)].into_iter().collect::<I32Array>());
+        let b = ArrayImpl::Int32([2, 2, 2].into_iter().collect::<I32Array>());
+        let gt = a.gt(&b).unwrap();
+        assert_eq!(values(&gt), vec![DataValue::Bool(false), DataValue::Null, DataValue::Bool(true)]);
+
+        let t = ArrayImpl::Bool([true, true, true].into_iter().collect());
+        let f = ArrayImpl::Bool([false, false, false].into_iter().collect());
+        assert_eq!(values(&gt.and(&f).unwrap()), values(&f));
+        assert_eq!(values(&gt.or(&t).unwrap()), values(&t));
+        assert_eq!(values(&gt.and(&t).unwrap()), values(&gt));
+        assert_eq!(values(&gt.not().unwrap()),
+                   vec![DataValue::Bool(true), DataValue::Null, DataValue::Bool(false)]);
+    }
+
+    #[test]
+    fn test_arith() {
+        let a = ArrayImpl::Int32([Some(7), None, Some(-3)].into_iter().collect::<I32Array>());
+        let b = ArrayImpl::Int32([2, 2, 2].into_iter().collect::<I32Array>());
+        assert_eq!(values(&a.add(&b).unwrap()),
+                   vec![DataValue::Int32(9), DataValue::Null, DataValue::Int32(-1)]);
+        assert_eq!(values(&a.div(&b).unwrap()),
+                   vec![DataValue::Int32(3), DataValue::Null, DataValue::Int32(-1)]);
+        assert_eq!(values(&a.rem(&b).unwrap()),
+                   vec![DataValue::Int32(1), DataValue::Null, DataValue::Int32(-1)]);
+        assert_eq!(values(&a.neg().unwrap()),
+                   vec![DataValue::Int32(-7), DataValue::Null, DataValue::Int32(3)]);
+
+        let zero = ArrayImpl::Int32([0, 0, 0].into_iter().collect::<I32Array>());
+        assert_eq!(a.div(&zero).err(), Some(ConvertError::DivisionByZero));
+        let max = ArrayImpl::Int32([i32::MAX, 0, 0].into_iter().collect::<I32Array>());
+        assert_eq!(max.add(&b).err(), Some(ConvertError::Overflow));
+    }
+}
@@ -2,7 +2,9 @@ use std::collections::{BTreeMap, HashSet};
 use std::fmt::{Display, Formatter};
 use std::hash::Hash;
 use std::str::FromStr;
-use sqlparser::ast::{ColumnDef, SqlOption, Value};
+use sqlparser::ast::{BinaryOperator, ColumnDef, Expr, SqlOption, TableConstraint};
+use crate::parser::WATERMARK_CONSTRAINT_PREFIX;
+use crate::types::DataTypeKind;
 use super::*;
 
 use std::result::Result as RawResult;
@@ -14,7 +16,9 @@ pub struct CreateTable {
     pub schema_id: SchemaId,
     pub table_name: String,
     pub columns: Vec<(String, ColumnDesc)>,
-    pub options: BTreeMap<String, String>
+    pub options: BTreeMap<String, String>,
+    /// The watermark column and its delay in milliseconds.
+    pub watermark: Option<(String, i64)>,
 }
 
 impl Display for CreateTable {
@@ -28,11 +32,16 @@ impl CreateTable {
     pub fn pretty_table<'a>(&self) -> Vec<(&'a str, Pretty<'a>)> {
         let cols = Pretty::Array(self.columns.iter().map(|(c, cd)| self.pretty_column(c,
         cd)).collect());
-        vec![
+        let mut fields = vec![
             ("schema_id", Pretty::display(&self.schema_id)),
             ("name", Pretty::display(&self.table_name)),
             ("columns", cols),
-        ]
+        ];
+        if let Some((column, delay)) = &self.watermark {
+            fields.push(("watermark", Pretty::display(&format!(
+                "{} - {}", column, crate::types::timestamp::format_interval(*delay)))));
+        }
+        fields
     }
 
     fn pretty_column<'a>(&self, name: &String, column_desc: &ColumnDesc) -> Pretty<'a> {
@@ -63,6 +72,7 @@ impl Binder {
         &mut self,
         name: ObjectName,
         columns: Vec<ColumnDef>,
+        constraints: Vec<TableConstraint>,
         with_options: Vec<SqlOption>) -> Result {
         // check empty columns
         if columns.is_empty() {
@@ -84,7 +94,20 @@ impl Binder {
             }
         }
 
-        let columns = columns
+        let mut watermark = None;
+        for constraint in constraints {
+            if let TableConstraint::Check { name: Some(name), expr } = constraint {
+                if let Some(column) = name.value.strip_prefix(WATERMARK_CONSTRAINT_PREFIX) {
+                    if watermark.is_some() {
+                        return Err(BindError::InvalidWatermark(
+                            "only one watermark is allowed".into()));
+                    }
+                    watermark = Some(self.bind_watermark(column, *expr, &columns)?);
+                }
+            }
+        }
+
+        let columns: Vec<_> = columns
             .iter()
             .map(|col| (col.name.value.clone(), ColumnDesc::from(col)))
             .collect();
@@ -97,6 +120,11 @@ impl Binder {
                                val.to_string());
             }
         }
+        let column_types: Vec<_> = columns.iter()
+            .map(|(name, desc)| (name.clone(), desc.datatype().clone()))
+            .collect();
+        self.connectors.validate(&options.clone().into_iter().collect(), &column_types)
+            .map_err(BindError::InvalidConnector)?;
 
         let create = self.egraph.add(Node::CreateTable(
             CreateTable {
@@ -104,8 +132,37 @@ impl Binder {
                 table_name: table_name.into(),
                 columns,
                 options,
+                watermark,
             }
         ));
         Ok(create)
     }
+
+    /// Binds `WATERMARK FOR <column> AS <expr>`, where the expression must be either
+    /// `<column>` or `<column> - <interval>`, and returns the column with the delay.
+    fn bind_watermark(&mut self, column: &str, expr: Expr, columns: &[ColumnDef])
+        -> Result<(String, i64)> {
+        let column_def = columns.iter()
+            .find(|c| c.name.value == column)
+            .ok_or_else(|| BindError::ColumnNotFound(column.into()))?;
+        if ColumnDesc::from(column_def).datatype().kind() != DataTypeKind::Timestamp {
+            return Err(BindError::InvalidWatermark(
+                format!("the watermark column {column} must be type timestamp")));
+        }
+        let is_column = |expr: &Expr| matches!(expr, Expr::Identifier(ident) if ident.value == column);
+        let invalid = |expr: &Expr| BindError::InvalidWatermark(
+            format!("expected `{column}` or `{column} - INTERVAL ..`, but got `{expr}`"));
+        let delay = match &expr {
+            expr if is_column(expr) => 0,
+            Expr::BinaryOp { left, op: BinaryOperator::Minus, right } if is_column(left) => {
+                let delay = self.bind_expr(*right.clone())?;
+                match self.node(delay) {
+                    Node::Constant(DataValue::Interval(v)) if *v >= 0 => *v,
+                    _ => return Err(invalid(&expr)),
+                }
+            }
+            _ => return Err(invalid(&expr)),
+        };
+        Ok((column.into(), delay))
+    }
 }
\ No newline at end of file
@@ -1,7 +1,10 @@
-use sqlparser::ast::{Expr, Function, FunctionArg, FunctionArgExpr};
+use sqlparser::ast::{BinaryOperator, DataType, DateTimeField, Expr, Function, FunctionArg, FunctionArgExpr, ObjectName,
+                     UnaryOperator, Value, WindowSpec};
 use crate::binder::Binder;
 
-use crate::binder::{Result, Node};
+use crate::binder::{BindError, Result, Node};
+use crate::types::DataValue;
+use crate::types::timestamp::{parse_interval, parse_timestamp};
 
 
 impl Binder {
@@ -22,13 +25,95 @@ impl Binder {
                 self.bind_colum_ref(&idents),
             Expr::Function(func) =>
                 self.bind_function(func),
-            _ => todo!("bind expression: {:?}", expr),
+            Expr::BinaryOp { left, op, right } =>
+                self.bind_binary_op(*left, op, *right),
+            Expr::UnaryOp { op, expr } =>
+                self.bind_unary_op(op, *expr),
+            Expr::Nested(expr) => self.bind_expr(*expr),
+            Expr::Between { expr, negated, low, high } =>
+                self.bind_between(*expr, negated, *low, *high),
+            Expr::Interval { value, leading_field, .. } =>
+                self.bind_interval(*value, leading_field),
+            Expr::TypedString { data_type, value } =>
+                self.bind_typed_string(data_type, value),
+            _ => Err(BindError::InvalidExpression(format!("unsupported expression {}", expr))),
         }?;
         self.check_type(id)?;
         Ok(id)
     }
 
+    fn bind_binary_op(&mut self, left: Expr, op: BinaryOperator, right: Expr) -> Result {
+        use BinaryOperator::*;
+        let l = self.bind_expr(left)?;
+        let r = self.bind_expr(right)?;
+        let node = match op {
+            Plus => Node::Add([l, r]),
+            Minus => Node::Sub([l, r]),
+            Multiply => Node::Mul([l, r]),
+            Divide => Node::Div([l, r]),
+            Modulo => Node::Mod([l, r]),
+            Eq => Node::Eq([l, r]),
+            NotEq => Node::NotEq([l, r]),
+            Gt => Node::Gt([l, r]),
+            Lt => Node::Lt([l, r]),
+            GtEq => Node::GtEq([l, r]),
+            LtEq => Node::LtEq([l, r]),
+            And => Node::And([l, r]),
+            Or => Node::Or([l, r]),
+            _ => return Err(BindError::InvalidExpression(format!("unsupported operator {}", op))),
+        };
+        Ok(self.egraph.add(node))
+    }
+
+    /// Binds `expr BETWEEN low AND high` as `expr >= low AND expr <= high`.
+    fn bind_between(&mut self, expr: Expr, negated: bool, low: Expr, high: Expr) -> Result {
+        let expr = self.bind_expr(expr)?;
+        let low = self.bind_expr(low)?;
+        let high = self.bind_expr(high)?;
+        let low = self.egraph.add(Node::GtEq([expr, low]));
+        let high = self.egraph.add(Node::LtEq([expr, high]));
+        let between = self.egraph.add(Node::And([low, high]));
+        Ok(match negated {
+            true => self.egraph.add(Node::Not(between)),
+            false => between,
+        })
+    }
+
+    fn bind_unary_op(&mut self, op: UnaryOperator, expr: Expr) -> Result {
+        use UnaryOperator::*;
+        let expr = self.bind_expr(expr)?;
+        let node = match op {
+            Plus => return Ok(expr),
+            Minus => Node::Neg(expr),
+            Not => Node::Not(expr),
+            _ => return Err(BindError::InvalidExpression(format!("unsupported operator {}", op))),
+        };
+        Ok(self.egraph.add(node))
+    }
+
+    fn bind_interval(&mut self, value: Expr, leading_field: Option<DateTimeField>) -> Result {
+        let value = match value {
+            Expr::Value(Value::SingleQuotedString(s) | Value::Number(s, _)) => s,
+            _ => return Err(BindError::InvalidExpression(format!("invalid interval: {value}"))),
+        };
+        let interval = parse_interval(&value, leading_field.as_ref())?;
+        Ok(self.egraph.add(Node::Constant(DataValue::Interval(interval))))
+    }
+
+    fn bind_typed_string(&mut self, data_type: DataType, value: String) -> Result {
+        let value = match data_type {
+            DataType::Timestamp(_, _) => DataValue::Timestamp(parse_timestamp(&value)?),
+            DataType::Interval => DataValue::Interval(parse_interval(&value, None)?),
+            _ => return Err(BindError::InvalidExpression(
+                format!("unsupported typed string {} '{}'", data_type, value))),
+        };
+        Ok(self.egraph.add(Node::Constant(value)))
+    }
+
     fn bind_function(&mut self, func: Function) -> Result {
+        if let Some(window) = func.over {
+            return self.bind_window_function(&func.name, func.args, window);
+        }
         let mut args = vec![];
         for arg in func.args {
             // ignore argument name
@@ -55,4 +140,58 @@ impl Binder {
         let id = self.egraph.add(node);
         Ok(id)
     }
+
+    /// Binds a window function, of which only `ROW_NUMBER()` is supported.
+    ///
+    /// # Example
+    /// - `ROW_NUMBER() OVER (PARTITION BY a ORDER BY b DESC)` =>
+    ///   `(over row_number (list $1.1) (list (desc $1.2)))`
+    fn bind_window_function(&mut self, name: &ObjectName, args: Vec<FunctionArg>, window: WindowSpec) -> Result {
+        let function = match name.to_string().to_lowercase().as_str() {
+            "row_number" if args.is_empty() => Node::RowNumber,
+            _ => return Err(BindError::InvalidExpression(format!("unsupported window function: {}", name))),
+        };
+        if window.window_frame.is_some() {
+            return Err(BindError::InvalidExpression("window frames are not supported".into()));
+        }
+        let function = self.egraph.add(function);
+        let partition_keys = self.bind_exprs(window.partition_by)?;
+        let order_keys = self.bind_orderby(window.order_by)?;
+        Ok(self.egraph.add(Node::Over([function, partition_keys, order_keys])))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use crate::catalog::{DatabaseCatalog, DEFAULT_SCHEMA_NAME};
+    use crate::parser::parse;
+    use crate::types::{DataType, DataTypeKind};
+    use super::*;
+
+    /// Binds a statement over the table `t(a int)`.
+    fn bind(sql: &str) -> Result<crate::planner::RecExpr> {
+        let catalog = Arc::new(DatabaseCatalog::new());
+        let schema = catalog.get_schema_by_name(DEFAULT_SCHEMA_NAME).unwrap();
+        let table = schema.get_table(schema.add_table("t").unwrap()).unwrap();
+        table.add_column("a", DataType::new(DataTypeKind::Int32, true).to_column()).unwrap();
+        let mut binder = Binder::new(catalog);
+        binder.bind(parse(sql).unwrap().remove(0))
+    }
+
+    #[test]
+    fn test_unsupported_operator() {
+        assert!(bind("select a + 2 * 3 from t").is_ok());
+        for sql in ["select 'a' || 'b' from t", "select a & 2 from t", "select ~a from t", "select 'a' like 'b' from t"] {
+            assert!(matches!(bind(sql), Err(BindError::InvalidExpression(_))), "{}", sql);
+        }
+    }
+
+    #[test]
+    fn test_typed_string() {
+        assert!(bind("select a from t where TIMESTAMP '2024-01-01 00:00:00' > TIMESTAMP '2023-01-01 00:00:00'").is_ok());
+        for sql in ["select DATE '2024-01-01' from t", "select TIME '12:00:00' from t"] {
+            assert!(matches!(bind(sql), Err(BindError::InvalidExpression(_))), "{}", sql);
+        }
+    }
 }
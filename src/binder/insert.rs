@@ -10,6 +10,7 @@ impl Binder {
                        table_name: ObjectName,
                        columns: Vec<Ident>, source: Box<Query>) -> Result {
 
+        self.check_connector(&table_name, true)?;
         let table = self.bind_table_id(&table_name)?;
         let cols =
             self.bind_table_columns(&table_name, &columns)?;
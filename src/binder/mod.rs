@@ -7,11 +7,13 @@ mod column_ref;
 mod drop;
 
 use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 use egg::{Id};
 use sqlparser::ast::{Ident, ObjectName, Statement, Value};
 use crate::planner::{Expr as Node, RecExpr, TypeError, TypeSchemaAnalysis};
 use crate::catalog::*;
-use crate::types::DataValue;
+use crate::connector::{ConnectorRegistry, ConnectorRegistryRef};
+use crate::types::{ConvertError, DataValue};
 pub use crate::binder::create_table::CreateTable;
 pub use crate::binder::drop::*;
 
@@ -78,6 +80,32 @@ pub enum BindError {
 
     #[error("column {0} must appear in the GROUP BY clause or be used in an aggregate function")]
     ColumnNotInAgg(String),
+    #[error("conversion error: {0}")]
+    Convert(#[from] ConvertError),
+    #[error("invalid table function: {0}")]
+    InvalidTableFunction(String),
+    #[error("invalid watermark: {0}")]
+    InvalidWatermark(String),
+    #[error("column {0} is not a time attribute, which must be defined with WATERMARK FOR")]
+    NotTimeAttribute(String),
+    #[error("invalid connector: {0}")]
+    InvalidConnector(String),
+    #[error("can't insert into table {0}, whose connector is source-only")]
+    SourceOnlyTable(String),
+    #[error("can't select from table {0}, whose connector is sink-only")]
+    SinkOnlyTable(String),
+    #[error("unsupported query: {0}")]
+    UnsupportedQuery(String),
+    #[error("unsupported join: {0}")]
+    UnsupportedJoin(String),
+    #[error("ORDER BY without LIMIT is only supported over bounded tables")]
+    UnboundedSort,
+    #[error("LIMIT without ORDER BY is not supported over inputs with retractions")]
+    RetractingLimit,
+    #[error("invalid lookup join: {0}")]
+    InvalidLookupJoin(String),
+    #[error("can't look up table {0}, whose connector doesn't support lookups")]
+    NotLookupTable(String),
 }
 
 type TableName = String;
@@ -87,6 +115,7 @@ pub struct Binder {
     contexts: Vec<BinderContext>,
     tables: HashMap<TableName, TableRefId>,
     egraph: egg::EGraph<Node, TypeSchemaAnalysis>,
+    connectors: ConnectorRegistryRef,
 }
 
 #[derive(Debug, Default)]
@@ -112,10 +141,17 @@ impl Binder {
             tables: HashMap::default(),
             egraph: egg::EGraph::new(TypeSchemaAnalysis {
                 catalog: catalog.clone()
-            })
+            }),
+            connectors: Arc::new(ConnectorRegistry::new()),
         }
     }
 
+    /// Validates the options of `CREATE TABLE` with the factories of the registry.
+    pub fn with_connectors(mut self, connectors: ConnectorRegistryRef) -> Self {
+        self.connectors = connectors;
+        self
+    }
+
     fn current_ctx(&self) -> &BinderContext {
         self.contexts.last().unwrap()
     }
@@ -150,6 +186,11 @@ impl Binder {
         let id = self.bind_stmt(stmt)?;
         let extractor = egg::Extractor::new(&self.egraph, egg::AstSize);
         let (_, best) = extractor.find_best(id);
+        // window functions are only planned as the Top-N filtered by the row number
+        if best.as_ref().iter().any(|node| matches!(node, Node::Over(_))) {
+            return Err(BindError::InvalidExpression(
+                "ROW_NUMBER() is only supported in a subquery filtered by rn = n, rn <= n or rn < n".into()));
+        }
         Ok(best)
     }
 
@@ -164,9 +205,9 @@ impl Binder {
     fn bind_stmt(&mut self, stmt: Statement) -> Result {
         match stmt {
             Statement::CreateTable {
-                name, columns , with_options,
+                name, columns, constraints, with_options,
                 ..
-            } => self.bind_create_table(name, columns, with_options),
+            } => self.bind_create_table(name, columns, constraints, with_options),
             Statement::Drop {
                 object_type,
                 if_exists,
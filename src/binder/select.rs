@@ -6,6 +6,7 @@ use crate::planner::{Expr as Node};
 use egg::Language;
 use sqlparser::ast::{Expr, OrderByExpr, Query, Select, SelectItem, SetExpr, TableWithJoins, Values};
 use super::*;
+use crate::types::DataTypeKind;
 
 
 impl Binder {
@@ -17,25 +18,48 @@ impl Binder {
     }
 
     pub(in crate::binder) fn bind_query_internal(&mut self, query: Query) -> Result {
-        let child = match *query.body {
-            SetExpr::Select(select) => self.bind_insert_select_from(*select, query.order_by)?,
-            SetExpr::Values(values) => self.bind_values(values)?,
-            _ => todo!("handle query: "),
-        };
         let limit = match query.limit {
             None => self.egraph.add(Node::null()),
-            Some(expr) =>  self.bind_expr(expr)?
+            Some(expr) =>  self.bind_limit_offset(expr, "LIMIT")?
         };
         let offset = match query.offset {
             None => self.egraph.add(Node::zero()),
-            Some(offset) =>  self.bind_expr(offset.value)?,
+            Some(offset) =>  self.bind_limit_offset(offset.value, "OFFSET")?,
         };
-        Ok(self.egraph.add(Node::Limit([limit, offset, child])))
+        match *query.body {
+            SetExpr::Select(select) => self.bind_insert_select_from(*select, query.order_by, limit, offset),
+            SetExpr::Values(values) => {
+                let child = self.bind_values(values)?;
+                Ok(self.egraph.add(Node::Limit([limit, offset, child])))
+            }
+            body => Err(BindError::UnsupportedQuery(body.to_string())),
+        }
     }
 
+    /// Binds the argument of `LIMIT` or `OFFSET`, which must be a non-negative integer constant.
+    fn bind_limit_offset(&mut self, expr: Expr, clause: &str) -> Result {
+        let id = self.bind_expr(expr)?;
+        match self.node(id) {
+            Node::Constant(v) if v.as_usize().is_ok() => Ok(id),
+            _ => Err(BindError::InvalidExpression(
+                format!("argument of {clause} must be a non-negative integer"))),
+        }
+    }
+
+    /// Binds a `SELECT` with its `ORDER BY`, `LIMIT` and `OFFSET`.
+    ///
+    /// `ORDER BY` with `LIMIT` is a Top-N, and `ORDER BY` without `LIMIT` is a sort,
+    /// which is only allowed over bounded tables as it emits the rows once all are read.
+    /// `DISTINCT` deduplicates the projected rows before they're ordered.
+    ///
+    /// # Example
+    /// - `SELECT a FROM t ORDER BY b LIMIT 10` =>
+    ///   `(proj (list $1.1) (topn 10 0 (list (asc $1.2)) (list) (filter true (scan $1 (list ..) null))))`
+    /// - `SELECT DISTINCT a FROM t` =>
+    ///   `(limit null 0 (distinct (proj (list $1.1) (filter true (scan $1 (list ..) null)))))`
     pub fn bind_insert_select_from(
         &mut self,
-        select_stmt: Select, order_by: Vec<OrderByExpr>) -> Result {
+        select_stmt: Select, order_by: Vec<OrderByExpr>, limit: Id, offset: Id) -> Result {
         let from = self.bind_from(select_stmt.from)?;
         let proj = self.bind_proj(select_stmt.projection, from)?;
         let where_ = self.bind_where(select_stmt.selection)?;
@@ -48,19 +72,171 @@ impl Binder {
             false => self.egraph.add(Node::List([].into())),
         };
 
-        let mut plan = self.egraph.add(Node::Filter([where_, from]));
         let mut to_rewrite = [proj, distinct, having, orderby];
+        let (where_, from) = self.plan_row_number(where_, from, &mut to_rewrite)?;
+        let mut plan = self.egraph.add(Node::Filter([where_, from]));
         plan = self.plan_agg(&mut to_rewrite, groupby, plan)?;
         let [proj, distinct, having, orderby] = to_rewrite;
 
-        plan = self.egraph.add(Node::Proj([proj, plan]));
+        let distinct = !self.node(distinct).as_list().is_empty();
+        if distinct {
+            for key in self.node(orderby).as_list() {
+                let (Node::Asc(expr) | Node::Desc(expr)) = self.node(*key) else {
+                    unreachable!("not an order key");
+                };
+                if !self.node(proj).as_list().contains(expr) {
+                    return Err(BindError::InvalidExpression(
+                        "for SELECT DISTINCT, ORDER BY expressions must appear in select list".into()));
+                }
+            }
+            plan = self.egraph.add(Node::Proj([proj, plan]));
+            plan = self.egraph.add(Node::Distinct(plan));
+        }
+
+        let ordered = !self.node(orderby).as_list().is_empty();
+        if ordered && self.node(limit) != &Node::null() {
+            let partition_keys = self.egraph.add(Node::List([].into()));
+            plan = self.egraph.add(Node::TopN([limit, offset, orderby, partition_keys, plan]));
+        } else if ordered {
+            if !self.is_bounded(plan) {
+                return Err(BindError::UnboundedSort);
+            }
+            plan = self.egraph.add(Node::Order([orderby, plan]));
+        }
+
+        if !distinct {
+            plan = self.egraph.add(Node::Proj([proj, plan]));
+        }
+        if !ordered || self.node(limit) == &Node::null() {
+            // the rows retracted may not be the ones emitted
+            let limited = self.node(limit) != &Node::null() || self.node(offset) != &Node::zero();
+            if limited && !crate::planner::is_append_only(&self.egraph, plan) {
+                return Err(BindError::RetractingLimit);
+            }
+            plan = self.egraph.add(Node::Limit([limit, offset, plan]));
+        }
         Ok(plan)
     }
 
+    /// Plans a filter of the row number of a subquery in `FROM` as a Top-N of each partition,
+    /// returning the rest of the filter and the plan of `FROM`:
+    /// - `rn = n` keeps the n-th row of each partition, which deduplicates the rows when n is 1
+    /// - `rn <= n` or `rn < n` keeps the first rows of each partition, whose row numbers can't be selected
+    ///
+    /// The row number in `exprs` and the rest of the filter is rewritten to the constant.
+    ///
+    /// # Example
+    /// - `SELECT k, v FROM (SELECT k, v, ROW_NUMBER() OVER (PARTITION BY k ORDER BY ts DESC) AS rn FROM t)
+    ///    WHERE rn = 1` =>
+    ///   `(proj (list $1.1 $1.2) (filter true (proj (list $1.1 $1.2)
+    ///     (topn 1 0 (list (desc $1.3)) (list $1.1) (filter true (scan $1 (list ..) null))))))`
+    fn plan_row_number(&mut self, where_: Id, from: Id, exprs: &mut [Id]) -> Result<(Id, Id)> {
+        let subquery = match *self.node(from) {
+            Node::Limit([limit, offset, child]) if self.node(limit) == &Node::null()
+                && self.node(offset) == &Node::zero() => child,
+            _ => from,
+        };
+        let Node::Proj([list, child]) = *self.node(subquery) else {
+            return Ok((where_, from));
+        };
+        let Some(&row_number) = self.node(list).as_list().iter()
+            .find(|id| matches!(self.node(**id), Node::Over(_))) else {
+            return Ok((where_, from));
+        };
+        let Node::Over([_, partition_keys, order_keys]) = *self.node(row_number) else {
+            unreachable!("not a window function");
+        };
+
+        let mut conjuncts = vec![];
+        let mut stack = vec![where_];
+        while let Some(id) = stack.pop() {
+            match *self.node(id) {
+                Node::And([a, b]) => stack.extend([b, a]),
+                _ => conjuncts.push(id),
+            }
+        }
+        let Some(pos) = conjuncts.iter().position(|id| self.row_number_range(*id, row_number).is_some()) else {
+            return Err(BindError::InvalidExpression(
+                "ROW_NUMBER() must be filtered by rn = n, rn <= n or rn < n in the outer query".into()));
+        };
+        let (offset, limit) = self.row_number_range(conjuncts.remove(pos), row_number).unwrap();
+
+        let rest = conjuncts.into_iter().reduce(|a, b| self.egraph.add(Node::And([a, b])));
+        let mut where_ = rest.unwrap_or_else(|| self.egraph.add(Node::true_()));
+        let number = match limit {
+            1 => self.egraph.add(Node::Constant(DataValue::Int32(offset as i32 + 1))),
+            _ => self.egraph.add(Node::null()),
+        };
+        for id in exprs.iter_mut().chain([&mut where_]) {
+            *id = self.replace_in_expr(*id, row_number, number);
+            if limit != 1 && self.contains(*id, number) {
+                return Err(BindError::InvalidExpression(
+                    "the row number of a Top-N with more than one row per partition can't be selected".into()));
+            }
+        }
+
+        let [limit, offset] = [limit, offset].map(|v| self.egraph.add(Node::Constant(DataValue::Int32(v as i32))));
+        let plan = self.egraph.add(Node::TopN([limit, offset, order_keys, partition_keys, child]));
+        let list = self.node(list).as_list().iter().copied().filter(|id| *id != row_number).collect();
+        let list = self.egraph.add(Node::List(list));
+        Ok((where_, self.egraph.add(Node::Proj([list, plan]))))
+    }
+
+    /// Returns the offset and limit of the rows kept by a filter of the row number,
+    /// `None` if it's not `rn = n`, `rn <= n` or `rn < n` with a positive `n`.
+    fn row_number_range(&self, cond: Id, row_number: Id) -> Option<(usize, usize)> {
+        let positive = |n: Id| match self.node(n) {
+            Node::Constant(DataValue::Int32(n)) if *n > 0 => Some(*n as usize),
+            _ => None,
+        };
+        match *self.node(cond) {
+            Node::Eq([a, n]) | Node::Eq([n, a]) if a == row_number => positive(n).map(|n| (n - 1, 1)),
+            Node::LtEq([a, n]) | Node::GtEq([n, a]) if a == row_number => positive(n).map(|n| (0, n)),
+            Node::Lt([a, n]) | Node::Gt([n, a]) if a == row_number => positive(n).map(|n| (0, n - 1)),
+            _ => None,
+        }
+        .filter(|(_, limit)| *limit > 0)
+    }
+
+    /// Rewrites the expression `id` with the sub-expression `from` replaced by `to`.
+    fn replace_in_expr(&mut self, id: Id, from: Id, to: Id) -> Id {
+        if id == from {
+            return to;
+        }
+        let mut expr = self.node(id).clone();
+        for child in expr.children_mut() {
+            *child = self.replace_in_expr(*child, from, to);
+        }
+        self.egraph.add(expr)
+    }
+
+    /// Returns true if the expression `id` contains the sub-expression `sub`.
+    fn contains(&self, id: Id, sub: Id) -> bool {
+        id == sub || self.node(id).children().iter().any(|child| self.contains(*child, sub))
+    }
+
+    /// Returns true if all the tables read by the plan have a finite number of rows.
+    fn is_bounded(&self, plan: Id) -> bool {
+        match self.node(plan) {
+            Node::Scan([table, _, _]) => {
+                let table = self.catalog.get_table(self.node(*table).as_table()).unwrap();
+                table.get_option("connector")
+                    .and_then(|connector| self.connectors.get(&connector))
+                    .is_some_and(|factory| factory.is_bounded(&table.get_options()))
+            }
+            // the dimension table is looked up instead of read
+            Node::LookupJoin([_, _, left, _]) => self.is_bounded(*left),
+            node => node.children().iter().all(|child| self.is_bounded(*child)),
+        }
+    }
+
 
     pub(in crate::binder) fn bind_where(&mut self, selection: Option<Expr>) -> Result {
         let id = self.bind_selection(selection)?;
-        // todo!("check where")
+        if self.check_type(id)?.kind() != DataTypeKind::Bool {
+            return Err(BindError::InvalidExpression(
+                "argument of WHERE must be type boolean".into()));
+        }
         Ok(id)
     }
 
@@ -93,15 +269,19 @@ impl Binder {
 
 
 
+    /// Binds the tables of `FROM`, where a comma-separated list of tables is a cross join.
     pub fn bind_from(&mut self, tables: Vec<TableWithJoins>) -> Result {
-        let node = None;
+        let mut node = None;
         for table in tables {
             let table_node = self.bind_table_with_joins(table)?;
-            if let Some(node) = node {
-                unimplemented!("Not support multiple tables")
-            } else {
-                return Ok(table_node);
-            }
+            node = Some(match node {
+                Some(node) => {
+                    let inner = self.egraph.add(Node::Inner);
+                    let true_ = self.egraph.add(Node::true_());
+                    self.egraph.add(Node::Join([inner, true_, node, table_node]))
+                }
+                None => table_node,
+            });
         }
 
         if let Some(node) = node {
@@ -116,10 +296,10 @@ impl Binder {
         for item in projection {
             match item {
                 SelectItem::UnnamedExpr(expr) => {
-                    let ident = if let Expr::Identifier(ident) = &expr {
-                        Some(ident.value.clone())
-                    } else {
-                        None
+                    let ident = match &expr {
+                        Expr::Identifier(ident) => Some(ident.value.to_lowercase()),
+                        Expr::CompoundIdentifier(idents) => Some(idents.last().unwrap().value.to_lowercase()),
+                        _ => None,
                     };
                     let id = self.bind_expr(expr)?;
                     if let Some(ident) = ident {
@@ -127,8 +307,20 @@ impl Binder {
                     }
                     select_list.push(id)
                 },
+                SelectItem::ExprWithAlias { expr, alias } => {
+                    let id = self.bind_expr(expr)?;
+                    self.current_ctx_mut().output_aliases.insert(alias.value.to_lowercase(), id);
+                    select_list.push(id)
+                },
                 SelectItem::Wildcard(_) => {
-                    select_list.append(&mut self.schema(from))
+                    let schema = self.schema(from);
+                    let ctx = self.current_ctx_mut();
+                    for (name, ids) in &ctx.aliases {
+                        for id in ids.values().filter(|id| schema.contains(id)) {
+                            ctx.output_aliases.insert(name.clone(), *id);
+                        }
+                    }
+                    select_list.extend(schema)
                 },
                 _ => todo!("bind select list"),
             }
@@ -214,4 +406,56 @@ impl Binder {
         self.check_type(id)?;
         Ok(id)
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use crate::catalog::{DatabaseCatalog, DEFAULT_SCHEMA_NAME};
+    use crate::parser::parse;
+    use crate::planner::RecExpr;
+    use crate::types::DataType;
+    use super::*;
+
+    /// Binds a statement over the table `t(k int, ts timestamp, v int)`.
+    fn bind(sql: &str) -> Result<RecExpr> {
+        let catalog = Arc::new(DatabaseCatalog::new());
+        let schema = catalog.get_schema_by_name(DEFAULT_SCHEMA_NAME).unwrap();
+        let table = schema.get_table(schema.add_table("t").unwrap()).unwrap();
+        for (name, kind) in [("k", DataTypeKind::Int32), ("ts", DataTypeKind::Timestamp), ("v", DataTypeKind::Int32)] {
+            table.add_column(name, DataType::new(kind, true).to_column()).unwrap();
+        }
+        let mut binder = Binder::new(catalog);
+        binder.bind(parse(sql).unwrap().remove(0))
+    }
+
+    #[test]
+    fn test_row_number() {
+        let dedup = |order| format!(
+            "select k, v from (select k, v, row_number() over (partition by k order by ts {}) as rn from t) \
+             where rn = 1", order);
+        // keeping the first row of each key is a Top-1 of the partition in the ascending order
+        let plan = bind(&dedup("asc")).unwrap().to_string();
+        assert!(plan.contains("(topn 1 0 (list (asc $0.1)) (list $0.0) (filter true (scan $0"), "{}", plan);
+        // and keeping the last row is a Top-1 in the descending order
+        let plan = bind(&dedup("desc")).unwrap().to_string();
+        assert!(plan.contains("(topn 1 0 (list (desc $0.1)) (list $0.0) (filter true (scan $0"), "{}", plan);
+        // the row number of the rows kept is the constant
+        let plan = bind("select k, rn from (select k, row_number() over (partition by k order by ts) as rn from t) \
+                         where rn = 1").unwrap().to_string();
+        assert!(plan.starts_with("(limit NULL 0 (proj (list $0.0 1) (filter true"), "{}", plan);
+    }
+
+    #[test]
+    fn test_invalid_row_number() {
+        for sql in [
+            "select k from (select k, row_number() over (partition by k order by ts) as rn from t) where rn > 1",
+            "select k from (select k, row_number() over (partition by k order by ts) as rn from t) where rn = 0",
+            "select k from (select k, row_number() over (partition by k order by ts) as rn from t) where rn <> 1",
+            "select k from (select k, row_number() over (partition by k order by ts) as rn from t) where k > 1",
+            "select k, rn from (select k, row_number() over (partition by k order by ts) as rn from t) where rn <= 2",
+            "select k, row_number() over (partition by k order by ts) from t",
+        ] {
+            assert!(matches!(bind(sql), Err(BindError::InvalidExpression(_))), "{}", sql);
+        }
+    }
+}
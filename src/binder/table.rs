@@ -1,31 +1,289 @@
-use sqlparser::ast::{Ident, ObjectName, TableAlias, TableFactor, TableWithJoins};
+use sqlparser::ast::{Expr, FunctionArg, FunctionArgExpr, Join, JoinConstraint, JoinOperator,
+                     Query, TableAlias, TableFactor, TableWithJoins};
 use crate::binder::Binder;
+use egg::Language;
+use crate::connector::LookupCacheOptions;
+use crate::parser::SYSTEM_TIME_ARG;
+use crate::types::DataTypeKind;
 use super::*;
 
 impl Binder {
 
     pub(in crate::binder) fn bind_table_factor(&mut self, table: TableFactor) -> Result {
         match table {
+            TableFactor::Table { args: Some(args), .. } if system_time(&args).is_some() => {
+                Err(BindError::InvalidLookupJoin(
+                    "FOR SYSTEM_TIME AS OF is only allowed on the right table of a join".into()))
+            }
+            TableFactor::Table { name, alias, args: Some(args), .. } => {
+                self.bind_window_table_function(&name, alias, args)
+            }
             TableFactor::Table { name, alias, .. } => {
+                self.check_connector(&name, false)?;
                 let table_id = self.bind_table_id(&name)?;
                 let col_id = self.bind_table_name(&name, alias)?;
                 let true_ = self.egraph.add(Node::null());
                 Ok(self.egraph.add(Node::Scan([table_id, col_id, true_])))
             }
+            TableFactor::Derived { lateral: false, subquery, alias } => self.bind_subquery(*subquery, alias),
             _ => {
                 unimplemented!()
             }
         }
     }
 
+    /// Binds a subquery in `FROM`, whose output columns are accessed by their names
+    /// in the subquery, or the column names of the alias if given.
+    ///
+    /// # Example
+    /// - `(SELECT a, b + 1 AS c FROM t) AS s` => `(limit null 0 (proj (list $1.1 (+ $1.2 1)) ..))`
+    ///   with the columns `s.a` and `s.c`
+    fn bind_subquery(&mut self, query: Query, alias: Option<TableAlias>) -> Result {
+        let (id, ctx) = self.bind_query(query)?;
+        let schema = self.schema(id);
+        let table_alias = alias.as_ref().map_or(String::new(), |alias| alias.name.value.clone());
+        if !table_alias.is_empty() && !self.current_ctx_mut().table_aliases.insert(table_alias.clone()) {
+            return Err(BindError::DuplicatedTable(table_alias));
+        }
+        match alias.map(|alias| alias.columns).filter(|columns| !columns.is_empty()) {
+            Some(columns) if columns.len() != schema.len() => {
+                return Err(BindError::TupleLengthMismatch { expected: schema.len(), actual: columns.len() });
+            }
+            Some(columns) => {
+                for (column, id) in columns.into_iter().zip(schema) {
+                    self.add_alias(column.value.to_lowercase(), table_alias.clone(), id);
+                }
+            }
+            None => {
+                for (name, id) in ctx.output_aliases.into_iter().filter(|(_, id)| schema.contains(id)) {
+                    self.add_alias(name, table_alias.clone(), id);
+                }
+            }
+        }
+        Ok(id)
+    }
+
+    /// Binds a windowing table-valued function, which appends `window_start` and `window_end`
+    /// columns to a table:
+    /// - `TUMBLE(table, DESCRIPTOR(time_col), size)`
+    /// - `HOP(table, DESCRIPTOR(time_col), slide, size)`
+    ///
+    /// # Example
+    /// - `TUMBLE(t, DESCRIPTOR(ts), INTERVAL '10' SECOND)` =>
+    ///   `(window (list (window_start ts 10s 10s) (window_end ts 10s 10s)) (scan $1 (list ..) null))`
+    fn bind_window_table_function(
+        &mut self, name: &ObjectName, alias: Option<TableAlias>, args: Vec<FunctionArg>) -> Result {
+        let func_name = name.to_string().to_lowercase();
+        let invalid = || BindError::InvalidTableFunction(name.to_string());
+        let args: Vec<Expr> = args.into_iter()
+            .map(|arg| match arg {
+                FunctionArg::Named { arg: FunctionArgExpr::Expr(expr), .. }
+                | FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) => Ok(expr),
+                _ => Err(invalid()),
+            })
+            .try_collect()?;
+        let (table, descriptor, slide, size) = match (func_name.as_str(), args) {
+            ("tumble", args) if args.len() == 3 => {
+                let [table, descriptor, size]: [Expr; 3] = args.try_into().unwrap();
+                (table, descriptor, size.clone(), size)
+            }
+            ("hop", args) if args.len() == 4 => {
+                let [table, descriptor, slide, size]: [Expr; 4] = args.try_into().unwrap();
+                (table, descriptor, slide, size)
+            }
+            _ => return Err(invalid()),
+        };
+
+        let table_name = match table {
+            Expr::Identifier(ident) => ObjectName(vec![ident]),
+            Expr::CompoundIdentifier(idents) => ObjectName(idents),
+            _ => return Err(invalid()),
+        };
+        let table_alias = match &alias {
+            Some(alias) => alias.name.value.clone(),
+            None => split_name(&table_name)?.1.to_string(),
+        };
+        self.check_connector(&table_name, false)?;
+        let table_id = self.bind_table_id(&table_name)?;
+        let col_id = self.bind_table_name(&table_name, alias)?;
+        let null = self.egraph.add(Node::null());
+        let scan = self.egraph.add(Node::Scan([table_id, col_id, null]));
+
+        let (time_col_name, time_col) = match descriptor {
+            Expr::Function(func) if func.name.to_string().to_lowercase() == "descriptor"
+                && func.args.len() == 1 => match &func.args[0] {
+                FunctionArg::Unnamed(FunctionArgExpr::Expr(Expr::Identifier(ident))) => {
+                    (ident.value.clone(), self.bind_colum_ref(std::slice::from_ref(ident))?)
+                }
+                _ => return Err(invalid()),
+            },
+            _ => return Err(invalid()),
+        };
+        if self.check_type(time_col)?.kind() != DataTypeKind::Timestamp {
+            return Err(BindError::InvalidExpression(
+                "the time column of a window must be type timestamp".into()));
+        }
+        // windows are closed by watermarks, so the time column must have one
+        let column = self.node(time_col).as_column();
+        let watermark = self.catalog
+            .get_table(TableRefId::new(column.schema_id, column.table_id))
+            .and_then(|table| table.watermark());
+        if watermark.map(|w| w.column_id) != Some(column.column_id) {
+            return Err(BindError::NotTimeAttribute(time_col_name));
+        }
+        let slide = self.bind_window_interval(slide)?;
+        let size = self.bind_window_interval(size)?;
+
+        let window_start = self.egraph.add(Node::WindowStart([time_col, slide, size]));
+        let window_end = self.egraph.add(Node::WindowEnd([time_col, slide, size]));
+        self.add_alias("window_start".into(), table_alias.clone(), window_start);
+        self.add_alias("window_end".into(), table_alias, window_end);
+        let windows = self.egraph.add(Node::List([window_start, window_end].into()));
+        Ok(self.egraph.add(Node::Window([windows, scan])))
+    }
+
+    /// Binds the size or slide of a window, which must be a positive interval.
+    fn bind_window_interval(&mut self, expr: Expr) -> Result {
+        let id = self.bind_expr(expr)?;
+        match self.node(id) {
+            Node::Constant(DataValue::Interval(v)) if *v > 0 => Ok(id),
+            _ => Err(BindError::InvalidExpression(
+                "the size and slide of a window must be positive intervals".into())),
+        }
+    }
+
+    /// Binds a table with joins, which are joined from left to right.
+    ///
+    /// # Example
+    /// - `t1 JOIN t2 ON t1.a = t2.a` =>
+    ///   `(join inner (= $1.1 $2.1) (scan $1 (list ..) null) (scan $2 (list ..) null))`
     pub(in crate::binder) fn bind_table_with_joins(&mut self, tables: TableWithJoins) -> Result {
-        let node = self.bind_table_factor(tables.relation)?;
+        let mut node = self.bind_table_factor(tables.relation)?;
         for join in tables.joins {
-            todo!("handle join")
+            if matches!(&join.relation, TableFactor::Table { args: Some(args), .. } if system_time(args).is_some()) {
+                node = self.bind_lookup_join(node, join)?;
+                continue;
+            }
+            let right = self.bind_table_factor(join.relation)?;
+            let (join_type, condition) = self.bind_join_op(join.join_operator)?;
+            node = self.egraph.add(Node::Join([join_type, condition, node, right]));
         }
         Ok(node)
     }
 
+    /// Binds a lookup join, whose right table is a dimension table looked up by the rows
+    /// of the left one, at the processing time of each row.
+    ///
+    /// # Example
+    /// - `o JOIN d FOR SYSTEM_TIME AS OF o.proctime ON o.k = d.k` =>
+    ///   `(lookup_join inner (= $1.1 $2.1) (scan $1 (list ..) null) (scan $2 (list ..) null))`
+    fn bind_lookup_join(&mut self, left: Id, join: Join) -> Result {
+        let TableFactor::Table { name, alias, args: Some(args), .. } = join.relation else {
+            unreachable!("not a table of lookup join");
+        };
+        // only lookups at the processing time are supported, so the time is not bound
+        let time = system_time(&args).unwrap();
+        let is_proctime = match time {
+            Expr::Function(func) => func.name.to_string().eq_ignore_ascii_case("proctime") && func.args.is_empty(),
+            Expr::Identifier(ident) => ident.value.eq_ignore_ascii_case("proctime"),
+            Expr::CompoundIdentifier(idents) => idents.last().unwrap().value.eq_ignore_ascii_case("proctime"),
+            _ => false,
+        };
+        if !is_proctime {
+            return Err(BindError::InvalidLookupJoin(format!(
+                "only FOR SYSTEM_TIME AS OF proctime is supported, not {}", time)));
+        }
+        let (schema_name, table_name) = split_name(&name)?;
+        let table = self.catalog
+            .get_table_id_by_name(schema_name, table_name)
+            .and_then(|ref_id| self.catalog.get_table(ref_id))
+            .ok_or_else(|| BindError::TableNotFound(table_name.into()))?;
+        let is_lookup = table.get_option("connector")
+            .and_then(|connector| self.connectors.get(&connector))
+            .is_some_and(|factory| factory.is_lookup());
+        if !is_lookup {
+            return Err(BindError::NotLookupTable(table_name.into()));
+        }
+        LookupCacheOptions::from_options(&table.get_options()).map_err(BindError::InvalidLookupJoin)?;
+        let table_id = self.bind_table_id(&name)?;
+        let col_id = self.bind_table_name(&name, alias)?;
+        let null = self.egraph.add(Node::null());
+        let right = self.egraph.add(Node::Scan([table_id, col_id, null]));
+
+        let (join_type, condition) = self.bind_join_op(join.join_operator)?;
+        if !matches!(self.node(join_type), Node::Inner | Node::LeftOuter) {
+            return Err(BindError::InvalidLookupJoin("only inner and left outer joins are supported".into()));
+        }
+        let keys = self.lookup_keys(condition, right);
+        if keys.is_empty() {
+            return Err(BindError::InvalidLookupJoin(format!(
+                "the join condition must equal a column of {} to an expression of the other tables", table_name)));
+        }
+        // the rows are looked up by the equality of the values, which never holds for different types
+        for (column, key) in keys {
+            let (column_type, key_type) = (self.check_type(column)?.kind(), self.check_type(key)?.kind());
+            if column_type != key_type {
+                return Err(BindError::InvalidLookupJoin(format!(
+                    "a column of {} of type {} can't be looked up by a key of type {}",
+                    table_name, column_type, key_type)));
+            }
+        }
+        Ok(self.egraph.add(Node::LookupJoin([join_type, condition, left, right])))
+    }
+
+    /// Returns the columns of the dimension table and the expressions without its columns
+    /// which are equal by the conjuncts of the condition, which are the keys to look up.
+    fn lookup_keys(&self, condition: Id, dim: Id) -> Vec<(Id, Id)> {
+        let schema = &self.egraph[dim].data.schema;
+        let refers_dim = |mut ids: Vec<Id>| {
+            while let Some(id) = ids.pop() {
+                if schema.contains(&id) {
+                    return true;
+                }
+                ids.extend(self.node(id).children());
+            }
+            false
+        };
+        let mut keys = vec![];
+        let mut conjuncts = vec![condition];
+        while let Some(id) = conjuncts.pop() {
+            match *self.node(id) {
+                Node::And([a, b]) => conjuncts.extend([a, b]),
+                Node::Eq([a, b]) if schema.contains(&a) && !refers_dim(vec![b]) => keys.push((a, b)),
+                Node::Eq([a, b]) if schema.contains(&b) && !refers_dim(vec![a]) => keys.push((b, a)),
+                _ => {}
+            }
+        }
+        keys
+    }
+
+    /// Returns the join type and the condition of a join, which is bound after both tables
+    /// so that it can refer to their columns.
+    fn bind_join_op(&mut self, op: JoinOperator) -> Result<(Id, Id)> {
+        use JoinOperator::*;
+        let (join_type, constraint) = match op {
+            Inner(constraint) => (Node::Inner, constraint),
+            LeftOuter(constraint) => (Node::LeftOuter, constraint),
+            RightOuter(constraint) => (Node::RightOuter, constraint),
+            FullOuter(constraint) => (Node::FullOuter, constraint),
+            CrossJoin => (Node::Inner, JoinConstraint::None),
+            op => return Err(BindError::UnsupportedJoin(format!("{:?}", op))),
+        };
+        let condition = match constraint {
+            JoinConstraint::On(expr) => {
+                let id = self.bind_expr(expr)?;
+                if self.check_type(id)?.kind() != DataTypeKind::Bool {
+                    return Err(BindError::InvalidExpression(
+                        "argument of JOIN/ON must be type boolean".into()));
+                }
+                id
+            }
+            JoinConstraint::None => self.egraph.add(Node::true_()),
+            constraint => return Err(BindError::UnsupportedJoin(format!("{:?}", constraint))),
+        };
+        Ok((self.egraph.add(join_type), condition))
+    }
+
 
     pub fn bind_table_columns(
         &mut self, table_name: &ObjectName, columns: &[Ident])
@@ -79,6 +337,24 @@ impl Binder {
         Ok(id)
     }
 
+    /// Checks that the connector of the table can be written by `INSERT` if `insert`,
+    /// or read by `SELECT` otherwise.
+    pub(in crate::binder) fn check_connector(&self, table_name: &ObjectName, insert: bool) -> Result<()> {
+        let (schema_name, name) = split_name(table_name)?;
+        let table = self.catalog
+            .get_table_id_by_name(schema_name, name)
+            .and_then(|ref_id| self.catalog.get_table(ref_id))
+            .ok_or_else(|| BindError::TableNotFound(name.into()))?;
+        let Some(factory) = table.get_option("connector").and_then(|c| self.connectors.get(&c)) else {
+            return Ok(());
+        };
+        match insert {
+            true if !factory.is_sink() => Err(BindError::SourceOnlyTable(name.into())),
+            false if !factory.is_source() => Err(BindError::SinkOnlyTable(name.into())),
+            _ => Ok(()),
+        }
+    }
+
     pub fn bind_table_name(&mut self, name: &ObjectName, alias: Option<TableAlias>) -> Result {
         let (schema_name, table_name) = split_name(&name)?;
         let ref_id = self
@@ -113,4 +389,53 @@ impl Binder {
         let id = self.egraph.add(Node::List(ids.into()));
         Ok(id)
     }
-}
\ No newline at end of file
+}
+
+/// Returns the time of `FOR SYSTEM_TIME AS OF`, which is rewritten into a table argument.
+fn system_time(args: &[FunctionArg]) -> Option<&Expr> {
+    match args {
+        [FunctionArg::Named { name, arg: FunctionArgExpr::Expr(time) }] if name.value == SYSTEM_TIME_ARG => Some(time),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::sync::Arc;
+    use crate::catalog::{DatabaseCatalog, DEFAULT_SCHEMA_NAME};
+    use crate::parser::parse;
+    use crate::planner::RecExpr;
+    use crate::types::DataType;
+    use super::*;
+
+    /// Binds a statement over the table `o(k int, s string)` and the dimension table
+    /// `d(k bigint, s string)`.
+    fn bind(sql: &str) -> Result<RecExpr> {
+        let catalog = Arc::new(DatabaseCatalog::new());
+        let schema = catalog.get_schema_by_name(DEFAULT_SCHEMA_NAME).unwrap();
+        for (name, key) in [("o", DataTypeKind::Int32), ("d", DataTypeKind::Int64)] {
+            let table = schema.get_table(schema.add_table(name).unwrap()).unwrap();
+            table.add_column("k", DataType::new(key, true).to_column()).unwrap();
+            table.add_column("s", DataType::new(DataTypeKind::String, true).to_column()).unwrap();
+            table.add_options(BTreeMap::from([
+                ("connector".to_string(), "filesystem".to_string()),
+                ("path".to_string(), name.to_string()),
+                ("format".to_string(), "csv".to_string()),
+            ]));
+        }
+        let mut binder = Binder::new(catalog);
+        binder.bind(parse(sql).unwrap().remove(0))
+    }
+
+    #[test]
+    fn test_lookup_key_type() {
+        assert!(bind("select o.s from o join d for system_time as of proctime() on o.s = d.s").is_ok());
+        for sql in [
+            "select o.s from o join d for system_time as of proctime() on o.k = d.k",
+            "select o.s from o join d for system_time as of proctime() on d.s = o.k",
+        ] {
+            assert!(matches!(bind(sql), Err(BindError::InvalidLookupJoin(_))), "{}", sql);
+        }
+    }
+}
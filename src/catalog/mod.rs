@@ -60,7 +60,8 @@ pub struct ColumnRefId {
 
 impl Display for ColumnRefId {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        todo!()
+        // TODO: now ignore database and schema
+        write!(f, "${}.{}", self.table_id, self.column_id)
     }
 }
 
@@ -3,6 +3,15 @@ use std::sync::Mutex;
 use crate::catalog::column::ColumnCatalog;
 use crate::catalog::{CatalogError, ColumnDesc, ColumnId, TableId};
 
+/// The watermark strategy of a table, defined by
+/// `WATERMARK FOR <column> AS <column> - <delay>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatermarkDesc {
+    pub column_id: ColumnId,
+    /// The allowed lateness of rows in milliseconds.
+    pub delay: i64,
+}
+
 /// The catalog of a table.
 pub struct TableCatalog {
     id: TableId,
@@ -16,6 +25,7 @@ struct Inner {
     columns: BTreeMap<ColumnId, ColumnCatalog>,
     next_column_id: ColumnId,
     options: HashMap<String, String>,
+    watermark: Option<WatermarkDesc>,
 }
 
 impl TableCatalog {
@@ -28,6 +38,7 @@ impl TableCatalog {
                 columns: BTreeMap::new(),
                 next_column_id: 0,
                 options: HashMap::new(),
+                watermark: None,
             }),
         }
     }
@@ -62,6 +73,16 @@ impl TableCatalog {
         }
     }
 
+    pub fn set_watermark(&self, watermark: WatermarkDesc) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.watermark = Some(watermark);
+    }
+
+    pub fn watermark(&self) -> Option<WatermarkDesc> {
+        let inner = self.inner.lock().unwrap();
+        inner.watermark
+    }
+
     pub fn contains_column(&self, name: &str) -> bool {
         let inner = self.inner.lock().unwrap();
         inner.column_idxs.contains_key(name)
@@ -5,6 +5,9 @@ use std::collections::{BTreeMap, HashMap, HashSet};
 use futures::{SinkExt};
 use tokio::sync::{oneshot};
 use tokio::sync::oneshot::Sender;
+use crate::checkpoint::CheckpointReport;
+use crate::connector::CommitFuture;
+use crate::state::StateRef;
 
 #[derive(Debug)]
 pub struct BarrierCompletion {
@@ -20,6 +23,15 @@ pub struct BarrierManager {
     barrier_complete_sender: HashMap<u64, Sender<BarrierCompletion>>,
     receiver: Option<oneshot::Receiver<BarrierCompletion>>,
     sender: Option<Sender<BarrierCompletion>>,
+
+    /// The states of the stateful actors, whose handles are recorded in checkpoints.
+    states: BTreeMap<u32, StateRef>,
+    source_positions: BTreeMap<u64, BTreeMap<u32, String>>,
+    /// The final positions of the ended sources, which are reported for all later epochs.
+    ended_source_positions: BTreeMap<u32, String>,
+    /// The commits of the output of the sinks by epoch, which are the second phase
+    /// of two-phase commits.
+    commits: BTreeMap<u64, Vec<CommitFuture>>,
 }
 
 impl BarrierManager {
@@ -32,7 +44,11 @@ impl BarrierManager {
             barrier_complete_receiver: HashMap::new(),
             barrier_complete_sender: HashMap::new(),
             sender: Some(tx),
-            receiver: Some(rx)
+            receiver: Some(rx),
+            states: BTreeMap::new(),
+            source_positions: BTreeMap::new(),
+            ended_source_positions: BTreeMap::new(),
+            commits: BTreeMap::new(),
         }
     }
 
@@ -60,6 +76,60 @@ impl BarrierManager {
         self.all_actors.insert(actor_id);
     }
 
+    /// Removes an actor whose input is ended, which no longer blocks the barriers.
+    pub fn finish_actor(&mut self, actor_id: u32) {
+        self.all_actors.remove(&actor_id);
+        for (epoch, remain_actors) in self.epoch_barrier_remain_actors.iter_mut() {
+            if remain_actors.remove(&actor_id) && remain_actors.is_empty() {
+                if let Some(sender) = self.barrier_complete_sender.remove(epoch) {
+                    let _ = sender.send(BarrierCompletion { epoch: *epoch });
+                }
+            }
+        }
+    }
+
+    pub fn register_state(&mut self, actor_id: u32, state: StateRef) {
+        self.states.insert(actor_id, state);
+    }
+
+    /// Registers the commit of the output of a sink in `epoch`.
+    pub fn register_commit(&mut self, epoch: u64, commit: CommitFuture) {
+        self.commits.entry(epoch).or_default().push(commit);
+    }
+
+    /// Takes the commits of the epochs up to `epoch`, which is completed and checkpointed.
+    pub fn take_commits(&mut self, epoch: u64) -> Vec<CommitFuture> {
+        let remaining = self.commits.split_off(&(epoch + 1));
+        std::mem::replace(&mut self.commits, remaining).into_values().flatten().collect()
+    }
+
+    /// Reports the position of a source actor when it receives the barrier of `epoch`.
+    pub fn report_source_position(&mut self, epoch: u64, actor_id: u32, position: String) {
+        self.source_positions.entry(epoch).or_default().insert(actor_id, position);
+    }
+
+    /// Reports the final position of a source actor once its source is ended.
+    pub fn report_source_end(&mut self, actor_id: u32, position: String) {
+        self.ended_source_positions.insert(actor_id, position);
+    }
+
+    /// Takes what is reported for a completed epoch.
+    ///
+    /// The states are committed with the epoch and not changed until the next barrier,
+    /// so their current handles are the ones of the epoch.
+    pub fn take_report(&mut self, epoch: u64) -> CheckpointReport {
+        let mut source_positions = self.source_positions.remove(&epoch).unwrap_or_default();
+        for (actor_id, position) in &self.ended_source_positions {
+            source_positions.entry(*actor_id).or_insert_with(|| position.clone());
+        }
+        CheckpointReport {
+            source_positions,
+            state_handles: self.states.iter()
+                .filter_map(|(actor_id, state)| Some((*actor_id, state.handle()?)))
+                .collect(),
+        }
+    }
+
     pub fn register_barrier(&mut self, epoch: u64) {
         self.epoch_barrier_remain_actors.insert(epoch, self.all_actors.clone());
     }
@@ -88,10 +158,11 @@ pub struct BarrierService {
 
 impl BarrierService {
 
-    pub fn new(barrier_manager: Arc<Mutex<BarrierManager>>) -> Self {
+    /// Creates the service sending barriers of the epochs after `start_epoch`.
+    pub fn new(barrier_manager: Arc<Mutex<BarrierManager>>, start_epoch: u64) -> Self {
         Self {
             barrier_manager,
-            current_epoch: 0
+            current_epoch: start_epoch
         }
     }
 
@@ -0,0 +1,183 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use crate::state::{StateError, StateHandle};
+
+/// The number of completed checkpoints kept for each job.
+pub const NUM_RETAINED_CHECKPOINTS: usize = 3;
+
+const SQL_FILE: &str = "job.sql";
+const MANIFEST_FILE: &str = "manifest";
+
+/// What the actors report for an epoch, collected by the barrier manager.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CheckpointReport {
+    /// The positions of the sources at the barrier, by actor id.
+    pub source_positions: BTreeMap<u32, String>,
+    /// The committed state of the stateful executors, by actor id.
+    pub state_handles: BTreeMap<u32, StateHandle>,
+}
+
+/// A completed checkpoint of a job, from which the job can be restored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Manifest {
+    pub epoch: u64,
+    /// The statement of the job, which is planned again on restore.
+    pub sql: String,
+    /// The optimized plan of the job when the checkpoint was taken.
+    pub plan: String,
+    pub report: CheckpointReport,
+}
+
+/// The checkpoints of a job, in `dir/<epoch>`.
+///
+/// Each checkpoint has a manifest file and hard links to the state logs,
+/// as the live logs may be replaced by compaction.
+#[derive(Debug, Clone)]
+pub struct CheckpointStore {
+    dir: PathBuf,
+    plan: String,
+}
+
+impl CheckpointStore {
+    /// Creates the store of a job in `dir/job_id`, recording the statement of the job.
+    pub fn create(dir: impl AsRef<Path>, job_id: &str, sql: &str, plan: String) -> Result<Self, StateError> {
+        let dir = dir.as_ref().join(job_id);
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join(SQL_FILE), sql)?;
+        Ok(CheckpointStore { dir, plan: plan.replace('\n', " ") })
+    }
+
+    /// Writes the checkpoint of a completed epoch and removes the old checkpoints.
+    pub fn write(&self, epoch: u64, report: CheckpointReport) -> Result<(), StateError> {
+        let dir = self.dir.join(epoch.to_string());
+        std::fs::create_dir_all(&dir)?;
+        let mut manifest = format!("epoch {}\nplan {}\n", epoch, self.plan);
+        for (actor_id, position) in &report.source_positions {
+            manifest += &format!("source {} {}\n", actor_id, position);
+        }
+        for (actor_id, handle) in &report.state_handles {
+            let name = format!("state-{}.log", actor_id);
+            let link = dir.join(&name);
+            if link.exists() {
+                std::fs::remove_file(&link)?;
+            }
+            if std::fs::hard_link(&handle.path, &link).is_err() {
+                std::fs::copy(&handle.path, &link)?;
+            }
+            manifest += &format!("state {} {} {}\n", actor_id, handle.len, name);
+        }
+        // the checkpoint is completed once the manifest is renamed
+        let tmp = dir.join(format!("{}.tmp", MANIFEST_FILE));
+        let mut file = std::fs::File::create(&tmp)?;
+        file.write_all(manifest.as_bytes())?;
+        file.sync_all()?;
+        std::fs::rename(&tmp, dir.join(MANIFEST_FILE))?;
+
+        let mut epochs = Self::epochs(&self.dir)?;
+        epochs.reverse();
+        for epoch in epochs.into_iter().skip(NUM_RETAINED_CHECKPOINTS) {
+            std::fs::remove_dir_all(self.dir.join(epoch.to_string()))?;
+        }
+        Ok(())
+    }
+
+    /// Removes the checkpoints after `epoch`, which a job restored from the checkpoint
+    /// of `epoch` has abandoned and would otherwise overwrite or be restored from.
+    pub fn remove_after(&self, epoch: u64) -> Result<(), StateError> {
+        for newer in Self::epochs(&self.dir)?.into_iter().filter(|e| *e > epoch) {
+            std::fs::remove_dir_all(self.dir.join(newer.to_string()))?;
+        }
+        Ok(())
+    }
+
+    /// Returns the epochs of the checkpoints in `job_dir`, in ascending order.
+    fn epochs(job_dir: &Path) -> Result<Vec<u64>, StateError> {
+        let mut epochs = vec![];
+        for entry in std::fs::read_dir(job_dir)? {
+            let entry = entry?;
+            if let Some(epoch) = entry.file_name().to_str().and_then(|name| name.parse().ok()) {
+                epochs.push(epoch);
+            }
+        }
+        epochs.sort_unstable();
+        Ok(epochs)
+    }
+}
+
+impl Manifest {
+    /// Reads the checkpoint of `epoch` of a job from the checkpoint directory.
+    pub fn read(dir: impl AsRef<Path>, job_id: &str, epoch: u64) -> Result<Self, StateError> {
+        let job_dir = dir.as_ref().join(job_id);
+        let dir = job_dir.join(epoch.to_string());
+        let corrupted = |line: &str| StateError::Corrupted(format!(
+            "invalid line in manifest of epoch {}: {}", epoch, line));
+
+        let sql = std::fs::read_to_string(job_dir.join(SQL_FILE))?;
+        let mut manifest = Manifest { epoch, sql, plan: String::new(), report: CheckpointReport::default() };
+        for line in std::fs::read_to_string(dir.join(MANIFEST_FILE))?.lines() {
+            let (kind, rest) = line.split_once(' ').ok_or_else(|| corrupted(line))?;
+            match kind {
+                "epoch" if rest.parse() == Ok(epoch) => {}
+                "plan" => manifest.plan = rest.to_string(),
+                "source" => {
+                    let (actor_id, position) = rest.split_once(' ').ok_or_else(|| corrupted(line))?;
+                    let actor_id = actor_id.parse().map_err(|_| corrupted(line))?;
+                    manifest.report.source_positions.insert(actor_id, position.to_string());
+                }
+                "state" => {
+                    let mut fields = rest.splitn(3, ' ');
+                    let (Some(actor_id), Some(len), Some(name)) = (fields.next(), fields.next(), fields.next()) else {
+                        return Err(corrupted(line));
+                    };
+                    let handle = StateHandle {
+                        path: dir.join(name),
+                        len: len.parse().map_err(|_| corrupted(line))?,
+                    };
+                    let actor_id = actor_id.parse().map_err(|_| corrupted(line))?;
+                    manifest.report.state_handles.insert(actor_id, handle);
+                }
+                _ => return Err(corrupted(line)),
+            }
+        }
+        Ok(manifest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_and_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("state.log");
+        std::fs::write(&log, b"0123456789").unwrap();
+
+        let store = CheckpointStore::create(dir.path(), "job", "select 1", "(proj 1)".into()).unwrap();
+        let mut report = CheckpointReport::default();
+        report.source_positions.insert(1, "file.csv:42".into());
+        report.state_handles.insert(2, StateHandle { path: log.clone(), len: 4 });
+        for epoch in 1..=5 {
+            store.write(epoch, report.clone()).unwrap();
+        }
+        // replacing the live log as compaction does doesn't change the checkpoint
+        std::fs::write(dir.path().join("compact.log"), b"changed").unwrap();
+        std::fs::rename(dir.path().join("compact.log"), &log).unwrap();
+
+        assert!(Manifest::read(dir.path(), "job", 2).is_err());
+        let manifest = Manifest::read(dir.path(), "job", 5).unwrap();
+        assert_eq!(manifest.sql, "select 1");
+        assert_eq!(manifest.plan, "(proj 1)");
+        assert_eq!(manifest.report.source_positions, report.source_positions);
+        let handle = &manifest.report.state_handles[&2];
+        assert_eq!(handle.len, 4);
+        assert_eq!(&std::fs::read(&handle.path).unwrap()[..4], b"0123");
+
+        // restoring from the checkpoint 3 abandons the later ones
+        store.remove_after(3).unwrap();
+        assert!(Manifest::read(dir.path(), "job", 3).is_ok());
+        assert!(Manifest::read(dir.path(), "job", 4).is_err());
+        assert!(Manifest::read(dir.path(), "job", 5).is_err());
+    }
+}
@@ -1,5 +1,5 @@
 mod barrier_manager;
+mod manifest;
 
-pub use barrier_manager::*
-
-;
+pub use barrier_manager::*;
+pub use manifest::*;
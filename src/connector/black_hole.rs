@@ -1,29 +1,37 @@
 use std::sync::Arc;
-use futures_async_stream::try_stream;
+use futures::future::{self, BoxFuture, FutureExt};
 use crate::array::DataChunk;
 use crate::catalog::{ColumnId, TableCatalog};
-use crate::connector::StreamConnector;
-use crate::stream::{Barrier, Message};
-use crate::connector::ExecuteError;
+use crate::connector::{BoxedSink, ConnectorContext, ConnectorFactory, SinkConnector};
+use crate::executor::ExecuteError;
 
 pub struct BlackHole {
     pub column_ids: Vec<ColumnId>,
     pub table: Arc<TableCatalog>,
 }
 
+/// Creates the sinks of `'connector' = 'blackhole'`, which discard the rows written.
+pub struct BlackHoleFactory;
 
-impl StreamConnector for BlackHole {
+impl ConnectorFactory for BlackHoleFactory {
+    fn name(&self) -> &str {
+        "blackhole"
+    }
 
-    #[try_stream(boxed, ok = Message, error = ExecuteError)]
-    async fn read(&self) {
-        unimplemented!()
+    fn is_sink(&self) -> bool {
+        true
     }
 
-    fn write(&mut self, chunk: DataChunk) {
-        // do nothing
+    fn create_sink(&self, context: ConnectorContext) -> Result<BoxedSink, ExecuteError> {
+        Ok(Box::new(BlackHole {
+            column_ids: context.column_ids,
+            table: context.table,
+        }))
     }
+}
 
-    fn on_receive_barrier(&mut self, barrier: Barrier) {
-        // do nothing
+impl SinkConnector for BlackHole {
+    fn write(&mut self, _chunk: DataChunk) -> BoxFuture<'_, Result<(), ExecuteError>> {
+        future::ready(Ok(())).boxed()
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+use std::io::Write;
+use csv::{ReaderBuilder, StringRecord, WriterBuilder};
+use crate::array::{ArrayBuilderImpl, DataChunk};
+use crate::executor::ExecuteError;
+use crate::types::{DataType, DataTypeKind, DataValue};
+
+/// The CSV format of files, with options:
+/// - `csv.field-delimiter`: the delimiter of fields, `,` by default
+/// - `csv.quote-character`: the quote of fields containing delimiters, quotes or line breaks,
+///   `"` by default
+/// - `csv.header`: whether each file starts with a header line, `false` by default
+/// - `csv.null-literal`: the string of NULL values, besides empty fields of non-string columns
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvFormat {
+    pub delimiter: u8,
+    pub quote: u8,
+    pub header: bool,
+    pub null_literal: Option<String>,
+}
+
+impl Default for CsvFormat {
+    fn default() -> Self {
+        CsvFormat {
+            delimiter: b',',
+            quote: b'"',
+            header: false,
+            null_literal: None,
+        }
+    }
+}
+
+impl CsvFormat {
+    /// Parses the options, returning an error message if invalid.
+    pub fn from_options(options: &HashMap<String, String>) -> Result<Self, String> {
+        let char_option = |key: &str, default: u8| options.get(key).map_or(Ok(default), |value| {
+            match value.as_str() {
+                "\\t" => Ok(b'\t'),
+                value if value.len() == 1 => Ok(value.as_bytes()[0]),
+                _ => Err(format!("invalid {}: {:?} is not a single character", key, value)),
+            }
+        });
+        let header = match options.get("csv.header").map(|header| header.to_lowercase()).as_deref() {
+            None | Some("false") => false,
+            Some("true") => true,
+            Some(header) => return Err(format!("invalid csv.header: {}", header)),
+        };
+        Ok(CsvFormat {
+            delimiter: char_option("csv.field-delimiter", b',')?,
+            quote: char_option("csv.quote-character", b'"')?,
+            header,
+            null_literal: options.get("csv.null-literal").cloned(),
+        })
+    }
+
+    /// Parses the records of the lines into a chunk of `types`, skipping blank lines.
+    pub fn parse_chunk(&self, lines: &[String], types: &[DataType]) -> Result<DataChunk, ExecuteError> {
+        let mut builders: Vec<_> = types.iter()
+            .map(|ty| ArrayBuilderImpl::with_capacity(lines.len(), ty))
+            .collect();
+        for line in lines.iter().filter(|line| !line.trim().is_empty()) {
+            let malformed = |reason: String| ExecuteError::MalformedRow(line.clone(), reason);
+            let record = self.parse_record(line).map_err(|e| malformed(e.to_string()))?;
+            if record.len() != types.len() {
+                return Err(malformed(format!("expected {} fields, got {}", types.len(), record.len())));
+            }
+            for ((builder, field), ty) in builders.iter_mut().zip(record.iter()).zip(types) {
+                if self.null_literal.as_deref() == Some(field) {
+                    builder.push(&DataValue::Null);
+                } else if ty.kind() == DataTypeKind::String {
+                    builder.push(&DataValue::String(field.to_string()));
+                } else {
+                    builder.push_str(field).map_err(|e| malformed(e.to_string()))?;
+                }
+            }
+        }
+        Ok(builders.into_iter().map(|builder| builder.finish()).collect())
+    }
+
+    fn parse_record(&self, line: &str) -> csv::Result<StringRecord> {
+        let mut reader = ReaderBuilder::new()
+            .has_headers(false)
+            .delimiter(self.delimiter)
+            .quote(self.quote)
+            .from_reader(line.as_bytes());
+        reader.records().next().unwrap_or_else(|| Ok(StringRecord::new()))
+    }
+
+    /// Writes the rows as records, where NULL is the null literal or an empty field.
+    pub fn write_rows(&self, out: &mut impl Write, rows: impl Iterator<Item = Vec<DataValue>>)
+        -> std::io::Result<()> {
+        let mut writer = WriterBuilder::new()
+            .delimiter(self.delimiter)
+            .quote(self.quote)
+            .from_writer(out);
+        for row in rows {
+            writer.write_record(row.iter().map(|value| match value {
+                DataValue::Null => self.null_literal.clone().unwrap_or_default(),
+                value => value.to_string(),
+            }))?;
+        }
+        writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_chunk() {
+        let format = CsvFormat {
+            delimiter: b';',
+            null_literal: Some("\\N".into()),
+            ..Default::default()
+        };
+        let types = [
+            DataType::new_nullable(DataTypeKind::Int32),
+            DataType::new_nullable(DataTypeKind::String),
+            DataType::new_nullable(DataTypeKind::Bool),
+            DataType::new_nullable(DataTypeKind::Timestamp),
+        ];
+        let lines = [
+            r#"1;"a;""b""
+c";true;2024-01-01 00:00:01"#.to_string(),
+            "".to_string(),
+            r#"\N;;;\N"#.to_string(),
+        ];
+        let chunk = format.parse_chunk(&lines, &types).unwrap();
+        assert_eq!(chunk.cardinality(), 2);
+        let row: Vec<_> = chunk.row(0).values().collect();
+        assert_eq!(row[1], DataValue::String("a;\"b\"\nc".into()));
+        assert_eq!(row[2], DataValue::Bool(true));
+        let row: Vec<_> = chunk.row(1).values().collect();
+        assert_eq!(row, [DataValue::Null, DataValue::String("".into()), DataValue::Null, DataValue::Null]);
+
+        let malformed = ["x;a;true;\\N".to_string()];
+        assert!(matches!(format.parse_chunk(&malformed, &types), Err(ExecuteError::MalformedRow(..))));
+        let malformed = ["1;a".to_string()];
+        assert!(matches!(format.parse_chunk(&malformed, &types), Err(ExecuteError::MalformedRow(..))));
+    }
+}
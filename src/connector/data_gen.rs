@@ -1,55 +1,311 @@
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use futures_async_stream::try_stream;
 use crate::catalog::{ColumnId, TableCatalog};
-use crate::connector::StreamConnector;
-use crate::stream::{Barrier, Message};
-use crate::executor::ExecuteError;
+use crate::connector::{parse_duration, BoxedSource, ConnectorContext, ConnectorFactory, SourceConnector};
+use crate::stream::Message;
+use crate::executor::{ExecuteError, PROCESSING_WINDOW_SIZE};
 use std::time::Duration;
-use crate::types::DataTypeKind;
+use crate::types::{DataType, DataTypeKind};
 use crate::array::ArrayBuilderImpl;
 use crate::array::DataChunk;
 use crate::types::DataValue;
+use crate::types::timestamp;
 
+/// Generates rows of random or sequential values, with options:
+/// - `rows-per-second`: the rate of rows, 10 by default
+/// - `number-of-rows`: the number of rows to generate, unbounded by default
+/// - `seed`: the seed of random values, which are reproducible with the same seed
+/// - `fields.<col>.kind`: `random` by default, or `sequence`
+/// - `fields.<col>.min`, `fields.<col>.max`: the range of a random `INT`
+/// - `fields.<col>.length`: the length of a random `STRING`, 10 by default
+/// - `fields.<col>.max-past`: how far a random `TIMESTAMP` may be before now, 0 by default
+/// - `fields.<col>.start`, `fields.<col>.end`: the range of a sequence of `INT` or `STRING`,
+///   which ends the source when exhausted
 pub struct DataGenSource {
     pub column_ids: Vec<ColumnId>,
     pub table: Arc<TableCatalog>,
+    /// The options, or the error if they're invalid, which fails the source when read.
+    options: Result<DataGenOptions, String>,
+    /// The number of rows generated, reported as the position at barriers.
+    generated: AtomicU64,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataGenOptions {
+    rows_per_second: u64,
+    number_of_rows: Option<u64>,
+    seed: u64,
+    fields: Vec<FieldGenerator>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum FieldGenerator {
+    Sequence { start: i64, end: i64, string: bool },
+    RandomInt { min: i64, max: i64 },
+    RandomString { length: usize },
+    RandomBool,
+    RandomTimestamp { max_past: i64 },
+}
+
+impl DataGenOptions {
+    /// Parses the options of the columns, returning an error message if invalid.
+    pub fn parse(options: &HashMap<String, String>, columns: &[(String, DataType)]) -> Result<Self, String> {
+        let parse_u64 = |key: &str| options.get(key)
+            .map(|value| value.parse::<u64>().map_err(|_| format!("invalid {}: {}", key, value)))
+            .transpose();
+        let rows_per_second = match parse_u64("rows-per-second")? {
+            Some(0) => return Err("rows-per-second must be positive".into()),
+            rows_per_second => rows_per_second.unwrap_or(10),
+        };
+        let seed = match parse_u64("seed")? {
+            Some(seed) => seed,
+            None => timestamp::now() as u64,
+        };
+        let fields = columns.iter()
+            .map(|(name, ty)| FieldGenerator::parse(options, name, ty))
+            .try_collect()?;
+        Ok(DataGenOptions {
+            rows_per_second,
+            number_of_rows: parse_u64("number-of-rows")?,
+            seed,
+            fields,
+        })
+    }
+
+    /// Returns the number of rows to generate, `None` if unbounded.
+    fn total_rows(&self) -> Option<u64> {
+        self.fields.iter()
+            .filter_map(|field| match field {
+                FieldGenerator::Sequence { start, end, .. } => Some((end - start + 1).max(0) as u64),
+                _ => None,
+            })
+            .chain(self.number_of_rows)
+            .min()
+    }
+}
+
+impl FieldGenerator {
+    fn parse(options: &HashMap<String, String>, name: &str, ty: &DataType) -> Result<Self, String> {
+        let option = |key: &str| options.get(&format!("fields.{}.{}", name, key));
+        let parse_i64 = |key: &str, default: i64| option(key).map_or(Ok(default), |value| {
+            value.parse::<i64>().map_err(|_| format!("invalid fields.{}.{}: {}", name, key, value))
+        });
+        let kind = option("kind").map_or("random".to_string(), |kind| kind.to_lowercase());
+        match (kind.as_str(), ty.kind()) {
+            ("sequence", kind @ (DataTypeKind::Int32 | DataTypeKind::String)) => {
+                let (Some(_), Some(_)) = (option("start"), option("end")) else {
+                    return Err(format!("fields.{}.start and fields.{}.end are required for a sequence", name, name));
+                };
+                let (start, end) = (parse_i64("start", 0)?, parse_i64("end", 0)?);
+                if kind == DataTypeKind::Int32 && (start < i32::MIN as i64 || end > i32::MAX as i64) {
+                    return Err(format!("the sequence of {} is out of the range of INT", name));
+                }
+                Ok(FieldGenerator::Sequence { start, end, string: kind == DataTypeKind::String })
+            }
+            ("random", DataTypeKind::Int32) => {
+                let (min, max) = (parse_i64("min", i32::MIN as i64)?, parse_i64("max", i32::MAX as i64)?);
+                if min > max || min < i32::MIN as i64 || max > i32::MAX as i64 {
+                    return Err(format!("invalid range of {}: [{}, {}]", name, min, max));
+                }
+                Ok(FieldGenerator::RandomInt { min, max })
+            }
+            ("random", DataTypeKind::String) => Ok(FieldGenerator::RandomString {
+                length: parse_i64("length", 10)?.max(0) as usize,
+            }),
+            ("random", DataTypeKind::Bool) => Ok(FieldGenerator::RandomBool),
+            ("random", DataTypeKind::Timestamp) => {
+                let max_past = match option("max-past") {
+                    Some(value) => parse_duration(value)
+                        .ok_or_else(|| format!("invalid fields.{}.max-past: {}", name, value))?
+                        .as_millis() as i64,
+                    None => 0,
+                };
+                Ok(FieldGenerator::RandomTimestamp { max_past })
+            }
+            ("random" | "sequence", ty) => Err(format!("{} values of {} can't be generated for {}", kind, name, ty)),
+            (kind, _) => Err(format!("invalid fields.{}.kind: {}", name, kind)),
+        }
+    }
+
+    /// Generates the value of the `row`-th row in the `col`-th column.
+    fn generate(&self, seed: u64, row: u64, col: usize) -> DataValue {
+        let random = random(seed, row, col);
+        match self {
+            FieldGenerator::Sequence { start, string: false, .. } => DataValue::Int32((start + row as i64) as i32),
+            FieldGenerator::Sequence { start, string: true, .. } => DataValue::String((start + row as i64).to_string()),
+            FieldGenerator::RandomInt { min, max } => {
+                let range = (max - min) as u64 + 1;
+                DataValue::Int32((min + (random % range) as i64) as i32)
+            }
+            FieldGenerator::RandomString { length } => {
+                const CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+                let s = (0..*length)
+                    .map(|i| CHARS[(splitmix64(random.wrapping_add(i as u64)) % CHARS.len() as u64) as usize] as char)
+                    .collect();
+                DataValue::String(s)
+            }
+            FieldGenerator::RandomBool => DataValue::Bool(random % 2 == 0),
+            FieldGenerator::RandomTimestamp { max_past } => {
+                DataValue::Timestamp(timestamp::now() - (random % (*max_past as u64 + 1)) as i64)
+            }
+        }
+    }
+}
 
-impl StreamConnector for DataGenSource {
+/// Returns a random number determined by the seed and the cell,
+/// so that the rows can be generated again from any position.
+fn random(seed: u64, row: u64, col: usize) -> u64 {
+    splitmix64(seed ^ splitmix64(row.wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ col as u64))
+}
 
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Creates the sources of `'connector' = 'datagen'`.
+pub struct DataGenFactory;
+
+impl ConnectorFactory for DataGenFactory {
+    fn name(&self) -> &str {
+        "datagen"
+    }
+
+    fn optional_options(&self) -> &[&str] {
+        &["rows-per-second", "number-of-rows", "seed", "fields."]
+    }
+
+    fn validate(&self, options: &HashMap<String, String>, columns: &[(String, DataType)]) -> Result<(), String> {
+        DataGenOptions::parse(options, columns).map(|_| ())
+    }
+
+    fn is_source(&self) -> bool {
+        true
+    }
+
+    fn is_bounded(&self, options: &HashMap<String, String>) -> bool {
+        // a sequence ends the source when exhausted
+        options.contains_key("number-of-rows")
+            || options.iter().any(|(key, value)| key.starts_with("fields.") && key.ends_with(".kind")
+                && value.eq_ignore_ascii_case("sequence"))
+    }
+
+    fn create_source(&self, context: ConnectorContext) -> Result<BoxedSource, ExecuteError> {
+        Ok(Box::new(DataGenSource::new(context.column_ids, context.table, &context.options)))
+    }
+}
+
+impl DataGenSource {
+    pub fn new(column_ids: Vec<ColumnId>, table: Arc<TableCatalog>, options: &HashMap<String, String>) -> Self {
+        let columns: Vec<_> = column_ids.iter()
+            .map(|id| {
+                let column = table.get_column(*id).unwrap();
+                (column.name().to_string(), column.datatype())
+            })
+            .collect();
+        DataGenSource {
+            column_ids,
+            table,
+            options: DataGenOptions::parse(options, &columns),
+            generated: AtomicU64::new(0),
+        }
+    }
+}
+
+impl SourceConnector for DataGenSource {
+
+    /// Generates `rows-per-second` rows every second, until all rows are generated.
     #[try_stream(boxed, ok = Message, error = ExecuteError)]
     async fn read(&self) {
-        let defaultSize = 10;
-        for iter in 1..100 {
-            tokio::time::sleep(Duration::from_secs(1)).await;
-            let mut result = vec![];
-            for col_id in self.column_ids.iter() {
-                let col = self.table.get_column(*col_id);
-                let data_type = col.unwrap().datatype();
-                match data_type.kind() {
-                    DataTypeKind::Int32 => {
-                        let mut builder =
-                            ArrayBuilderImpl::with_capacity(defaultSize, &data_type);
-                        for i in 0..defaultSize {
-                            let value :i32 = i.try_into().unwrap();
-                            builder.push(&DataValue::Int32(value + (*col_id as i32)));
-                        }
-                        result.push(builder)
-                    },
-                    _ => todo!()
+        let types: Vec<_> = self.column_ids.iter()
+            .map(|id| self.table.get_column(*id).unwrap().datatype())
+            .collect();
+        let options = self.options.as_ref().map_err(|e| ExecuteError::InvalidOptions(e.clone()))?;
+        let total_rows = options.total_rows();
+        let mut interval = tokio::time::interval(Duration::from_secs(1));
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            let start = self.generated.load(Ordering::SeqCst);
+            let end = start + options.rows_per_second;
+            let end = total_rows.map_or(end, |total| end.min(total));
+            if start >= end {
+                break;
+            }
+            interval.tick().await;
+            for chunk_start in (start..end).step_by(PROCESSING_WINDOW_SIZE) {
+                let chunk_end = end.min(chunk_start + PROCESSING_WINDOW_SIZE as u64);
+                let mut builders: Vec<_> = types.iter()
+                    .map(|ty| ArrayBuilderImpl::with_capacity((chunk_end - chunk_start) as usize, ty))
+                    .collect();
+                for row in chunk_start..chunk_end {
+                    for (col, (builder, field)) in builders.iter_mut().zip(&options.fields).enumerate() {
+                        builder.push(&field.generate(options.seed, row, col));
+                    }
                 }
+                // the position is updated before the rows are emitted and a barrier may follow
+                self.generated.store(chunk_end, Ordering::SeqCst);
+                yield Message::Chunk(builders.into_iter().map(|builder| builder.finish())
+                    .collect::<DataChunk>());
             }
-            yield Message::Chunk(result.into_iter().map(|builder| builder.finish())
-                .collect::<DataChunk>());
         }
     }
 
-    fn write(&mut self, chunk: DataChunk) {
-        unimplemented!()
+    /// The position is the number of rows generated.
+    fn position(&self) -> Option<String> {
+        Some(self.generated.load(Ordering::SeqCst).to_string())
     }
 
-    fn on_receive_barrier(&mut self, barrier: Barrier) {
-        todo!()
+    fn seek(&mut self, position: &str) {
+        if let Ok(generated) = position.parse() {
+            self.generated.store(generated, Ordering::SeqCst);
+        }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_generate() {
+        let options: HashMap<_, _> = [
+            ("seed", "42"),
+            ("number-of-rows", "100"),
+            ("fields.id.kind", "sequence"),
+            ("fields.id.start", "5"),
+            ("fields.id.end", "14"),
+            ("fields.v.min", "-3"),
+            ("fields.v.max", "3"),
+            ("fields.s.length", "4"),
+        ].into_iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        let columns = [
+            ("id".to_string(), DataType::new_nullable(DataTypeKind::Int32)),
+            ("v".to_string(), DataType::new_nullable(DataTypeKind::Int32)),
+            ("s".to_string(), DataType::new_nullable(DataTypeKind::String)),
+        ];
+        let options = DataGenOptions::parse(&options, &columns).unwrap();
+        assert_eq!(options.total_rows(), Some(10));
+
+        for row in 0..10 {
+            assert_eq!(options.fields[0].generate(options.seed, row, 0), DataValue::Int32(5 + row as i32));
+            let DataValue::Int32(v) = options.fields[1].generate(options.seed, row, 1) else { panic!() };
+            assert!((-3..=3).contains(&v));
+            let DataValue::String(s) = options.fields[2].generate(options.seed, row, 2) else { panic!() };
+            assert_eq!(s.len(), 4);
+            // reproducible with the same seed
+            assert_eq!(options.fields[2].generate(options.seed, row, 2), DataValue::String(s));
+        }
+
+        let invalid: HashMap<_, _> = [("fields.id.kind".to_string(), "sequence".to_string())].into();
+        assert!(DataGenOptions::parse(&invalid, &columns).is_err());
+        let invalid: HashMap<_, _> = [("rows-per-second".to_string(), "0".to_string())].into();
+        assert!(DataGenOptions::parse(&invalid, &columns).is_err());
+        let invalid: HashMap<_, _> = [("fields.v.min", "10"), ("fields.v.max", "1")]
+            .into_iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        assert!(DataGenOptions::parse(&invalid, &columns).is_err());
+    }
+}
@@ -1,145 +1,779 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::collections::btree_map::Entry;
 use std::fs::File;
-use std::io::{Write};
-use std::sync::Arc;
+use std::io::{BufWriter, SeekFrom};
+use std::path::{Path, PathBuf};
+use futures::future::{self, BoxFuture, FutureExt};
+use std::sync::{Arc, Mutex};
 use futures_async_stream::try_stream;
 use crate::catalog::{ColumnId, TableCatalog};
-use crate::connector::StreamConnector;
+use crate::connector::{BoxedLookup, BoxedSink, BoxedSource, CommitFuture, ConnectorContext, ConnectorFactory,
+                       LookupCacheOptions, LookupConnector, SinkConnector, SourceConnector};
 use crate::stream::{Barrier, Message};
 use crate::connector::ExecuteError;
-use prettytable::csv::Writer;
 use std::io::BufRead;
-use std::time::Duration;
-use tokio::io::{AsyncBufReadExt, BufReader};
-use crate::array::{ArrayBuilderImpl, DataChunk};
-use crate::types::{DataType, DataTypeKind, DataValue};
+use std::time::{Duration, Instant};
+use regex::Regex;
+use tokio::io::{AsyncBufReadExt, AsyncSeekExt, BufReader};
+use crate::array::{DataChunk, Row, RowKind};
+use crate::types::{DataType, DataValue};
+use crate::connector::format::Format;
+use crate::connector::parse_duration;
+use crate::executor::PROCESSING_WINDOW_SIZE;
 use std::string::String;
 
+/// How often the source polls the files for new lines.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
 
-pub struct FileSystemConnector {
+
+/// Reads the file or the files in the directory `path`, with options:
+/// - `source.glob`: the pattern of the names of the files to read in the directory
+/// - `source.monitor-interval`: how often to scan the directory for new files,
+///   which is only scanned at start if not set
+/// - `source.batch-size`: the maximum number of rows in a chunk
+/// - `source.batch-timeout`: how long a partial chunk waits for more rows, `1s` by default
+pub struct FileSystemSource {
+    pub column_ids: Vec<ColumnId>,
+    pub table: Arc<TableCatalog>,
+    path: String,
+    format: Format,
+    options: SourceOptions,
+    /// The byte offsets after the rows emitted from each file.
+    offsets: Mutex<BTreeMap<PathBuf, u64>>,
+}
+
+/// Looks up the rows by keys in the file or the files in the directory `path`, which are
+/// read again by each lookup, so that the changes of the files are seen once the results
+/// cached by the lookup join expire. The options are:
+/// - `source.glob`: the pattern of the names of the files to read in the directory
+pub struct FileSystemLookup {
     pub column_ids: Vec<ColumnId>,
     pub table: Arc<TableCatalog>,
     path: String,
-    writer: Option<Writer<File>>,
+    format: Format,
+    glob: Option<Regex>,
+    /// The indexes of the columns compared with the lookup keys.
+    key_indexes: Vec<usize>,
+}
+
+/// Writes to the directory `path` with two-phase commit, with options:
+/// - `sink.changelog-mode`: `all` to write the change kind as the first field of each record,
+///   otherwise only inserts can be written
+pub struct FileSystemSink {
+    pub column_ids: Vec<ColumnId>,
+    pub table: Arc<TableCatalog>,
+    /// The writer of the in-progress file of the current epoch, opened on the first write.
+    writer: Option<BufWriter<File>>,
+    format: Format,
+    part_files: Arc<PartFiles>,
+    /// The epoch of the rows being written, which is ended by the next barrier.
+    epoch: u64,
+    changelog: bool,
 }
 
-impl FileSystemConnector {
-    pub fn new_source(column_ids: Vec<ColumnId>,
-               table: Arc<TableCatalog>, options: HashMap<String, String>) -> FileSystemConnector {
-        FileSystemConnector {
+/// Returns the `path` option, which is required.
+fn path_option(options: &HashMap<String, String>) -> Result<String, ExecuteError> {
+    options.get("path").cloned()
+        .ok_or_else(|| ExecuteError::InvalidOptions("option 'path' is required by connector filesystem".into()))
+}
+
+impl FileSystemSource {
+    pub fn new(column_ids: Vec<ColumnId>,
+               table: Arc<TableCatalog>, options: HashMap<String, String>) -> Result<FileSystemSource, ExecuteError> {
+        Ok(FileSystemSource {
             column_ids,
             table,
-            path: options.get("path").unwrap().clone(),
-            writer: None,
-        }
+            path: path_option(&options)?,
+            format: Format::from_options(&options).map_err(ExecuteError::InvalidOptions)?,
+            options: SourceOptions::from_options(&options).map_err(ExecuteError::InvalidOptions)?,
+            offsets: Mutex::new(BTreeMap::new()),
+        })
+    }
+}
+
+impl FileSystemLookup {
+    pub fn new(column_ids: Vec<ColumnId>, table: Arc<TableCatalog>,
+               options: HashMap<String, String>, key_indexes: Vec<usize>) -> Result<FileSystemLookup, ExecuteError> {
+        Ok(FileSystemLookup {
+            column_ids,
+            table,
+            path: path_option(&options)?,
+            format: Format::from_options(&options).map_err(ExecuteError::InvalidOptions)?,
+            glob: options.get("source.glob").map(|glob| glob_to_regex(glob)),
+            key_indexes,
+        })
     }
+}
 
-    pub fn new_sink(column_ids: Vec<ColumnId>,
-                    table: Arc<TableCatalog>, options: HashMap<String, String>) -> FileSystemConnector {
-        FileSystemConnector {
+impl FileSystemSink {
+    /// Creates a sink for a job, which is restored from the checkpoint of `restored_epoch` if given,
+    /// failing if the files left by the job can't be recovered.
+    pub fn new(column_ids: Vec<ColumnId>,
+               table: Arc<TableCatalog>, options: HashMap<String, String>,
+               job_id: &str, restored_epoch: Option<u64>) -> Result<FileSystemSink, ExecuteError> {
+        let format = Format::from_options(&options).map_err(ExecuteError::InvalidOptions)?;
+        let part_files = PartFiles {
+            dir: PathBuf::from(path_option(&options)?),
+            job_id: job_id.to_string(),
+            extension: format.extension(),
+            pending: Mutex::new(BTreeSet::new()),
+        };
+        part_files.recover(restored_epoch.unwrap_or(0))?;
+        Ok(FileSystemSink {
             column_ids,
             table,
-            path: options.get("path").unwrap().clone(),
-            writer: Some(Writer::from_path(options.get("path").unwrap().clone()).expect("s")),
+            writer: None,
+            format,
+            part_files: Arc::new(part_files),
+            epoch: restored_epoch.unwrap_or(0) + 1,
+            changelog: options.get("sink.changelog-mode")
+                .is_some_and(|mode| mode.eq_ignore_ascii_case("all")),
+        })
+    }
+}
+
+/// Creates the sources and sinks of `'connector' = 'filesystem'`.
+pub struct FileSystemFactory;
+
+impl ConnectorFactory for FileSystemFactory {
+    fn name(&self) -> &str {
+        "filesystem"
+    }
+
+    fn required_options(&self) -> &[&str] {
+        &["path"]
+    }
+
+    fn optional_options(&self) -> &[&str] {
+        &[
+            "format",
+            "source.glob",
+            "source.monitor-interval",
+            "source.batch-size",
+            "source.batch-timeout",
+            "sink.changelog-mode",
+            "csv.field-delimiter",
+            "csv.quote-character",
+            "csv.header",
+            "csv.null-literal",
+            "json.on-type-mismatch",
+            "lookup.cache.max-rows",
+            "lookup.cache.ttl",
+        ]
+    }
+
+    fn validate(&self, options: &HashMap<String, String>, _columns: &[(String, DataType)]) -> Result<(), String> {
+        Format::from_options(options)?;
+        SourceOptions::from_options(options)?;
+        LookupCacheOptions::from_options(options)?;
+        match options.get("sink.changelog-mode").map(|mode| mode.to_lowercase()).as_deref() {
+            None | Some("all") => Ok(()),
+            Some(mode) => Err(format!("invalid sink.changelog-mode: {}", mode)),
         }
     }
+
+    fn is_source(&self) -> bool {
+        true
+    }
+
+    fn is_sink(&self) -> bool {
+        true
+    }
+
+    fn is_lookup(&self) -> bool {
+        true
+    }
+
+    fn create_source(&self, context: ConnectorContext) -> Result<BoxedSource, ExecuteError> {
+        Ok(Box::new(FileSystemSource::new(context.column_ids, context.table, context.options)?))
+    }
+
+    fn create_sink(&self, context: ConnectorContext) -> Result<BoxedSink, ExecuteError> {
+        Ok(Box::new(FileSystemSink::new(context.column_ids, context.table, context.options,
+                                        context.job_id, context.restored_epoch)?))
+    }
+
+    fn create_lookup(&self, context: ConnectorContext, key_indexes: Vec<usize>) -> Result<BoxedLookup, ExecuteError> {
+        Ok(Box::new(FileSystemLookup::new(context.column_ids, context.table, context.options, key_indexes)?))
+    }
 }
 
-impl FileSystemConnector {
+struct SourceOptions {
+    glob: Option<Regex>,
+    monitor_interval: Option<Duration>,
+    batch_size: usize,
+    batch_timeout: Duration,
+}
 
-    fn build_chunk_from_line(&self, line: Vec<String>) -> DataChunk {
-        let mut result = vec![];
-        // row1: [col1, col2, xxx]
-        // row2: [col1, col2, xxx]
-        let rows: Vec<_> = line.into_iter().map(|line| line.split_terminator(",")
-            .map(|value| value.to_string())
-            .into_iter().collect::<Vec<_>>())
-            .collect();
-        for (col_index, col_id) in self.column_ids.iter().enumerate() {
-            let data_type = self.table.get_column(*col_id)
-                .unwrap().datatype();
-            result.push(FileSystemConnector::column_value_builder(data_type, col_index, &rows))
-        }
-        result.into_iter().map(|builder| builder.finish())
-            .collect::<DataChunk>()
-    }
-
-    fn column_value_builder(data_type: DataType,
-                            col_index: usize,
-                            rows: &Vec<Vec<String>>) -> ArrayBuilderImpl {
-        let rows_count = rows.len();
-        let mut builder =
-            ArrayBuilderImpl::with_capacity(rows_count, &data_type);
-        match data_type.kind() {
-            DataTypeKind::Int32 => {
-                for row_index in 0 .. rows.len()  {
-                    let item_value = rows.get(row_index)
-                        .unwrap().get(col_index).unwrap();
-                    builder.push(&DataValue::Int32(item_value.parse().expect("Failed to parse string to int32")))
-                }
+impl SourceOptions {
+    fn from_options(options: &HashMap<String, String>) -> Result<Self, String> {
+        let duration = |key: &str| options.get(key)
+            .map(|value| parse_duration(value).ok_or_else(|| format!("invalid {}: {}", key, value)))
+            .transpose();
+        Ok(SourceOptions {
+            glob: options.get("source.glob").map(|glob| glob_to_regex(glob)),
+            monitor_interval: duration("source.monitor-interval")?,
+            batch_size: match options.get("source.batch-size") {
+                Some(size) => size.parse().ok().filter(|size| *size > 0)
+                    .ok_or_else(|| format!("invalid source.batch-size: {}", size))?,
+                None => PROCESSING_WINDOW_SIZE,
             },
-            DataTypeKind::String => {
-                let mut builder =
-                    ArrayBuilderImpl::with_capacity(rows_count, &data_type);
-                for row_index in 0 .. rows.len() {
-                    let item_value = rows.get(row_index)
-                        .unwrap().get(col_index).unwrap();
-                    builder.push_str(item_value).expect("Fail to push build chunk");
+            batch_timeout: duration("source.batch-timeout")?.unwrap_or(Duration::from_secs(1)),
+        })
+    }
+}
+
+/// A file being read by the source, from which only complete records are emitted.
+struct SourceFile {
+    reader: BufReader<tokio::fs::File>,
+    /// The offset after the last complete record.
+    offset: u64,
+    /// The bytes of the incomplete last record.
+    partial: Vec<u8>,
+    /// The quote character, between which line breaks don't end a record.
+    quote: Option<u8>,
+    skip_header: bool,
+}
+
+impl SourceFile {
+    async fn open(path: &Path, offset: u64, format: &Format) -> std::io::Result<Self> {
+        let mut file = tokio::fs::File::open(path).await?;
+        file.seek(SeekFrom::Start(offset)).await?;
+        Ok(SourceFile {
+            reader: BufReader::new(file),
+            offset,
+            partial: vec![],
+            quote: format.quote(),
+            skip_header: format.has_header() && offset == 0,
+        })
+    }
+
+    /// Reads the next complete record without the line terminator, `None` if not available yet.
+    async fn next_line(&mut self) -> std::io::Result<Option<String>> {
+        loop {
+            let n = self.reader.read_until(b'\n', &mut self.partial).await?;
+            if n == 0 || !self.partial.ends_with(b"\n") {
+                return Ok(None);
+            }
+            // the line break is in a quoted field
+            if self.quote.is_some_and(|quote| self.partial.iter().filter(|b| **b == quote).count() % 2 == 1) {
+                continue;
+            }
+            self.offset += self.partial.len() as u64;
+            let line = String::from_utf8_lossy(&self.partial).trim_end_matches(['\n', '\r']).to_string();
+            self.partial.clear();
+            if self.skip_header {
+                self.skip_header = false;
+                continue;
+            }
+            return Ok(Some(line));
+        }
+    }
+
+    /// Reads the records to the end of the file, including the last one without a line break.
+    async fn read_to_end(mut self) -> std::io::Result<Vec<String>> {
+        let mut lines = vec![];
+        while let Some(line) = self.next_line().await? {
+            lines.push(line);
+        }
+        let last = String::from_utf8_lossy(&self.partial).trim_end_matches(['\n', '\r']).to_string();
+        if !last.is_empty() && !self.skip_header {
+            lines.push(last);
+        }
+        Ok(lines)
+    }
+}
+
+/// Converts a glob of file names, where `*` matches any characters and `?` matches one,
+/// to a regex.
+fn glob_to_regex(glob: &str) -> Regex {
+    let pattern = glob.split('*')
+        .map(|part| part.split('?').map(regex::escape).collect::<Vec<_>>().join("."))
+        .collect::<Vec<_>>()
+        .join(".*");
+    Regex::new(&format!("^{}$", pattern)).unwrap()
+}
+
+/// The output files of a sink in `dir`, one for each epoch, written with two-phase commit.
+///
+/// The rows of an epoch are written to a hidden in-progress file, which is flushed and
+/// pending when the sink receives the barrier, and renamed to the part file
+/// when the epoch is completed.
+struct PartFiles {
+    dir: PathBuf,
+    job_id: String,
+    /// The extension of the files, by the format.
+    extension: &'static str,
+    /// The epochs whose files are flushed but not committed.
+    pending: Mutex<BTreeSet<u64>>,
+}
+
+impl PartFiles {
+    fn in_progress_path(&self, epoch: u64) -> PathBuf {
+        self.dir.join(format!(".part-{}-{}.{}.inprogress", self.job_id, epoch, self.extension))
+    }
+
+    fn part_path(&self, epoch: u64) -> PathBuf {
+        self.dir.join(format!("part-{}-{}.{}", self.job_id, epoch, self.extension))
+    }
+
+    /// Recovers the files left by the job before it's restored from `restored_epoch`.
+    /// The files of the epochs in the checkpoint are committed and the others are discarded,
+    /// including the ones committed after the checkpoint, whose rows are written again
+    /// by the restored job.
+    fn recover(&self, restored_epoch: u64) -> std::io::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let in_progress = (format!(".part-{}-", self.job_id), format!(".{}.inprogress", self.extension));
+        let committed = (format!("part-{}-", self.job_id), format!(".{}", self.extension));
+        let epoch_of = |name: &str, (prefix, suffix): &(String, String)| -> Option<u64> {
+            name.strip_prefix(prefix.as_str())?.strip_suffix(suffix.as_str())?.parse().ok()
+        };
+        for entry in std::fs::read_dir(&self.dir)? {
+            let name = entry?.file_name();
+            let Some(name) = name.to_str() else {
+                continue;
+            };
+            if let Some(epoch) = epoch_of(name, &in_progress) {
+                if epoch <= restored_epoch {
+                    std::fs::rename(self.in_progress_path(epoch), self.part_path(epoch))?;
+                } else {
+                    std::fs::remove_file(self.in_progress_path(epoch))?;
                 }
+            } else if let Some(epoch) = epoch_of(name, &committed).filter(|epoch| *epoch > restored_epoch) {
+                std::fs::remove_file(self.part_path(epoch))?;
             }
-            _ => todo!()
         }
-        builder
+        Ok(())
     }
 }
 
+impl PartFiles {
+    /// Commits the output of the epochs up to `epoch`.
+    fn commit(&self, epoch: u64) -> std::io::Result<()> {
+        let mut pending = self.pending.lock().unwrap();
+        while let Some(&first) = pending.first() {
+            if first > epoch {
+                break;
+            }
+            std::fs::rename(self.in_progress_path(first), self.part_path(first))?;
+            pending.remove(&first);
+        }
+        Ok(())
+    }
+}
 
-impl StreamConnector for FileSystemConnector {
+fn column_types(table: &TableCatalog, column_ids: &[ColumnId]) -> Vec<DataType> {
+    column_ids.iter()
+        .map(|col_id| table.get_column(*col_id).unwrap().datatype())
+        .collect()
+}
 
+fn column_names(table: &TableCatalog, column_ids: &[ColumnId]) -> Vec<String> {
+    column_ids.iter()
+        .map(|col_id| table.get_column(*col_id).unwrap().name().to_string())
+        .collect()
+}
+
+/// Lists the files to read: `path` itself, or the files matching the glob if it's a directory.
+async fn list_files(path: &str, glob: Option<&Regex>) -> std::io::Result<Vec<PathBuf>> {
+    let path = PathBuf::from(path);
+    if !tokio::fs::metadata(&path).await?.is_dir() {
+        return Ok(vec![path]);
+    }
+    let mut files = vec![];
+    let mut entries = tokio::fs::read_dir(&path).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name();
+        let matched = glob.map_or(true, |glob| name.to_str().is_some_and(|name| glob.is_match(name)));
+        if matched && entry.file_type().await?.is_file() {
+            files.push(entry.path());
+        }
+    }
+    Ok(files)
+}
+
+impl SourceConnector for FileSystemSource {
+
+    /// Tails the files, emitting a chunk when it's full or has waited for the batch timeout.
     #[try_stream(boxed, ok = Message, error = ExecuteError)]
     async fn read(&self) {
-        let chunk_size = 10;
-        let file = tokio::fs::File::open(self.path.as_str()).await.expect("Fail to open file ");
-        // create reader using file
-        let reader = BufReader::new(file);
-        // get iterator over lines
-        let mut lines = reader.lines();
+        let options = &self.options;
+        let names = column_names(&self.table, &self.column_ids);
+        let types = column_types(&self.table, &self.column_ids);
+        let mut files: BTreeMap<PathBuf, SourceFile> = BTreeMap::new();
+        let mut last_scan: Option<Instant> = None;
         let mut rows = vec![];
+        let mut batch_start = Instant::now();
         loop {
-            match lines.next_line().await.expect("Fail to get next line") {
-                Some(line) => {
-                    rows.push(line);
-                    if rows.len() > chunk_size {
-                        yield Message::Chunk(self.build_chunk_from_line(rows));
-                        rows = vec![];
+            let scan = match (last_scan, options.monitor_interval) {
+                (None, _) => true,
+                (Some(last_scan), Some(interval)) => last_scan.elapsed() >= interval,
+                (Some(_), None) => false,
+            };
+            if scan {
+                for path in list_files(&self.path, options.glob.as_ref()).await? {
+                    if let Entry::Vacant(entry) = files.entry(path) {
+                        let offset = self.offsets.lock().unwrap().get(entry.key()).copied().unwrap_or(0);
+                        let file = SourceFile::open(entry.key(), offset, &self.format).await?;
+                        entry.insert(file);
                     }
                 }
-                None => {
-                    // No new line available; wait before trying again
-                    tokio::time::sleep(Duration::from_secs(1)).await;
+                last_scan = Some(Instant::now());
+            }
+
+            let mut has_new_rows = false;
+            for file in files.values_mut() {
+                while rows.len() < options.batch_size {
+                    let Some(line) = file.next_line().await? else {
+                        break;
+                    };
+                    if rows.is_empty() {
+                        batch_start = Instant::now();
+                    }
+                    rows.push(line);
+                    has_new_rows = true;
                 }
             }
+
+            if !rows.is_empty() && (rows.len() >= options.batch_size || batch_start.elapsed() >= options.batch_timeout) {
+                // the offsets are updated before the rows are emitted and a barrier may follow
+                *self.offsets.lock().unwrap() = files.iter()
+                    .map(|(path, file)| (path.clone(), file.offset))
+                    .collect();
+                yield Message::Chunk(self.format.parse_chunk(&std::mem::take(&mut rows), &names, &types)?);
+            } else if !has_new_rows {
+                // no new line available, wait before trying again
+                tokio::time::sleep(POLL_INTERVAL.min(options.batch_timeout)).await;
+            }
         }
     }
 
-    fn write(&mut self, chunk: DataChunk) {
-        if let Some(ref mut writer) = &mut self.writer {
-            if let Some(ref mut writer) = &mut self.writer {
+    /// The position is the offsets of the files, as `<offset>:<path>` separated by tabs.
+    fn position(&self) -> Option<String> {
+        let offsets = self.offsets.lock().unwrap();
+        Some(offsets.iter()
+            .map(|(path, offset)| format!("{}:{}", offset, path.display()))
+            .collect::<Vec<_>>()
+            .join("\t"))
+    }
+
+    fn seek(&mut self, position: &str) {
+        *self.offsets.lock().unwrap() = position.split('\t')
+            .filter_map(|entry| {
+                let (offset, path) = entry.split_once(':')?;
+                Some((PathBuf::from(path), offset.parse().ok()?))
+            })
+            .collect();
+    }
+}
+
+impl FileSystemLookup {
+    async fn lookup_rows(&self, keys: &[Row]) -> Result<Vec<Vec<Row>>, ExecuteError> {
+        let names = column_names(&self.table, &self.column_ids);
+        let types = column_types(&self.table, &self.column_ids);
+        let indexes: HashMap<&Row, usize> = keys.iter().enumerate().map(|(i, key)| (key, i)).collect();
+        let mut rows = vec![vec![]; keys.len()];
+        for path in list_files(&self.path, self.glob.as_ref()).await? {
+            let records = SourceFile::open(&path, 0, &self.format).await?.read_to_end().await?;
+            for records in records.chunks(PROCESSING_WINDOW_SIZE) {
+                let chunk = self.format.parse_chunk(records, &names, &types)?;
                 for i in 0..chunk.cardinality() {
-                    let row: Vec<_> = chunk.arrays.iter().map(|a| a.get(i).to_string()).collect();
-                    writer.write_record(&row).expect("Fail to write record.");
+                    let row = chunk.row(i);
+                    let key: Row = self.key_indexes.iter().map(|idx| row.get(*idx)).collect();
+                    if let Some(idx) = indexes.get(&key) {
+                        rows[*idx].push(row.to_owned());
+                    }
                 }
             }
-        } else {
-            panic!("Fail to write for writer is not initialized")
         }
+        Ok(rows)
+    }
+}
+
+impl LookupConnector for FileSystemLookup {
+    /// Scans the files for the rows of all the keys at once.
+    fn lookup<'a>(&'a self, keys: &'a [Row]) -> BoxFuture<'a, Result<Vec<Vec<Row>>, ExecuteError>> {
+        self.lookup_rows(keys).boxed()
     }
+}
 
-    fn on_receive_barrier(&mut self, barrier: Barrier) {
-        if let Some(ref mut writer) = &mut self.writer {
-            writer.flush().expect("Fail to flush.");
+impl FileSystemSink {
+    fn write_chunk(&mut self, chunk: DataChunk) -> Result<(), ExecuteError> {
+        if chunk.cardinality() == 0 {
+            return Ok(());
+        }
+        let mut names = column_names(&self.table, &self.column_ids);
+        if self.changelog {
+            names.insert(0, "op".into());
+        }
+        let writer = match &mut self.writer {
+            Some(writer) => writer,
+            None => {
+                let file = File::create(self.part_files.in_progress_path(self.epoch))?;
+                let mut writer = BufWriter::new(file);
+                self.format.write_header(&mut writer, &names)?;
+                self.writer.insert(writer)
+            }
+        };
+        let mut rows = Vec::with_capacity(chunk.cardinality());
+        for i in 0..chunk.cardinality() {
+            let mut row: Vec<_> = chunk.arrays.iter().map(|a| a.get(i)).collect();
+            match (self.changelog, chunk.op(i)) {
+                (true, op) => row.insert(0, DataValue::String(op.to_string())),
+                (false, RowKind::Insert) => {}
+                (false, op) => return Err(ExecuteError::Sink(format!(
+                    "can't write {} row to an append-only file, \
+                     set 'sink.changelog-mode' = 'all' to write changelog", op))),
+            }
+            rows.push(row);
         }
+        self.format.write_rows(writer, &names, rows.into_iter())?;
+        Ok(())
+    }
+
+    /// Flushes the in-progress file of the current epoch, which is pending to be committed.
+    fn flush_epoch(&mut self) -> Result<(), ExecuteError> {
+        if let Some(writer) = self.writer.take() {
+            let file = writer.into_inner().map_err(|e| e.into_error())?;
+            file.sync_all()?;
+            self.part_files.pending.lock().unwrap().insert(self.epoch);
+        }
+        Ok(())
     }
 }
 
+impl SinkConnector for FileSystemSink {
+    fn write(&mut self, chunk: DataChunk) -> BoxFuture<'_, Result<(), ExecuteError>> {
+        future::ready(self.write_chunk(chunk)).boxed()
+    }
 
+    /// Flushes the in-progress file of the epoch ended by the barrier,
+    /// which is committed when the epoch is completed.
+    fn on_barrier(&mut self, barrier: &Barrier) -> BoxFuture<'_, Result<Option<CommitFuture>, ExecuteError>> {
+        if self.epoch != barrier.epoch {
+            let error = ExecuteError::Sink(format!(
+                "the barrier of epoch {} is received in epoch {}", barrier.epoch, self.epoch));
+            return future::ready(Err(error)).boxed();
+        }
+        let epoch = barrier.epoch;
+        let result = self.flush_epoch().map(|_| {
+            let part_files = self.part_files.clone();
+            let commit = async move { Ok(part_files.commit(epoch)?) };
+            Some(commit.boxed())
+        });
+        self.epoch = epoch + 1;
+        future::ready(result).boxed()
+    }
+
+    /// Commits all the files written, as the input is finished.
+    fn close(&mut self) -> BoxFuture<'_, Result<(), ExecuteError>> {
+        let result = self.flush_epoch()
+            .and_then(|_| Ok(self.part_files.commit(self.epoch)?));
+        future::ready(result).boxed()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use futures::stream::{BoxStream, StreamExt};
+    use crate::catalog::{DatabaseCatalog, DEFAULT_SCHEMA_NAME};
+    use crate::types::DataTypeKind;
+    use super::*;
+
+    /// Returns the source of the csv files of `t(a int, b string)` at `path`.
+    fn source(path: &Path, options: &[(&str, &str)]) -> FileSystemSource {
+        let catalog = DatabaseCatalog::new();
+        let schema = catalog.get_schema_by_name(DEFAULT_SCHEMA_NAME).unwrap();
+        let table = schema.get_table(schema.add_table("t").unwrap()).unwrap();
+        let column_ids = vec![
+            table.add_column("a", DataType::new_nullable(DataTypeKind::Int32).to_column()).unwrap(),
+            table.add_column("b", DataType::new_nullable(DataTypeKind::String).to_column()).unwrap(),
+        ];
+        let mut options: HashMap<String, String> = options.iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        options.insert("path".into(), path.display().to_string());
+        options.insert("format".into(), "csv".into());
+        FileSystemSource::new(column_ids, table, options).unwrap()
+    }
+
+    /// Returns the rows of the next chunk read, `None` if none is read within the timeout.
+    async fn next_rows(stream: &mut BoxStream<'_, Result<Message, ExecuteError>>, timeout: Duration)
+        -> Option<Vec<Row>> {
+        let msg = tokio::time::timeout(timeout, stream.next()).await.ok()?;
+        let Message::Chunk(chunk) = msg.unwrap().unwrap() else {
+            panic!("not a chunk");
+        };
+        Some((0..chunk.cardinality()).map(|i| chunk.row(i).to_owned()).collect())
+    }
+
+    fn row(a: i32, b: &str) -> Row {
+        vec![DataValue::Int32(a), DataValue::String(b.into())]
+    }
+
+    fn append(path: &Path, content: &str) {
+        let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path).unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_source_offsets() {
+        let dir = tempfile::tempdir().unwrap();
+        let (a, b) = (dir.path().join("a.csv"), dir.path().join("b.csv"));
+        append(&a, "1,x\n2,y\n");
+        append(&b, "3,z\n");
+        let options = [("source.batch-timeout", "0ms")];
+        let first = source(dir.path(), &options);
+        let mut stream = first.read();
+        assert_eq!(next_rows(&mut stream, Duration::from_secs(5)).await,
+                   Some(vec![row(1, "x"), row(2, "y"), row(3, "z")]));
+        // the offset of each file is after its rows emitted
+        let position = first.position().unwrap();
+        assert_eq!(position, format!("8:{}\t4:{}", a.display(), b.display()));
+
+        // the rows appended are read from the end of the rows emitted
+        append(&a, "4,w\n");
+        assert_eq!(next_rows(&mut stream, Duration::from_secs(5)).await, Some(vec![row(4, "w")]));
+        drop(stream);
+
+        // a source resumed from the position only reads the rows after it
+        let mut resumed = source(dir.path(), &options);
+        resumed.seek(&position);
+        let mut stream = resumed.read();
+        assert_eq!(next_rows(&mut stream, Duration::from_secs(5)).await, Some(vec![row(4, "w")]));
+        assert_eq!(next_rows(&mut stream, Duration::from_millis(300)).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_source_batch() {
+        let dir = tempfile::tempdir().unwrap();
+        append(&dir.path().join("a.csv"), "1,x\n2,y\n3,z\n");
+        let source = source(dir.path(), &[("source.batch-size", "2"), ("source.batch-timeout", "300ms")]);
+        let mut stream = source.read();
+        // a full chunk is emitted at once, and a partial one once it has waited for the timeout
+        let start = Instant::now();
+        assert_eq!(next_rows(&mut stream, Duration::from_secs(5)).await, Some(vec![row(1, "x"), row(2, "y")]));
+        assert!(start.elapsed() < Duration::from_millis(300));
+        assert_eq!(next_rows(&mut stream, Duration::from_secs(5)).await, Some(vec![row(3, "z")]));
+        assert!(start.elapsed() >= Duration::from_millis(300));
+    }
+
+    #[tokio::test]
+    async fn test_source_monitor_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        append(&dir.path().join("a.csv"), "1,x\n");
+        let source = source(dir.path(), &[
+            ("source.batch-timeout", "0ms"),
+            ("source.monitor-interval", "100ms"),
+            ("source.glob", "*.csv"),
+        ]);
+        let mut stream = source.read();
+        assert_eq!(next_rows(&mut stream, Duration::from_secs(5)).await, Some(vec![row(1, "x")]));
+
+        // the new files matching the glob are picked up by the next scan
+        append(&dir.path().join("b.txt"), "2,y\n");
+        append(&dir.path().join("c.csv"), "3,z\n");
+        assert_eq!(next_rows(&mut stream, Duration::from_secs(5)).await, Some(vec![row(3, "z")]));
+    }
+
+    #[tokio::test]
+    async fn test_source_quoted_line_breaks() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.csv");
+        append(&path, "1,\"first\n");
+        let source = source(&path, &[("source.batch-timeout", "0ms")]);
+        let mut stream = source.read();
+        // the record isn't complete until the closing quote and line break are written
+        assert_eq!(next_rows(&mut stream, Duration::from_millis(300)).await, None);
+        assert_eq!(source.position().unwrap(), "");
+        append(&path, "second\"\n2,\"a,b\"\n");
+        assert_eq!(next_rows(&mut stream, Duration::from_secs(5)).await,
+                   Some(vec![row(1, "first\nsecond"), row(2, "a,b")]));
+        assert_eq!(source.position().unwrap(), format!("25:{}", path.display()));
+    }
+
+    #[test]
+    fn test_part_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let part_files = PartFiles {
+            dir: dir.path().to_path_buf(),
+            job_id: "job".into(),
+            extension: "json",
+            pending: Mutex::new(BTreeSet::new()),
+        };
+        for epoch in 1..=3 {
+            std::fs::write(part_files.in_progress_path(epoch), "1,2\n").unwrap();
+            part_files.pending.lock().unwrap().insert(epoch);
+        }
+        part_files.commit(2).unwrap();
+        assert!(dir.path().join("part-job-1.json").exists() && part_files.part_path(2).exists());
+        assert!(part_files.in_progress_path(3).exists() && !part_files.part_path(3).exists());
+
+        // the file of epoch 3 is discarded when restored from epoch 2
+        part_files.recover(2).unwrap();
+        assert!(!part_files.in_progress_path(3).exists() && !part_files.part_path(3).exists());
+
+        // the file of epoch 4 is committed when restored from epoch 4
+        std::fs::write(part_files.in_progress_path(4), "1,2\n").unwrap();
+        part_files.recover(4).unwrap();
+        assert!(dir.path().join("part-job-4.json").exists());
+    }
+
+    #[test]
+    fn test_part_files_restored_from_older_checkpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let part_files = |job_id: &str| PartFiles {
+            dir: dir.path().to_path_buf(),
+            job_id: job_id.into(),
+            extension: "csv",
+            pending: Mutex::new(BTreeSet::new()),
+        };
+        let (job, other) = (part_files("job"), part_files("other"));
+        for epoch in 1..=3 {
+            std::fs::write(job.part_path(epoch), "1\n").unwrap();
+        }
+        std::fs::write(job.in_progress_path(4), "1\n").unwrap();
+        std::fs::write(other.part_path(3), "1\n").unwrap();
+
+        // the parts committed after the checkpoint of epoch 1 are written again by the restored job
+        job.recover(1).unwrap();
+        let mut files: Vec<_> = std::fs::read_dir(dir.path()).unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        files.sort();
+        assert_eq!(files, vec!["part-job-1.csv", "part-other-3.csv"]);
+    }
+
+    #[tokio::test]
+    async fn test_sink_barrier_of_other_epoch() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = DatabaseCatalog::new();
+        let schema = catalog.get_schema_by_name(DEFAULT_SCHEMA_NAME).unwrap();
+        let table = schema.get_table(schema.add_table("t").unwrap()).unwrap();
+        let column_ids = vec![table.add_column("a", DataType::new_nullable(DataTypeKind::Int32).to_column()).unwrap()];
+        let options = HashMap::from([
+            ("path".to_string(), dir.path().display().to_string()),
+            ("format".to_string(), "csv".to_string()),
+        ]);
+        let mut sink = FileSystemSink::new(column_ids, table, options, "job", None).unwrap();
+        sink.write(DataChunk::single(1)).await.unwrap();
+
+        // the rows of epoch 1 aren't committed as epoch 2
+        let result = sink.on_barrier(&Barrier { epoch: 2, timestamp: 0 }).await;
+        assert!(matches!(result, Err(ExecuteError::Sink(_))));
+        let commit = sink.on_barrier(&Barrier { epoch: 1, timestamp: 0 }).await.unwrap().unwrap();
+        commit.await.unwrap();
+        assert_eq!(std::fs::read_to_string(dir.path().join("part-job-1.csv")).unwrap(), "1\n");
+    }
+
+    #[test]
+    fn test_glob_to_regex() {
+        let glob = glob_to_regex("part-?.csv*");
+        assert!(glob.is_match("part-1.csv"));
+        assert!(glob.is_match("part-2.csv.gz"));
+        assert!(!glob.is_match("part-10.csv"));
+        assert!(!glob.is_match("part-1xcsv"));
+    }
+}
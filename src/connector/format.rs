@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+use std::io::Write;
+use crate::array::DataChunk;
+use crate::connector::csv::CsvFormat;
+use crate::connector::json::JsonFormat;
+use crate::executor::ExecuteError;
+use crate::types::{DataType, DataValue};
+
+/// The format of files, selected by `'format' = 'csv' | 'json'`, `csv` by default.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Format {
+    Csv(CsvFormat),
+    Json(JsonFormat),
+}
+
+impl Format {
+    /// Parses the options of the format, returning an error message if invalid.
+    pub fn from_options(options: &HashMap<String, String>) -> Result<Self, String> {
+        match options.get("format").map(|format| format.to_lowercase()).as_deref() {
+            None | Some("csv") => CsvFormat::from_options(options).map(Format::Csv),
+            Some("json") => JsonFormat::from_options(options).map(Format::Json),
+            Some(format) => Err(format!("unsupported format: {}", format)),
+        }
+    }
+
+    /// Returns the extension of the files in the format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Csv(_) => "csv",
+            Format::Json(_) => "json",
+        }
+    }
+
+    /// Returns the quote character, between which line breaks don't end a record.
+    pub fn quote(&self) -> Option<u8> {
+        match self {
+            Format::Csv(csv) => Some(csv.quote),
+            Format::Json(_) => None,
+        }
+    }
+
+    /// Returns whether each file starts with a header line.
+    pub fn has_header(&self) -> bool {
+        matches!(self, Format::Csv(csv) if csv.header)
+    }
+
+    /// Parses the records into a chunk of the columns.
+    pub fn parse_chunk(&self, records: &[String], names: &[String], types: &[DataType])
+        -> Result<DataChunk, ExecuteError> {
+        match self {
+            Format::Csv(csv) => csv.parse_chunk(records, types),
+            Format::Json(json) => json.parse_chunk(records, names, types),
+        }
+    }
+
+    pub fn write_header(&self, out: &mut impl Write, names: &[String]) -> std::io::Result<()> {
+        match self {
+            Format::Csv(csv) if csv.header => csv.write_rows(out, std::iter::once(
+                names.iter().map(|name| DataValue::String(name.clone())).collect())),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn write_rows(&self, out: &mut impl Write, names: &[String],
+                      rows: impl Iterator<Item = Vec<DataValue>>) -> std::io::Result<()> {
+        match self {
+            Format::Csv(csv) => csv.write_rows(out, rows),
+            Format::Json(json) => json.write_rows(out, names, rows),
+        }
+    }
+}
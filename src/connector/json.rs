@@ -0,0 +1,388 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::Write;
+use crate::array::{ArrayBuilderImpl, DataChunk};
+use crate::executor::ExecuteError;
+use crate::types::{timestamp, DataType, DataTypeKind, DataValue};
+
+/// What to do with a row whose field can't be converted to the type of its column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnTypeMismatch {
+    /// Fails the job.
+    Fail,
+    /// Drops the row, as well as lines that aren't JSON objects.
+    Skip,
+    /// Reads the field as NULL.
+    Null,
+}
+
+/// The JSON Lines format of files, one JSON object per line whose fields are mapped
+/// to the columns by name, with options:
+/// - `json.on-type-mismatch`: `fail` by default, `skip` or `null`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonFormat {
+    pub on_type_mismatch: OnTypeMismatch,
+}
+
+impl JsonFormat {
+    /// Parses the options, returning an error message if invalid.
+    pub fn from_options(options: &HashMap<String, String>) -> Result<Self, String> {
+        let on_type_mismatch = match options.get("json.on-type-mismatch").map(|v| v.to_lowercase()).as_deref() {
+            None | Some("fail") => OnTypeMismatch::Fail,
+            Some("skip") => OnTypeMismatch::Skip,
+            Some("null") => OnTypeMismatch::Null,
+            Some(value) => return Err(format!("invalid json.on-type-mismatch: {}", value)),
+        };
+        Ok(JsonFormat { on_type_mismatch })
+    }
+
+    /// Parses the objects of the lines into a chunk of the columns, where missing fields are NULL.
+    pub fn parse_chunk(&self, lines: &[String], names: &[String], types: &[DataType])
+        -> Result<DataChunk, ExecuteError> {
+        let mut builders: Vec<_> = types.iter()
+            .map(|ty| ArrayBuilderImpl::with_capacity(lines.len(), ty))
+            .collect();
+        'rows: for line in lines.iter().filter(|line| !line.trim().is_empty()) {
+            let malformed = |reason: String| ExecuteError::MalformedRow(line.clone(), reason);
+            let fields = match JsonValue::parse(line) {
+                Ok(JsonValue::Object(fields)) => fields,
+                _ if self.on_type_mismatch == OnTypeMismatch::Skip => continue,
+                Ok(_) => return Err(malformed("not a JSON object".into())),
+                Err(e) => return Err(malformed(e)),
+            };
+            let mut row = Vec::with_capacity(types.len());
+            for (name, ty) in names.iter().zip(types) {
+                let field = fields.iter().find(|(key, _)| key == name)
+                    .or_else(|| fields.iter().find(|(key, _)| key.eq_ignore_ascii_case(name)));
+                let value = match field {
+                    None => DataValue::Null,
+                    Some((_, value)) => match (value.to_data_value(&ty.kind()), self.on_type_mismatch) {
+                        (Some(value), _) => value,
+                        (None, OnTypeMismatch::Fail) => return Err(malformed(format!(
+                            "field {} of {} can't be converted to {}", name, value, ty.kind()))),
+                        (None, OnTypeMismatch::Skip) => continue 'rows,
+                        (None, OnTypeMismatch::Null) => DataValue::Null,
+                    },
+                };
+                row.push(value);
+            }
+            for (builder, value) in builders.iter_mut().zip(&row) {
+                builder.push(value);
+            }
+        }
+        Ok(builders.into_iter().map(|builder| builder.finish()).collect())
+    }
+
+    /// Writes each row as a JSON object of the columns in a line.
+    pub fn write_rows(&self, out: &mut impl Write, names: &[String],
+                      rows: impl Iterator<Item = Vec<DataValue>>) -> std::io::Result<()> {
+        for row in rows {
+            let mut line = String::from("{");
+            for (i, (name, value)) in names.iter().zip(&row).enumerate() {
+                if i > 0 {
+                    line.push(',');
+                }
+                write_json_string(&mut line, name);
+                line.push(':');
+                match value {
+                    DataValue::Null => line.push_str("null"),
+                    DataValue::Bool(v) => write!(line, "{}", v).unwrap(),
+                    DataValue::Int32(v) => write!(line, "{}", v).unwrap(),
+                    value => write_json_string(&mut line, &value.to_string()),
+                }
+            }
+            line.push_str("}\n");
+            out.write_all(line.as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+fn write_json_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// A parsed JSON value, where numbers are kept as text to be converted exactly.
+#[derive(Debug, Clone, PartialEq)]
+enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl std::fmt::Display for JsonValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JsonValue::Null => write!(f, "null"),
+            JsonValue::Bool(v) => write!(f, "{}", v),
+            JsonValue::Number(v) => write!(f, "{}", v),
+            JsonValue::String(v) => {
+                let mut s = String::new();
+                write_json_string(&mut s, v);
+                write!(f, "{}", s)
+            }
+            JsonValue::Array(values) => {
+                write!(f, "[")?;
+                for (i, value) in values.iter().enumerate() {
+                    write!(f, "{}{}", if i > 0 { "," } else { "" }, value)?;
+                }
+                write!(f, "]")
+            }
+            JsonValue::Object(fields) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    let mut s = String::new();
+                    write_json_string(&mut s, key);
+                    write!(f, "{}{}:{}", if i > 0 { "," } else { "" }, s, value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+impl JsonValue {
+    fn parse(s: &str) -> Result<Self, String> {
+        let mut parser = JsonParser { s: s.as_bytes(), pos: 0 };
+        let value = parser.parse_value()?;
+        parser.skip_whitespace();
+        if parser.pos != s.len() {
+            return Err(format!("unexpected character at {}", parser.pos));
+        }
+        Ok(value)
+    }
+
+    /// Converts the value to a column of `kind`, `None` if the types mismatch.
+    fn to_data_value(&self, kind: &DataTypeKind) -> Option<DataValue> {
+        Some(match (kind, self) {
+            (_, JsonValue::Null) => DataValue::Null,
+            (DataTypeKind::Bool, JsonValue::Bool(v)) => DataValue::Bool(*v),
+            (DataTypeKind::Int32, JsonValue::Number(v)) => DataValue::Int32(v.parse().ok()?),
+            (DataTypeKind::String, JsonValue::String(v)) => DataValue::String(v.clone()),
+            (DataTypeKind::String, value) => DataValue::String(value.to_string()),
+            (DataTypeKind::Timestamp, JsonValue::String(v)) => DataValue::Timestamp(timestamp::parse_timestamp(v).ok()?),
+            (DataTypeKind::Timestamp, JsonValue::Number(v)) => DataValue::Timestamp(v.parse().ok()?),
+            (DataTypeKind::Interval, JsonValue::String(v)) => DataValue::Interval(timestamp::parse_interval(v, None).ok()?),
+            (DataTypeKind::Interval, JsonValue::Number(v)) => DataValue::Interval(v.parse().ok()?),
+            _ => return None,
+        })
+    }
+}
+
+struct JsonParser<'a> {
+    s: &'a [u8],
+    pos: usize,
+}
+
+impl JsonParser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.pos < self.s.len() && self.s[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, c: u8) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.s.get(self.pos) != Some(&c) {
+            return Err(format!("expected '{}' at {}", c as char, self.pos));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn parse_value(&mut self) -> Result<JsonValue, String> {
+        self.skip_whitespace();
+        let keyword = |parser: &mut Self, word: &str, value: JsonValue| {
+            if parser.s[parser.pos..].starts_with(word.as_bytes()) {
+                parser.pos += word.len();
+                Ok(value)
+            } else {
+                Err(format!("invalid value at {}", parser.pos))
+            }
+        };
+        match self.s.get(self.pos) {
+            None => Err("unexpected end".into()),
+            Some(b'{') => {
+                self.pos += 1;
+                let mut fields = vec![];
+                self.skip_whitespace();
+                if self.s.get(self.pos) == Some(&b'}') {
+                    self.pos += 1;
+                    return Ok(JsonValue::Object(fields));
+                }
+                loop {
+                    self.skip_whitespace();
+                    let key = self.parse_string()?;
+                    self.expect(b':')?;
+                    fields.push((key, self.parse_value()?));
+                    self.skip_whitespace();
+                    match self.s.get(self.pos) {
+                        Some(b',') => self.pos += 1,
+                        Some(b'}') => {
+                            self.pos += 1;
+                            return Ok(JsonValue::Object(fields));
+                        }
+                        _ => return Err(format!("expected ',' or '}}' at {}", self.pos)),
+                    }
+                }
+            }
+            Some(b'[') => {
+                self.pos += 1;
+                let mut values = vec![];
+                self.skip_whitespace();
+                if self.s.get(self.pos) == Some(&b']') {
+                    self.pos += 1;
+                    return Ok(JsonValue::Array(values));
+                }
+                loop {
+                    values.push(self.parse_value()?);
+                    self.skip_whitespace();
+                    match self.s.get(self.pos) {
+                        Some(b',') => self.pos += 1,
+                        Some(b']') => {
+                            self.pos += 1;
+                            return Ok(JsonValue::Array(values));
+                        }
+                        _ => return Err(format!("expected ',' or ']' at {}", self.pos)),
+                    }
+                }
+            }
+            Some(b'"') => Ok(JsonValue::String(self.parse_string()?)),
+            Some(b't') => keyword(self, "true", JsonValue::Bool(true)),
+            Some(b'f') => keyword(self, "false", JsonValue::Bool(false)),
+            Some(b'n') => keyword(self, "null", JsonValue::Null),
+            Some(b'-' | b'0'..=b'9') => {
+                let start = self.pos;
+                self.pos += 1;
+                while self.s.get(self.pos).is_some_and(|c| matches!(c, b'0'..=b'9' | b'.' | b'e' | b'E' | b'+' | b'-')) {
+                    self.pos += 1;
+                }
+                let number = std::str::from_utf8(&self.s[start..self.pos]).unwrap();
+                number.parse::<f64>().map_err(|_| format!("invalid number {}", number))?;
+                Ok(JsonValue::Number(number.to_string()))
+            }
+            Some(_) => Err(format!("invalid value at {}", self.pos)),
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, String> {
+        if self.s.get(self.pos) != Some(&b'"') {
+            return Err(format!("expected string at {}", self.pos));
+        }
+        self.pos += 1;
+        let mut bytes = vec![];
+        loop {
+            match self.s.get(self.pos) {
+                None => return Err("unterminated string".into()),
+                Some(b'"') => {
+                    self.pos += 1;
+                    return String::from_utf8(bytes).map_err(|e| e.to_string());
+                }
+                Some(b'\\') => {
+                    let c = match self.s.get(self.pos + 1) {
+                        Some(b'"') => '"',
+                        Some(b'\\') => '\\',
+                        Some(b'/') => '/',
+                        Some(b'b') => '\u{8}',
+                        Some(b'f') => '\u{c}',
+                        Some(b'n') => '\n',
+                        Some(b'r') => '\r',
+                        Some(b't') => '\t',
+                        Some(b'u') => {
+                            self.pos += 2;
+                            let mut code = self.parse_hex4()?;
+                            // a surrogate pair
+                            if (0xD800..0xDC00).contains(&code) && self.s[self.pos..].starts_with(b"\\u") {
+                                self.pos += 2;
+                                let low = self.parse_hex4()?;
+                                code = 0x10000 + ((code - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF);
+                            }
+                            let c = char::from_u32(code).ok_or_else(|| format!("invalid unicode escape at {}", self.pos))?;
+                            bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                            continue;
+                        }
+                        _ => return Err(format!("invalid escape at {}", self.pos)),
+                    };
+                    bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                    self.pos += 2;
+                }
+                Some(c) => {
+                    bytes.push(*c);
+                    self.pos += 1;
+                }
+            }
+        }
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, String> {
+        let hex = self.s.get(self.pos..self.pos + 4)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+            .ok_or_else(|| format!("invalid unicode escape at {}", self.pos))?;
+        self.pos += 4;
+        Ok(hex)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_chunk() {
+        let names = ["id".to_string(), "name".to_string(), "ts".to_string()];
+        let types = [
+            DataType::new_nullable(DataTypeKind::Int32),
+            DataType::new_nullable(DataTypeKind::String),
+            DataType::new_nullable(DataTypeKind::Timestamp),
+        ];
+        let lines = [
+            r#"{"id": 1, "name": "a\"\u00e9\ud83d\ude00", "ts": "2024-01-01", "extra": [1, {}]}"#.to_string(),
+            r#"{"Name": {"x": null}}"#.to_string(),
+            r#"{"id": "x", "name": 1.5}"#.to_string(),
+        ];
+        let format = |on_type_mismatch| JsonFormat { on_type_mismatch };
+
+        assert!(matches!(format(OnTypeMismatch::Fail).parse_chunk(&lines, &names, &types),
+                         Err(ExecuteError::MalformedRow(..))));
+        let chunk = format(OnTypeMismatch::Skip).parse_chunk(&lines, &names, &types).unwrap();
+        assert_eq!(chunk.cardinality(), 2);
+        let row: Vec<_> = chunk.row(0).values().collect();
+        assert_eq!(row[1], DataValue::String("a\"é😀".into()));
+        let row: Vec<_> = chunk.row(1).values().collect();
+        assert_eq!(row, [DataValue::Null, DataValue::String(r#"{"x":null}"#.into()), DataValue::Null]);
+        let chunk = format(OnTypeMismatch::Null).parse_chunk(&lines, &names, &types).unwrap();
+        let row: Vec<_> = chunk.row(2).values().collect();
+        assert_eq!(row, [DataValue::Null, DataValue::String("1.5".into()), DataValue::Null]);
+        assert!(format(OnTypeMismatch::Null).parse_chunk(&["{\"id\": 1".to_string()], &names, &types).is_err());
+    }
+
+    #[test]
+    fn test_write_rows() {
+        let names = ["id".to_string(), "name".to_string()];
+        let rows = vec![
+            vec![DataValue::Int32(1), DataValue::String("a\"\n".into())],
+            vec![DataValue::Null, DataValue::Bool(true)],
+        ];
+        let mut out = vec![];
+        JsonFormat { on_type_mismatch: OnTypeMismatch::Fail }
+            .write_rows(&mut out, &names, rows.into_iter()).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(),
+                   "{\"id\":1,\"name\":\"a\\\"\\n\"}\n{\"id\":null,\"name\":true}\n");
+    }
+}
@@ -0,0 +1,127 @@
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+use futures::future::BoxFuture;
+use crate::array::Row;
+use crate::connector::parse_duration;
+use crate::executor::ExecuteError;
+
+/// A connector querying the rows of tables by keys, which is the dimension table
+/// of lookup joins.
+pub trait LookupConnector {
+    /// Returns the rows matching each key, whose values are compared with the
+    /// lookup key columns the connector is created with.
+    fn lookup<'a>(&'a self, keys: &'a [Row]) -> BoxFuture<'a, Result<Vec<Vec<Row>>, ExecuteError>>;
+}
+
+/// The options of the cache of lookup results:
+/// - `lookup.cache.max-rows`: the maximum number of keys in the cache, which is disabled if not set
+/// - `lookup.cache.ttl`: how long a cached result lives, forever if not set
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LookupCacheOptions {
+    pub max_rows: usize,
+    pub ttl: Option<Duration>,
+}
+
+impl LookupCacheOptions {
+    pub fn from_options(options: &HashMap<String, String>) -> Result<Self, String> {
+        let max_rows = match options.get("lookup.cache.max-rows") {
+            Some(value) => value.parse()
+                .map_err(|_| format!("invalid lookup.cache.max-rows: {}", value))?,
+            None => 0,
+        };
+        let ttl = options.get("lookup.cache.ttl")
+            .map(|value| parse_duration(value).ok_or_else(|| format!("invalid lookup.cache.ttl: {}", value)))
+            .transpose()?;
+        Ok(LookupCacheOptions { max_rows, ttl })
+    }
+}
+
+/// A cache of the rows looked up by keys, which evicts the least recently used key
+/// once full, and expires the results older than the TTL.
+pub struct LookupCache {
+    options: LookupCacheOptions,
+    entries: HashMap<Row, CacheEntry>,
+    /// The keys by the time they're used last.
+    lru: BTreeMap<u64, Row>,
+    /// The logical time incremented by each use.
+    clock: u64,
+}
+
+struct CacheEntry {
+    rows: Vec<Row>,
+    loaded_at: Instant,
+    used_at: u64,
+}
+
+impl LookupCache {
+    pub fn new(options: LookupCacheOptions) -> Self {
+        LookupCache {
+            options,
+            entries: HashMap::new(),
+            lru: BTreeMap::new(),
+            clock: 0,
+        }
+    }
+
+    /// Returns the cached rows of a key, `None` if not cached or expired.
+    pub fn get(&mut self, key: &Row) -> Option<&[Row]> {
+        let entry = self.entries.get(key)?;
+        if self.options.ttl.is_some_and(|ttl| entry.loaded_at.elapsed() >= ttl) {
+            let entry = self.entries.remove(key).unwrap();
+            self.lru.remove(&entry.used_at);
+            return None;
+        }
+        self.clock += 1;
+        let entry = self.entries.get_mut(key).unwrap();
+        let key = self.lru.remove(&entry.used_at).unwrap();
+        entry.used_at = self.clock;
+        self.lru.insert(self.clock, key);
+        Some(&entry.rows)
+    }
+
+    /// Caches the rows of a key, evicting the least recently used keys if full.
+    pub fn put(&mut self, key: Row, rows: Vec<Row>) {
+        if self.options.max_rows == 0 {
+            return;
+        }
+        self.clock += 1;
+        if let Some(entry) = self.entries.remove(&key) {
+            self.lru.remove(&entry.used_at);
+        }
+        while self.entries.len() >= self.options.max_rows {
+            let (_, evicted) = self.lru.pop_first().unwrap();
+            self.entries.remove(&evicted);
+        }
+        self.lru.insert(self.clock, key.clone());
+        self.entries.insert(key, CacheEntry { rows, loaded_at: Instant::now(), used_at: self.clock });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::DataValue;
+
+    #[test]
+    fn test_lookup_cache() {
+        let key = |v| vec![DataValue::Int32(v)];
+        let mut cache = LookupCache::new(LookupCacheOptions { max_rows: 2, ttl: None });
+        cache.put(key(1), vec![key(10)]);
+        cache.put(key(2), vec![]);
+        // the key 1 is used after the key 2, which is evicted first
+        assert_eq!(cache.get(&key(1)), Some(&[key(10)][..]));
+        cache.put(key(3), vec![key(30)]);
+        assert_eq!(cache.get(&key(2)), None);
+        assert_eq!(cache.get(&key(1)), Some(&[key(10)][..]));
+        assert_eq!(cache.get(&key(3)), Some(&[key(30)][..]));
+
+        let mut cache = LookupCache::new(LookupCacheOptions { max_rows: 2, ttl: Some(Duration::ZERO) });
+        cache.put(key(1), vec![key(10)]);
+        assert_eq!(cache.get(&key(1)), None);
+
+        // nothing is cached by default
+        let mut cache = LookupCache::new(LookupCacheOptions::from_options(&HashMap::new()).unwrap());
+        cache.put(key(1), vec![key(10)]);
+        assert_eq!(cache.get(&key(1)), None);
+    }
+}
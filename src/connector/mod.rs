@@ -1,27 +1,74 @@
 pub mod data_gen;
 mod print;
 mod file_system;
+mod csv;
+mod json;
+mod format;
 mod black_hole;
 mod value;
+mod registry;
+mod lookup;
 
 pub use print::Print;
 pub use black_hole::BlackHole;
-pub use file_system::FileSystemConnector;
+pub use file_system::{FileSystemSink, FileSystemSource};
 pub use value::ValueConnector;
+pub use lookup::{LookupCache, LookupCacheOptions, LookupConnector};
+pub use registry::{BoxedLookup, BoxedSink, BoxedSource, ConnectorContext, ConnectorFactory, ConnectorRegistry, ConnectorRegistryRef};
+pub use crate::stream::{Barrier, Message};
 
+use std::time::Duration;
+use futures::future::{self, BoxFuture, FutureExt};
 use futures_async_stream::try_stream;
 use crate::array::DataChunk;
-use crate::stream::{Barrier, Message};
 use crate::executor::ExecuteError;
 
-pub trait StreamConnector {
+/// Parses a duration like `500ms`, `10s`, `1min` or `2h` in connector options.
+pub(crate) fn parse_duration(s: &str) -> Option<Duration> {
+    let s = s.trim();
+    let unit_start = s.find(|c: char| !c.is_ascii_digit())?;
+    let value: u64 = s[..unit_start].parse().ok()?;
+    match s[unit_start..].trim() {
+        "ms" => Some(Duration::from_millis(value)),
+        "s" => Some(Duration::from_secs(value)),
+        "min" => Some(Duration::from_secs(value * 60)),
+        "h" => Some(Duration::from_secs(value * 60 * 60)),
+        _ => None,
+    }
+}
 
+/// The commit of the output of an epoch by a sink, which is awaited once the epoch
+/// is completed and checkpointed.
+pub type CommitFuture = BoxFuture<'static, Result<(), ExecuteError>>;
 
+/// A connector reading the rows of tables.
+pub trait SourceConnector {
     #[try_stream(boxed, ok = Message, error = ExecuteError)]
     async fn read(&self);
 
+    /// Returns the position after the rows read so far, which is recorded in checkpoints.
+    fn position(&self) -> Option<String> {
+        None
+    }
 
-    fn write(&mut self, chunk: DataChunk);
+    /// Resumes reading from a position recorded in a checkpoint.
+    fn seek(&mut self, _position: &str) {}
+}
 
-    fn on_receive_barrier(&mut self, barrier: Barrier);
-}
\ No newline at end of file
+/// A connector writing the rows to tables.
+pub trait SinkConnector: Send {
+    /// Writes the rows of the current epoch.
+    fn write(&mut self, chunk: DataChunk) -> BoxFuture<'_, Result<(), ExecuteError>>;
+
+    /// Ends the epoch of the barrier, returning the commit of its output if the sink
+    /// writes with two-phase commit, whose output of an epoch is only visible when
+    /// the epoch is completed.
+    fn on_barrier(&mut self, _barrier: &Barrier) -> BoxFuture<'_, Result<Option<CommitFuture>, ExecuteError>> {
+        future::ready(Ok(None)).boxed()
+    }
+
+    /// Flushes and commits the output when the input is finished.
+    fn close(&mut self) -> BoxFuture<'_, Result<(), ExecuteError>> {
+        future::ready(Ok(())).boxed()
+    }
+}
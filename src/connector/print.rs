@@ -1,29 +1,38 @@
 use std::sync::Arc;
-use futures_async_stream::try_stream;
+use futures::future::{self, BoxFuture, FutureExt};
 use crate::array::DataChunk;
 use crate::catalog::{ColumnId, TableCatalog};
-use crate::connector::StreamConnector;
-use crate::connector::ExecuteError;
-use crate::stream::Barrier;
-use crate::connector::Message;
+use crate::connector::{BoxedSink, ConnectorContext, ConnectorFactory, SinkConnector};
+use crate::executor::ExecuteError;
 
 pub struct Print {
     pub column_ids: Vec<ColumnId>,
     pub table: Arc<TableCatalog>,
 }
 
+/// Creates the sinks of `'connector' = 'print'`, which print the rows written.
+pub struct PrintFactory;
 
-impl StreamConnector for Print {
-    #[try_stream(boxed, ok = Message, error = ExecuteError)]
-    async fn read(&self) {
-        unimplemented!()
+impl ConnectorFactory for PrintFactory {
+    fn name(&self) -> &str {
+        "print"
     }
 
-    fn write(&mut self, chunk: DataChunk) {
-        println!("{}", chunk);
+    fn is_sink(&self) -> bool {
+        true
+    }
+
+    fn create_sink(&self, context: ConnectorContext) -> Result<BoxedSink, ExecuteError> {
+        Ok(Box::new(Print {
+            column_ids: context.column_ids,
+            table: context.table,
+        }))
     }
+}
 
-    fn on_receive_barrier(&mut self, barrier: Barrier) {
-        // do nothing
+impl SinkConnector for Print {
+    fn write(&mut self, chunk: DataChunk) -> BoxFuture<'_, Result<(), ExecuteError>> {
+        println!("{}", chunk);
+        future::ready(Ok(())).boxed()
     }
-}
\ No newline at end of file
+}
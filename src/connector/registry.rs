@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use crate::catalog::{ColumnId, TableCatalog};
+use crate::connector::{LookupConnector, SinkConnector, SourceConnector};
+use crate::executor::ExecuteError;
+use crate::types::DataType;
+
+pub type BoxedSource = Box<dyn SourceConnector + Send + Sync>;
+pub type BoxedSink = Box<dyn SinkConnector + Sync>;
+pub type BoxedLookup = Box<dyn LookupConnector + Send + Sync>;
+pub type ConnectorRegistryRef = Arc<ConnectorRegistry>;
+
+/// What a connector of a table is created with.
+pub struct ConnectorContext<'a> {
+    pub table: Arc<TableCatalog>,
+    /// The columns read or written by the job, in order.
+    pub column_ids: Vec<ColumnId>,
+    pub options: HashMap<String, String>,
+    pub job_id: &'a str,
+    /// The epoch of the checkpoint the job is restored from.
+    pub restored_epoch: Option<u64>,
+}
+
+/// Creates the connectors of the tables with `'connector' = '<name>'`.
+pub trait ConnectorFactory: Send + Sync {
+    /// Returns the name in the `connector` option, which is case-insensitive.
+    fn name(&self) -> &str;
+
+    /// Returns the options that must be set.
+    fn required_options(&self) -> &[&str] {
+        &[]
+    }
+
+    /// Returns the other options accepted, where a key ending with `.` accepts
+    /// all options with the prefix.
+    fn optional_options(&self) -> &[&str] {
+        &[]
+    }
+
+    /// Validates the values of the options with the columns of the table,
+    /// returning an error message if invalid.
+    fn validate(&self, _options: &HashMap<String, String>, _columns: &[(String, DataType)]) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Returns whether the tables can be read by `SELECT`.
+    fn is_source(&self) -> bool {
+        false
+    }
+
+    /// Returns whether the tables can be written by `INSERT`.
+    fn is_sink(&self) -> bool {
+        false
+    }
+
+    /// Returns whether the tables of the options have a finite number of rows,
+    /// so that the jobs reading them finish by themselves.
+    fn is_bounded(&self, _options: &HashMap<String, String>) -> bool {
+        false
+    }
+
+    /// Returns whether the tables can be queried by keys in lookup joins.
+    fn is_lookup(&self) -> bool {
+        false
+    }
+
+    /// Creates the source of a table, failing if the connector isn't a source
+    /// or the source can't be opened.
+    fn create_source(&self, _context: ConnectorContext) -> Result<BoxedSource, ExecuteError> {
+        Err(ExecuteError::NotSupported(format!("connector {} is not a source", self.name())))
+    }
+
+    /// Creates the sink of a table, failing if the connector isn't a sink
+    /// or the sink can't be opened.
+    fn create_sink(&self, _context: ConnectorContext) -> Result<BoxedSink, ExecuteError> {
+        Err(ExecuteError::NotSupported(format!("connector {} is not a sink", self.name())))
+    }
+
+    /// Creates the lookup connector of a table, which queries the rows by the values of
+    /// the columns at `key_indexes` of the columns read, failing if lookups aren't supported.
+    fn create_lookup(&self, _context: ConnectorContext, _key_indexes: Vec<usize>) -> Result<BoxedLookup, ExecuteError> {
+        Err(ExecuteError::NotSupported(format!("connector {} doesn't support lookups", self.name())))
+    }
+}
+
+/// The connector factories by name, which are shared by the binder validating
+/// `CREATE TABLE` and the executor builder creating the connectors.
+pub struct ConnectorRegistry {
+    factories: Mutex<HashMap<String, Arc<dyn ConnectorFactory>>>,
+}
+
+impl Default for ConnectorRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConnectorRegistry {
+    /// Creates a registry of the builtin connectors.
+    pub fn new() -> Self {
+        let registry = ConnectorRegistry {
+            factories: Mutex::new(HashMap::new()),
+        };
+        registry.register(super::data_gen::DataGenFactory);
+        registry.register(super::print::PrintFactory);
+        registry.register(super::black_hole::BlackHoleFactory);
+        registry.register(super::file_system::FileSystemFactory);
+        registry
+    }
+
+    /// Registers a factory, replacing the one of the same name.
+    pub fn register(&self, factory: impl ConnectorFactory + 'static) {
+        let name = factory.name().to_lowercase();
+        self.factories.lock().unwrap().insert(name, Arc::new(factory));
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn ConnectorFactory>> {
+        self.factories.lock().unwrap().get(&name.to_lowercase()).cloned()
+    }
+
+    /// Returns the factory of the `connector` option after validating the options
+    /// against it, or an error message.
+    pub fn validate(&self, options: &HashMap<String, String>, columns: &[(String, DataType)])
+        -> Result<Arc<dyn ConnectorFactory>, String> {
+        let name = options.get("connector").ok_or("option 'connector' is required")?;
+        let factory = self.get(name).ok_or_else(|| format!("unknown connector: {}", name))?;
+        for key in factory.required_options() {
+            if !options.contains_key(*key) {
+                return Err(format!("option '{}' is required by connector {}", key, name));
+            }
+        }
+        for key in options.keys().filter(|key| *key != "connector") {
+            let accepted = factory.required_options().iter()
+                .chain(factory.optional_options())
+                .any(|option| match option.strip_suffix('.') {
+                    Some(_) => key.starts_with(option),
+                    None => key == option,
+                });
+            if !accepted {
+                return Err(format!("unknown option '{}' of connector {}", key, name));
+            }
+        }
+        factory.validate(options, columns)?;
+        Ok(factory)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::DataTypeKind;
+
+    #[test]
+    fn test_validate() {
+        let registry = ConnectorRegistry::new();
+        let columns = [("v".to_string(), DataType::new_nullable(DataTypeKind::Int32))];
+        let options = |pairs: &[(&str, &str)]| -> HashMap<String, String> {
+            pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+        };
+
+        let factory = registry.validate(&options(&[("connector", "DataGen"), ("fields.v.max", "9")]), &columns);
+        assert_eq!(factory.unwrap().name(), "datagen");
+        assert!(registry.validate(&options(&[("path", "/tmp")]), &columns).is_err());
+        assert!(registry.validate(&options(&[("connector", "kafka")]), &columns).is_err());
+        // missing required option
+        assert!(registry.validate(&options(&[("connector", "filesystem")]), &columns).is_err());
+        // unknown option
+        assert!(registry.validate(&options(&[("connector", "print"), ("path", "/tmp")]), &columns).is_err());
+        // invalid value
+        assert!(registry.validate(&options(&[("connector", "filesystem"), ("path", "/tmp"), ("format", "xml")]), &columns).is_err());
+    }
+}
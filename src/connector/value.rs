@@ -1,9 +1,9 @@
 use futures_async_stream::try_stream;
 use crate::array::{ArrayBuilderImpl, DataChunk};
-use crate::connector::StreamConnector;
+use crate::connector::SourceConnector;
 use crate::executor::PROCESSING_WINDOW_SIZE;
 use crate::planner::RecExpr;
-use crate::stream::{Barrier, Message};
+use crate::stream::Message;
 use crate::types::DataType;
 use crate::executor::evaluator::Evaluator;
 use crate::connector::ExecuteError;
@@ -16,7 +16,7 @@ pub struct ValueConnector {
 }
 
 
-impl StreamConnector for ValueConnector {
+impl SourceConnector for ValueConnector {
     #[try_stream(boxed, ok = Message, error = ExecuteError)]
     async fn read(&self) {
         let dummy = DataChunk::single(0);
@@ -36,13 +36,4 @@ impl StreamConnector for ValueConnector {
             yield Message::Chunk(chunk1);
         }
     }
-
-
-    fn write(&mut self, chunk: DataChunk) {
-        todo!()
-    }
-
-    fn on_receive_barrier(&mut self, barrier: Barrier) {
-        todo!()
-    }
 }
\ No newline at end of file
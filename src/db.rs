@@ -4,23 +4,30 @@ use crate::array::data_chunk::DataChunk;
 use crate::binder::{Binder, BindError};
 use crate::catalog::{CatalogRef, DatabaseCatalog};
 use crate::executor::{ExecutorBuilder, ExecuteError};
-use crate::parser::{parse, ParserError};
+use crate::parser::{parse, split_statements, Expr, ParserError, Statement, Value};
 use tokio::task::JoinHandle;
 use uuid::Uuid;
-use crate::checkpoint::BarrierManager;
-use crate::stream::StreamRunningJob;
+use crate::checkpoint::{BarrierManager, CheckpointStore, Manifest};
+use crate::connector::{parse_duration, ConnectorFactory, ConnectorRegistry, ConnectorRegistryRef};
+use crate::stream::{JobHandle, JobInfo, StreamRunningJob};
+use crate::types::timestamp;
 use {
     once_cell::sync::Lazy,
     regex::Regex,
 };
 use crate::planner::Optimizer;
+use crate::state::{DEFAULT_STATE_DIR, StateBackend, StateError};
+use std::path::PathBuf;
+use std::time::Duration;
 
 // one for the actual jobs, one for the thread that send checkpoint
 type RunningJob = (JoinHandle<Result<(), Error>>, JoinHandle<()>);
 
 pub struct RunResult {
     pub result_chunk: DataChunk,
-    pub job_id: Option<String>
+    pub job_id: Option<String>,
+    /// The handle of the streaming job started by the statement.
+    pub job: Option<JobHandle>,
 }
 
 impl RunResult {
@@ -28,18 +35,32 @@ impl RunResult {
         RunResult {
             result_chunk: chunk,
             job_id,
+            job: None,
         }
     }
 }
 
 static KILL_JOB_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^kill job ([\da-fA-F-]+);*$").unwrap());
-static SHOW_JOB_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^show jobs;*$").unwrap());
+static SHOW_JOB_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)^show jobs;*$").unwrap());
+static DESCRIBE_JOB_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)^describe job ([\da-fA-F-]+);*$").unwrap());
+static RESTORE_JOB_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)^restore job ([\da-fA-F-]+) from checkpoint (\d+);*$").unwrap());
 
 pub struct Database {
     catalog: CatalogRef,
     barrier_manager: Arc<Mutex<BarrierManager>>,
+    /// The streaming jobs by id, including the ones stopped, which are kept to be shown.
     running_jobs: HashMap<String, StreamRunningJob>,
-    last_running_job_id: Option<String>
+    last_running_job_id: Option<String>,
+    /// The state backend of the jobs submitted afterwards.
+    state_backend: StateBackend,
+    state_dir: PathBuf,
+    /// How long the state of an unused key is kept by the jobs submitted afterwards,
+    /// forever if `None`.
+    state_ttl: Option<Duration>,
+    /// The directory of the checkpoints of the jobs submitted afterwards,
+    /// `None` if checkpointing is disabled.
+    checkpoint_dir: Option<PathBuf>,
+    connectors: ConnectorRegistryRef,
 }
 
 impl Default for Database {
@@ -58,10 +79,20 @@ impl Database {
         Database { catalog: catalog.clone(),
             barrier_manager,
             running_jobs: Default::default(),
-            last_running_job_id: None
+            last_running_job_id: None,
+            state_backend: StateBackend::Memory,
+            state_dir: PathBuf::from(DEFAULT_STATE_DIR),
+            state_ttl: None,
+            checkpoint_dir: None,
+            connectors: Arc::new(ConnectorRegistry::new()),
         }
     }
 
+    /// Registers a connector, which tables can use by `'connector' = '<name>'`.
+    pub fn register_connector(&self, factory: impl ConnectorFactory + 'static) {
+        self.connectors.register(factory);
+    }
+
     pub fn get_last_running_job_id(&mut self) -> Option<String> {
         self.last_running_job_id.clone()
     }
@@ -74,25 +105,97 @@ impl Database {
         self.last_running_job_id = None;
     }
 
-    async fn try_run_in_extend_executor(&mut self, sql: &str) -> Option<DataChunk> {
+    /// Returns the information of all streaming jobs, ordered by start time.
+    pub fn jobs(&self) -> Vec<JobInfo> {
+        let mut jobs: Vec<JobInfo> = self.running_jobs.values().map(|job| job.info()).collect();
+        jobs.sort_by(|a, b| (a.start_time, &a.job_id).cmp(&(b.start_time, &b.job_id)));
+        jobs
+    }
+
+    /// Returns the information of a streaming job.
+    pub fn job(&self, job_id: &str) -> Option<JobInfo> {
+        self.running_jobs.get(job_id).map(|job| job.info())
+    }
+
+    async fn try_run_in_extend_executor(&mut self, sql: &str) -> Option<Result<DataChunk, Error>> {
         if let Some(cap) = KILL_JOB_RE.captures(sql.trim()) {
             let job_id = cap.get(1).unwrap().as_str();
             return if self.stop_job(job_id).await {
-                Some(DataChunk::single_str(format!("Kill job {} successfully", job_id).as_str()))
+                Some(Ok(DataChunk::single_str(format!("Kill job {} successfully", job_id).as_str())))
             } else {
-                Some(DataChunk::single_str(format!("Kill job {} successfully", job_id).as_str()))
+                Some(Err(Error::UnknownJob(job_id.to_string())))
             }
         }
         if SHOW_JOB_RE.is_match(sql.trim()) {
-            let running_job_ids: Vec<String> = self.running_jobs.keys().map(|job_id|
-                job_id.clone()).collect();
-            return Some(DataChunk::from_strs(running_job_ids));
+            // job id, status, start time, last checkpoint epoch, sql
+            let rows = self.jobs().into_iter().map(|job| vec![
+                job.job_id,
+                job.status.to_string(),
+                timestamp::format_timestamp(job.start_time),
+                job.last_checkpoint_epoch.map_or("-".to_string(), |epoch| epoch.to_string()),
+                job.sql,
+            ]).collect();
+            return Some(Ok(DataChunk::from_str_rows(5, rows)));
+        }
+        if let Some(cap) = DESCRIBE_JOB_RE.captures(sql.trim()) {
+            let job_id = cap.get(1).unwrap().as_str();
+            let Some(job) = self.job(job_id) else {
+                return Some(Err(Error::UnknownJob(job_id.to_string())));
+            };
+            let rows = vec![
+                vec!["id".to_string(), job.job_id.clone()],
+                vec!["status".to_string(), job.status.to_string()],
+                vec!["start time".to_string(), timestamp::format_timestamp(job.start_time)],
+                vec!["sql".to_string(), job.sql.clone()],
+                vec!["last checkpoint epoch".to_string(),
+                     job.last_checkpoint_epoch.map_or("-".to_string(), |epoch| epoch.to_string())],
+                vec!["error".to_string(), job.error().unwrap_or("-").to_string()],
+            ];
+            return Some(Ok(DataChunk::from_str_rows(2, rows)));
         }
-        return None;
+        None
     }
 
+    /// Sets a variable of the session by `SET <variable> = <value>`:
+    /// - `state.backend`: `memory` or `disk`
+    /// - `state.dir`: the directory of the disk state backend, where each job has its own state
+    ///   by its id, so only `RESTORE JOB` starts a job with the state of an earlier one
+    /// - `state.ttl`: how long the state of a key not updated is kept, like `1h`, or `0`
+    ///   to keep it forever, which is the default
+    /// - `checkpoint.dir`: the directory of checkpoints, which enables checkpointing
+    fn set_variable(&mut self, variable: &str, value: &[Expr]) -> Result<(), Error> {
+        let value = match value {
+            [Expr::Value(Value::SingleQuotedString(v))] => v.clone(),
+            [Expr::Identifier(ident)] => ident.value.clone(),
+            _ => return Err(Error::InvalidVariable(format!("invalid value of {}", variable))),
+        };
+        match (variable.to_lowercase().as_str(), value.to_lowercase().as_str()) {
+            ("state.backend", "memory") => self.state_backend = StateBackend::Memory,
+            ("state.backend", "disk") => self.state_backend = StateBackend::Disk {
+                dir: self.state_dir.clone(),
+            },
+            ("state.dir", _) => {
+                self.state_dir = PathBuf::from(value);
+                if let StateBackend::Disk { dir } = &mut self.state_backend {
+                    dir.clone_from(&self.state_dir);
+                }
+            }
+            ("state.ttl", "0") => self.state_ttl = None,
+            ("state.ttl", ttl) => {
+                let ttl = parse_duration(ttl)
+                    .ok_or_else(|| Error::InvalidVariable(format!("invalid state.ttl: {}", value)))?;
+                self.state_ttl = Some(ttl).filter(|ttl| !ttl.is_zero());
+            }
+            ("checkpoint.dir", _) => self.checkpoint_dir = Some(PathBuf::from(value)),
+            _ => return Err(Error::InvalidVariable(format!("{} = {}", variable, value))),
+        }
+        Ok(())
+    }
+
+    /// Stops a job if it's running, returning whether the job exists.
+    /// The stopped job is kept to be shown.
     pub async fn stop_job(&mut self, job_id: &str) -> bool {
-        if let Some(job) = self.running_jobs.remove(job_id) {
+        if let Some(job) = self.running_jobs.get_mut(job_id) {
             println!("stopping job {}.", job_id);
             job.stop().await;
             true
@@ -101,16 +204,64 @@ impl Database {
         }
     }
 
+    /// Restores a job from a checkpoint by `RESTORE JOB <id> FROM CHECKPOINT <epoch>`,
+    /// stopping the job if it's running.
+    async fn restore_job(&mut self, job_id: &str, epoch: u64) -> Result<RunResult, Error> {
+        let Some(checkpoint_dir) = self.checkpoint_dir.clone() else {
+            return Err(Error::Restore("checkpoint.dir is not set".into()));
+        };
+        let manifest = Manifest::read(&checkpoint_dir, job_id, epoch)
+            .map_err(|e| Error::Restore(format!("checkpoint {} of job {}: {}", epoch, job_id, e)))?;
+        if !manifest.report.state_handles.is_empty() && matches!(self.state_backend, StateBackend::Memory) {
+            return Err(Error::Restore("restoring state requires state.backend = 'disk'".into()));
+        }
+        self.stop_job(job_id).await;
+        let sql = manifest.sql.clone();
+        self.run_job(&sql, job_id.to_string(), Some(manifest)).await
+    }
+
+    /// Runs the statements of a script separated by `;` in order, returning the result
+    /// of each statement. A failed statement doesn't stop the following ones.
+    pub async fn run_script(&mut self, script: &str) -> Vec<(String, Result<RunResult, Error>)> {
+        let mut results = vec![];
+        for sql in split_statements(script) {
+            let result = self.run(&sql).await;
+            results.push((sql, result));
+        }
+        results
+    }
+
+    /// Runs a single statement.
     pub async fn run(&mut self, sql: &str) -> Result<RunResult, Error> {
+        if let Some(cap) = RESTORE_JOB_RE.captures(sql.trim()) {
+            let job_id = cap.get(1).unwrap().as_str();
+            let epoch = cap.get(2).unwrap().as_str().parse()
+                .map_err(|_| Error::Restore(format!("invalid epoch in {}", sql)))?;
+            return self.restore_job(job_id, epoch).await;
+        }
         if let Some(data_chunk) = self.try_run_in_extend_executor(sql).await {
-            return Ok(RunResult::new(data_chunk, None));
+            return Ok(RunResult::new(data_chunk?, None));
         }
+        self.run_job(sql, Uuid::new_v4().to_string(), None).await
+    }
+
+    /// Runs a statement as the job `job_id`, restored from the checkpoint if given.
+    async fn run_job(&mut self, sql: &str, job_id: String, restore_from: Option<Manifest>)
+        -> Result<RunResult, Error> {
         // parse sql
-        let stmts = parse(sql)?;
-        assert_eq!(stmts.len(), 1, "only support to handle one statement at once.");
-        let stmt = stmts.get(0).unwrap().to_owned();
+        let mut stmts = parse(sql)?;
+        if stmts.len() != 1 {
+            return Err(Error::NotSingleStatement(stmts.len()));
+        }
+        let stmt = stmts.remove(0);
+        if let Statement::SetVariable { variable, value, .. } = &stmt {
+            self.set_variable(&variable.to_string(), value)?;
+            return Ok(RunResult::new(
+                DataChunk::single_str("execute successfully"), None));
+        }
 
-        let mut binder = Binder::new(self.catalog.clone());
+        let mut binder = Binder::new(self.catalog.clone())
+            .with_connectors(self.connectors.clone());
         let optimizer = Optimizer::new(self.catalog.clone());
 
         let bound = binder.bind(stmt)?;
@@ -118,17 +269,30 @@ impl Database {
         let optimized = optimizer.optimize(&bound);
 
         let mut executor_builder = ExecutorBuilder::new(
-            self.catalog.clone(), &optimized);
-
+            self.catalog.clone(), &optimized)
+            .with_state_backend(self.state_backend.clone())
+            .with_state_ttl(self.state_ttl)
+            .with_connectors(self.connectors.clone());
+        if let Some(manifest) = restore_from {
+            executor_builder = executor_builder.with_restore_from(manifest);
+        }
 
-        let job_id = Uuid::new_v4();
-        let job = executor_builder.build_job(job_id.to_string());
-        return if let Some(running_job) = job.run().await {
+        let mut job = executor_builder.build_job(job_id.to_string());
+        job.sql = sql.to_string();
+        if let (Some(dir), false) = (&self.checkpoint_dir, job.is_ddl_job) {
+            let store = CheckpointStore::create(dir, &job_id, sql, optimized.to_string())?;
+            if job.start_epoch > 0 {
+                store.remove_after(job.start_epoch)?;
+            }
+            job.checkpoint_store = Some(store);
+        }
+        return if let Some((running_job, handle)) = job.run().await? {
             self.running_jobs.insert(job_id.to_string(), running_job);
             let s = format!("running job id: {}", job_id);
-            Ok(RunResult::new(
-                DataChunk::single_str(s.as_str()),
-                Some(job_id.to_string())))
+            Ok(RunResult {
+                job: Some(handle),
+                ..RunResult::new(DataChunk::single_str(s.as_str()), Some(job_id.to_string()))
+            })
         } else {
             Ok(RunResult::new(
                 DataChunk::single_str("execute successfully"), None))
@@ -145,4 +309,189 @@ pub enum Error {
     Bind(#[from] BindError),
     #[error("execute error: {0}")]
     Execute(#[from] ExecuteError),
-}
\ No newline at end of file
+    #[error("invalid variable: {0}")]
+    InvalidVariable(String),
+    #[error("checkpoint error: {0}")]
+    Checkpoint(#[from] StateError),
+    #[error("failed to restore job: {0}")]
+    Restore(String),
+    #[error("expected a single statement but got {0}, scripts are run by run_script")]
+    NotSingleStatement(usize),
+    #[error("job panicked: {0}")]
+    Panic(String),
+    #[error("unknown job: {0}")]
+    UnknownJob(String),
+}
+#[cfg(test)]
+mod tests {
+    use futures::{StreamExt, TryStreamExt};
+    use crate::array::Row;
+    use crate::types::DataValue;
+    use super::*;
+
+    /// Applies the changes output by the job to `rows` until they're `expected`,
+    /// or nothing is output for a while.
+    async fn apply_changes(handle: &mut JobHandle, rows: &mut Vec<Row>, expected: &[Row]) {
+        while rows.as_slice() != expected {
+            let Ok(Some(chunk)) = tokio::time::timeout(Duration::from_secs(10), handle.next()).await else {
+                break;
+            };
+            let chunk = chunk.unwrap();
+            for i in 0..chunk.cardinality() {
+                let row = chunk.row(i).to_owned();
+                if chunk.op(i).is_retract() {
+                    let pos = rows.iter().position(|r| *r == row).expect("retracting a row not emitted");
+                    rows.remove(pos);
+                } else {
+                    rows.push(row);
+                }
+            }
+            rows.sort();
+        }
+    }
+
+    /// Waits for a checkpoint of the job after `epoch`, returning its epoch.
+    async fn next_checkpoint(db: &Database, job_id: &str, epoch: Option<u64>) -> u64 {
+        loop {
+            let last = db.job(job_id).unwrap().last_checkpoint_epoch;
+            if last > epoch {
+                return last.unwrap();
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_run_script() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("t.csv"), "1\n2\n").unwrap();
+        let script = format!(
+            "create table t(a int) with ('connector' = 'filesystem', 'path' = '{}', 'format' = 'csv');\n\
+             select a from t where a > 1;\n\
+             select b from t;\n\
+             drop table t;\n\
+             select a from t;",
+            dir.path().display());
+        let mut results = Database::new().run_script(&script).await;
+        // each DDL job is ended before the next statement is run
+        assert!(matches!(results.iter().map(|(_, result)| result.as_ref().map(|_| ())).collect::<Vec<_>>().as_slice(),
+                         [Ok(()), Ok(()), Err(Error::Bind(_)), Ok(()), Err(Error::Bind(_))]));
+        let (_, Ok(create)) = &results[0] else {
+            unreachable!()
+        };
+        assert!(create.job.is_none());
+        let (_, Ok(select)) = &mut results[1] else {
+            unreachable!()
+        };
+        // the source follows the file, so only the first output is taken
+        let output = select.job.take().unwrap().try_next().await.unwrap().unwrap();
+        let rows: Vec<_> = (0..output.cardinality()).map(|i| output.row(i).to_owned()).collect();
+        assert_eq!(rows, vec![vec![DataValue::Int32(2)]]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_set_operation() {
+        let results = Database::new().run_script("select 1 union select 2;").await;
+        assert!(matches!(results[0].1, Err(Error::Bind(BindError::UnsupportedQuery(_)))));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_limit_with_retractions() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = format!(
+            "create table t(a int) with ('connector' = 'filesystem', 'path' = '{}', 'format' = 'csv');\n\
+             select count(a) from t group by a limit 1;\n\
+             select count(a) from (select a from t limit 1) group by a;",
+            dir.path().display());
+        let results = Database::new().run_script(&script).await;
+        // the limit is rejected before the job is started
+        assert!(matches!(results[1].1, Err(Error::Bind(BindError::RetractingLimit))));
+        assert!(results[2].1.is_ok());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_source_and_sink_tables() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = format!(
+            "create table g(a int) with ('connector' = 'datagen');\n\
+             create table b(a int) with ('connector' = 'blackhole');\n\
+             create table s(a int) with ('connector' = 'filesystem', 'path' = '{}', 'format' = 'csv');\n\
+             insert into g values (1);\n\
+             select a from b;\n\
+             insert into s values (1), (2);",
+            dir.path().display());
+        let mut results = Database::new().run_script(&script).await;
+        assert!(matches!(results[3].1, Err(Error::Bind(BindError::SourceOnlyTable(_)))));
+        assert!(matches!(results[4].1, Err(Error::Bind(BindError::SinkOnlyTable(_)))));
+
+        // the output of the last epoch is committed when the insert is closed at the end of the input
+        let (_, Ok(insert)) = &mut results[5] else {
+            panic!("the insert isn't started");
+        };
+        let handle = insert.job.take().unwrap();
+        assert!(handle.try_collect::<Vec<_>>().await.unwrap().is_empty());
+        let files: Vec<_> = std::fs::read_dir(dir.path()).unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        let [file] = files.as_slice() else {
+            panic!("unexpected files: {:?}", files);
+        };
+        assert!(file.file_name().unwrap().to_str().unwrap().starts_with("part-"));
+        assert_eq!(std::fs::read_to_string(file).unwrap(), "1\n2\n");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_sink_open_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("file");
+        std::fs::write(&file, "").unwrap();
+        // the directory of the sink can't be created under a file
+        let script = format!(
+            "create table s(a int) with ('connector' = 'filesystem', 'path' = '{}', 'format' = 'csv');\n\
+             insert into s values (1);",
+            file.join("s").display());
+        let mut results = Database::new().run_script(&script).await;
+        let (_, Ok(insert)) = &mut results[1] else {
+            panic!("the insert isn't started");
+        };
+        let output = insert.job.take().unwrap().try_collect::<Vec<_>>().await;
+        assert!(matches!(output, Err(Error::Execute(ExecuteError::Io(_)))));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_restore_job() {
+        let dir = tempfile::tempdir().unwrap();
+        let data = dir.path().join("data");
+        std::fs::create_dir(&data).unwrap();
+        std::fs::write(data.join("t.csv"), "1\n1\n2\n").unwrap();
+        let script = format!(
+            "set state.backend = 'disk';\n\
+             set state.dir = '{}';\n\
+             set checkpoint.dir = '{}';\n\
+             create table t(a int) with ('connector' = 'filesystem', 'path' = '{}', 'format' = 'csv');\n\
+             select a, count(a) from t group by a;",
+            dir.path().join("state").display(), dir.path().join("checkpoints").display(), data.display());
+        let mut db = Database::new();
+        let mut results = db.run_script(&script).await;
+        let mut handle = results.pop().unwrap().1.unwrap().job.unwrap();
+        let job_id = handle.job_id();
+        let count = |a, count| vec![DataValue::Int32(a), DataValue::Int32(count)];
+
+        let mut rows = vec![];
+        apply_changes(&mut handle, &mut rows, &[count(1, 2), count(2, 1)]).await;
+        assert_eq!(rows, vec![count(1, 2), count(2, 1)]);
+        // the barrier of the second checkpoint is injected after all rows are read
+        let first = next_checkpoint(&db, &job_id, db.job(&job_id).unwrap().last_checkpoint_epoch).await;
+        let epoch = next_checkpoint(&db, &job_id, Some(first)).await;
+        db.run(&format!("kill job {}", job_id)).await.unwrap();
+
+        // the restored job counts the rows appended after the checkpoint on the restored counts
+        let mut file = std::fs::OpenOptions::new().append(true).open(data.join("t.csv")).unwrap();
+        std::io::Write::write_all(&mut file, b"1\n3\n").unwrap();
+        let restored = db.run(&format!("restore job {} from checkpoint {}", job_id, epoch)).await.unwrap();
+        assert_eq!(restored.job_id, Some(job_id));
+        let expected = [count(1, 3), count(2, 1), count(3, 1)];
+        apply_changes(&mut restored.job.unwrap(), &mut rows, &expected).await;
+        assert_eq!(rows, expected);
+    }
+}
@@ -0,0 +1,141 @@
+use futures::StreamExt;
+use futures_async_stream::try_stream;
+use crate::array::DataChunk;
+use crate::executor::{BoxedExecutor, ExecuteError};
+use crate::stream::{Barrier, Message, Watermark};
+
+/// One of the two inputs of an executor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Side {
+    Left = 0,
+    Right = 1,
+}
+
+impl Side {
+    pub fn other(self) -> Side {
+        match self {
+            Side::Left => Side::Right,
+            Side::Right => Side::Left,
+        }
+    }
+}
+
+/// A message of two inputs merged by [`barrier_align`].
+#[derive(Debug)]
+pub enum AlignedMessage {
+    Chunk(Side, DataChunk),
+    /// A barrier received from both inputs, or from the one not ended yet.
+    Barrier(Barrier),
+    /// The minimum watermark of the inputs.
+    Watermark(Watermark),
+}
+
+/// Merges two inputs, aligning their barriers.
+///
+/// Once a barrier is received from an input, the input is blocked until the barrier
+/// is received from the other one, and then the barrier is forwarded once. So all rows
+/// before the barrier from both inputs are forwarded before it, and none after it.
+/// An ended input no longer holds back the barriers or watermarks of the other one.
+#[try_stream(boxed, ok = AlignedMessage, error = ExecuteError)]
+pub async fn barrier_align(mut left: BoxedExecutor, mut right: BoxedExecutor) {
+    let mut watermarks = WatermarkAligner::default();
+    // the barrier received from one input, which blocks it
+    let mut pending: Option<(Side, Barrier)> = None;
+    let mut ended = [false, false];
+    loop {
+        let polled = |side: Side| !ended[side as usize] && !matches!(pending, Some((s, _)) if s == side);
+        let (poll_left, poll_right) = (polled(Side::Left), polled(Side::Right));
+        let next = async {
+            tokio::select! {
+                msg = left.next(), if poll_left => Some((Side::Left, msg)),
+                msg = right.next(), if poll_right => Some((Side::Right, msg)),
+                else => None,
+            }
+        };
+        let (side, msg) = match next.await {
+            Some(next) => next,
+            None => break,
+        };
+        match msg.transpose()? {
+            None => {
+                ended[side as usize] = true;
+                // the ended input will never send the barrier
+                if let Some((_, barrier)) = pending.take() {
+                    yield AlignedMessage::Barrier(barrier);
+                }
+                if let Some(watermark) = watermarks.end(side) {
+                    yield AlignedMessage::Watermark(watermark);
+                }
+            }
+            Some(Message::Chunk(chunk)) => yield AlignedMessage::Chunk(side, chunk),
+            Some(Message::Watermark(watermark)) => {
+                if let Some(watermark) = watermarks.update(side, watermark) {
+                    yield AlignedMessage::Watermark(watermark);
+                }
+            }
+            Some(Message::Barrier(barrier)) => match pending.take() {
+                Some((_, pending_barrier)) => {
+                    debug_assert_eq!(pending_barrier.epoch, barrier.epoch, "barriers are not aligned");
+                    yield AlignedMessage::Barrier(barrier);
+                }
+                None if ended[side.other() as usize] => yield AlignedMessage::Barrier(barrier),
+                None => pending = Some((side, barrier)),
+            },
+        }
+    }
+}
+
+/// Combines the watermarks of two inputs into their minimum, which advances only
+/// once both inputs have sent a watermark, unless one of them is ended.
+#[derive(Debug, Default)]
+pub struct WatermarkAligner {
+    /// The latest watermark of each input.
+    inputs: [Option<Watermark>; 2],
+    ended: [bool; 2],
+    current: Option<Watermark>,
+}
+
+impl WatermarkAligner {
+    /// Returns the combined watermark if it's advanced by the watermark of an input.
+    pub fn update(&mut self, side: Side, watermark: Watermark) -> Option<Watermark> {
+        let input = &mut self.inputs[side as usize];
+        *input = (*input).max(Some(watermark));
+        self.advance()
+    }
+
+    /// Returns the combined watermark if it's advanced once an input is ended.
+    pub fn end(&mut self, side: Side) -> Option<Watermark> {
+        self.ended[side as usize] = true;
+        self.advance()
+    }
+
+    fn advance(&mut self) -> Option<Watermark> {
+        let min = (0..2)
+            .filter(|i| !self.ended[*i])
+            .map(|i| self.inputs[i])
+            .min()??;
+        if self.current.map_or(true, |current| current < min) {
+            self.current = Some(min);
+            return Some(min);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watermark_aligner() {
+        let mut aligner = WatermarkAligner::default();
+        let watermark = |timestamp| Watermark { timestamp };
+        // held back until both inputs have a watermark
+        assert_eq!(aligner.update(Side::Left, watermark(10)), None);
+        assert_eq!(aligner.update(Side::Right, watermark(5)), Some(watermark(5)));
+        assert_eq!(aligner.update(Side::Right, watermark(20)), Some(watermark(10)));
+        assert_eq!(aligner.update(Side::Right, watermark(15)), None);
+        // the ended input no longer holds back the other one
+        assert_eq!(aligner.end(Side::Left), Some(watermark(20)));
+    }
+}
@@ -1,6 +1,7 @@
 use futures_async_stream::try_stream;
 use crate::array::data_chunk::DataChunk;
 use crate::binder::CreateTable;
+use crate::catalog::WatermarkDesc;
 use super::*;
 use crate::stream::Message;
 
@@ -22,6 +23,10 @@ impl CreateTableExecutor {
             column_descs.push(desc.clone());
         }
         table.add_options(self.plan.options);
+        if let Some((column, delay)) = &self.plan.watermark {
+            let column_id = table.get_column_by_name(column).unwrap().id();
+            table.set_watermark(WatermarkDesc { column_id, delay: *delay });
+        }
         yield Message::Chunk(DataChunk::no_column());
     }
 }
@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use futures_async_stream::try_stream;
+use crate::array::{DataChunk, DataChunkBuilder, Row, RowKind};
+use crate::executor::row_store::{state_key, RowStore};
+use crate::executor::{BoxedExecutor, ExecuteError, PROCESSING_WINDOW_SIZE};
+use crate::state::StateRef;
+use crate::stream::Message;
+use crate::types::{timestamp, DataType};
+
+/// The executor of `SELECT DISTINCT`, which emits a row the first time it's received,
+/// and deletes it once all of its copies are retracted.
+///
+/// With a TTL, a row not received or retracted within the TTL is forgotten, so that
+/// the state doesn't grow forever, and the row is emitted again if received later.
+pub struct DistinctExecutor {
+    pub child: BoxedExecutor,
+    inner: ExecutorInner,
+}
+
+struct ExecutorInner {
+    types: Vec<DataType>,
+    /// The ttl of the rows in milliseconds, `None` if they're kept forever.
+    ttl: Option<i64>,
+    rows: HashMap<Row, DistinctRowState>,
+    /// The persistent rows, whose key is the row and value is the count followed by the
+    /// access time, which are updated or expired since the last barrier.
+    store: RowStore<Row>,
+}
+
+/// The state of a distinct row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct DistinctRowState {
+    /// The number of the copies of the row, the row is deleted once it drops to zero.
+    count: i64,
+    /// The processing time the row is last received or retracted.
+    accessed_at: i64,
+}
+
+impl DistinctExecutor {
+
+    pub fn new(types: Vec<DataType>, ttl: Option<Duration>, state: StateRef, child: BoxedExecutor) -> Self {
+        DistinctExecutor {
+            child,
+            inner: ExecutorInner::new(types, ttl, state),
+        }
+    }
+
+    #[try_stream(boxed, ok = Message, error = ExecuteError)]
+    pub async fn execute(self) {
+        let DistinctExecutor { child, inner: mut this } = self;
+        this.load()?;
+        #[for_await]
+        for msg in child {
+            match msg? {
+                Message::Chunk(chunk) => {
+                    let mut builder = DataChunkBuilder::new(&this.types, PROCESSING_WINDOW_SIZE);
+                    for (op, row) in this.apply(&chunk, timestamp::now()) {
+                        if let Some(chunk) = builder.push_row_with_op(op, row) {
+                            yield Message::Chunk(chunk)
+                        }
+                    }
+                    if let Some(chunk) = builder.take() {
+                        yield Message::Chunk(chunk)
+                    }
+                }
+                Message::Barrier(barrier) => {
+                    this.expire(timestamp::now());
+                    this.flush(barrier.epoch)?;
+                    yield Message::Barrier(barrier)
+                }
+                Message::Watermark(watermark) => yield Message::Watermark(watermark),
+            }
+        }
+    }
+}
+
+impl ExecutorInner {
+
+    fn new(types: Vec<DataType>, ttl: Option<Duration>, state: StateRef) -> Self {
+        ExecutorInner {
+            types,
+            ttl: ttl.map(|ttl| ttl.as_millis() as i64),
+            rows: HashMap::new(),
+            store: RowStore::new(state),
+        }
+    }
+
+    /// Returns whether a row accessed at `accessed_at` has expired at `now`.
+    fn is_expired(&self, accessed_at: i64, now: i64) -> bool {
+        self.ttl.is_some_and(|ttl| now - accessed_at >= ttl)
+    }
+
+    /// Inserts or retracts the rows of the chunk at the processing time `now`,
+    /// returning the changes of the distinct rows.
+    fn apply(&mut self, chunk: &DataChunk, now: i64) -> Vec<(RowKind, Row)> {
+        let mut changes = vec![];
+        for i in 0..chunk.cardinality() {
+            let row = chunk.row(i).to_owned();
+            let expired = self.rows.get(&row).is_some_and(|state| self.is_expired(state.accessed_at, now));
+            if expired {
+                self.rows.remove(&row);
+                self.store.mark_dirty(row.clone());
+            }
+            if chunk.op(i).is_retract() {
+                // a retraction of a row never received or expired is ignored
+                let Some(state) = self.rows.get_mut(&row) else {
+                    continue;
+                };
+                state.count -= 1;
+                state.accessed_at = now;
+                if state.count == 0 {
+                    self.rows.remove(&row);
+                    changes.push((RowKind::Delete, row.clone()));
+                }
+            } else {
+                let state = self.rows.entry(row.clone())
+                    .or_insert(DistinctRowState { count: 0, accessed_at: now });
+                state.count += 1;
+                state.accessed_at = now;
+                if state.count == 1 {
+                    changes.push((RowKind::Insert, row.clone()));
+                }
+            }
+            self.store.mark_dirty(row);
+        }
+        changes
+    }
+
+    /// Forgets the rows expired at the processing time `now`.
+    fn expire(&mut self, now: i64) {
+        if self.ttl.is_none() {
+            return;
+        }
+        let expired: Vec<Row> = self.rows.iter()
+            .filter(|(_, state)| self.is_expired(state.accessed_at, now))
+            .map(|(row, _)| row.clone())
+            .collect();
+        for row in expired {
+            self.rows.remove(&row);
+            self.store.mark_dirty(row);
+        }
+    }
+
+    /// Loads the rows from the state.
+    fn load(&mut self) -> Result<(), ExecuteError> {
+        for (row, [count, accessed_at]) in self.store.load::<2>(&[], &self.types)? {
+            self.rows.insert(row, DistinctRowState { count, accessed_at });
+        }
+        Ok(())
+    }
+
+    /// Writes the updated rows to the state, and commits them with the epoch.
+    fn flush(&mut self, epoch: u64) -> Result<(), ExecuteError> {
+        let rows = &self.rows;
+        self.store.flush(epoch, |row| {
+            let state = rows.get(&row).map(|state| [state.count, state.accessed_at]);
+            (state_key(&[], &row), state)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::MemoryState;
+    use crate::types::{DataTypeKind, DataValue};
+
+    #[test]
+    fn test_apply() {
+        let types = vec![DataType::new_nullable(DataTypeKind::Int32)];
+        let mut this = ExecutorInner::new(types.clone(), Some(Duration::from_millis(10)),
+                                          std::sync::Arc::new(MemoryState::new()));
+        let row = |v| vec![DataValue::Int32(v)];
+        let chunk = |rows: &[(RowKind, i32)]| {
+            let mut builder = DataChunkBuilder::new(&types, PROCESSING_WINDOW_SIZE);
+            for (op, v) in rows {
+                builder.push_row_with_op(*op, row(*v));
+            }
+            builder.take().unwrap()
+        };
+
+        let changes = this.apply(&chunk(&[(RowKind::Insert, 1), (RowKind::Insert, 1), (RowKind::Insert, 2)]), 0);
+        assert_eq!(changes, vec![(RowKind::Insert, row(1)), (RowKind::Insert, row(2))]);
+        // the row is deleted once both copies are retracted
+        let changes = this.apply(&chunk(&[(RowKind::Delete, 1), (RowKind::Delete, 3)]), 5);
+        assert!(changes.is_empty());
+        let changes = this.apply(&chunk(&[(RowKind::Delete, 1)]), 6);
+        assert_eq!(changes, vec![(RowKind::Delete, row(1))]);
+        // the row 2 is forgotten after the ttl and emitted again
+        let changes = this.apply(&chunk(&[(RowKind::Insert, 2)]), 10);
+        assert_eq!(changes, vec![(RowKind::Insert, row(2))]);
+        this.expire(20);
+        assert!(this.rows.is_empty());
+    }
+
+    #[test]
+    fn test_retract_expired() {
+        let types = vec![DataType::new_nullable(DataTypeKind::Int32)];
+        let state: StateRef = std::sync::Arc::new(MemoryState::new());
+        let mut this = ExecutorInner::new(types.clone(), Some(Duration::from_millis(10)), state.clone());
+        let chunk = |op| {
+            let mut builder = DataChunkBuilder::new(&types, PROCESSING_WINDOW_SIZE);
+            builder.push_row_with_op(op, vec![DataValue::Int32(1)]);
+            builder.take().unwrap()
+        };
+
+        this.apply(&chunk(RowKind::Insert), 0);
+        this.flush(1).unwrap();
+        // the expired row is forgotten by the retraction, which is ignored
+        assert!(this.apply(&chunk(RowKind::Delete), 10).is_empty());
+        this.flush(2).unwrap();
+        let mut reloaded = ExecutorInner::new(types.clone(), Some(Duration::from_millis(10)), state);
+        reloaded.load().unwrap();
+        assert!(reloaded.rows.is_empty());
+    }
+}
@@ -1,8 +1,9 @@
 use std::fmt::{Display, Formatter};
 use egg::{Id, Language};
-use crate::array::{ArrayBuilderImpl, ArrayImpl, DataChunk};
+use crate::array::{Array, ArrayBuilderImpl, ArrayImpl, DataChunk};
+use crate::executor::ExecuteError;
 use crate::planner::{Expr, RecExpr};
-use crate::types::{ConvertError, DataValue};
+use crate::types::{ConvertError, DataType, DataTypeKind, DataValue};
 
 
 
@@ -40,6 +41,31 @@ impl<'a> Evaluator<'a> {
 
     pub fn eval(&self, chunk: &DataChunk) -> Result<ArrayImpl, ConvertError> {
         use Expr::*;
+        if let Some((op, a, b)) = self.node().binary_op() {
+            // a NULL literal has no specific type,
+            // so comparing or computing with it always returns NULL
+            if self.is_null(a) || self.is_null(b) {
+                if self.node().is_comparison() {
+                    return Ok(ArrayImpl::new_null(&DataType::new_nullable(DataTypeKind::Bool),
+                                                  chunk.cardinality()));
+                }
+                if !matches!(self.node(), And(_) | Or(_)) {
+                    let other = if self.is_null(a) { b } else { a };
+                    let ty = self.next(other).eval(chunk)?.data_type();
+                    return Ok(ArrayImpl::new_null(&ty, chunk.cardinality()));
+                }
+            }
+            let left = self.next(a).eval(chunk)?;
+            let right = self.next(b).eval(chunk)?;
+            return left.binary_op(&op, &right);
+        }
+        if let Some((op, a)) = self.node().unary_op() {
+            let array = self.next(a).eval(chunk)?;
+            if self.is_null(a) {
+                return Ok(array);
+            }
+            return array.unary_op(&op);
+        }
         match self.node() {
             Constant(v) => {
                 let mut builder =
@@ -56,12 +82,27 @@ impl<'a> Evaluator<'a> {
         }
     }
 
+    fn is_null(&self, id: Id) -> bool {
+        self.expr[id] == Expr::null()
+    }
+
     pub fn eval_list(&self, chunk: &DataChunk) -> Result<DataChunk, ConvertError> {
         let list = self.node().as_list();
         if list.is_empty() {
             return Ok(DataChunk::no_column())
         }
-        list.iter().map(|id| self.next(*id).eval(chunk)).collect()
+        let arrays: Vec<_> = list.iter().map(|id| self.next(*id).eval(chunk)).try_collect()?;
+        Ok(arrays.into_iter().collect())
+    }
+
+    /// Evaluates a condition on each row of the chunk, where null is treated as false.
+    pub fn eval_condition(&self, chunk: &DataChunk) -> Result<Vec<bool>, ExecuteError> {
+        match self.eval(chunk)? {
+            ArrayImpl::Bool(condition) => Ok((0..condition.len())
+                .map(|i| condition.get(i).copied().unwrap_or(false))
+                .collect()),
+            other => Err(ExecuteError::NotBoolCondition(self.to_string(), other.data_type().kind())),
+        }
     }
 
     /// Returns the initial aggregation states.
@@ -71,18 +112,37 @@ impl<'a> Evaluator<'a> {
             .collect()
     }
 
-    /// Append a list of values to a list of agg states.
+    /// Append a list of values to a list of agg states,
+    /// along with the numbers of non-null values aggregated.
     pub fn agg_list_append(
         &self,
         states: &mut [DataValue],
+        non_null_counts: &mut [i64],
         values: impl Iterator<Item = DataValue>,
     ) {
         let list = self.node().as_list();
-        for ((state, id), value) in states.iter_mut().zip(list).zip(values) {
+        for (((state, count), id), value) in states.iter_mut().zip(non_null_counts).zip(list).zip(values) {
+            *count += !value.is_null() as i64;
             *state = self.next(*id).agg_append(state.clone(), value);
         }
     }
 
+    /// Retract a list of values from a list of agg states,
+    /// along with the numbers of non-null values aggregated.
+    pub fn agg_list_retract(
+        &self,
+        states: &mut [DataValue],
+        non_null_counts: &mut [i64],
+        values: impl Iterator<Item = DataValue>,
+    ) -> Result<(), ConvertError> {
+        let list = self.node().as_list();
+        for (((state, count), id), value) in states.iter_mut().zip(non_null_counts).zip(list).zip(values) {
+            *count -= !value.is_null() as i64;
+            *state = self.next(*id).agg_retract(state.clone(), value, *count)?;
+        }
+        Ok(())
+    }
+
     fn init_agg_state(&self) -> DataValue {
         use Expr::*;
         match self.node() {
@@ -97,9 +157,24 @@ impl<'a> Evaluator<'a> {
         use Expr::*;
         match self.node() {
             Count(_) => state.add(DataValue::Int32(!value.is_null() as _)),
+            // nulls are ignored
+            Sum(_) if value.is_null() => state,
             Sum(_) => state.add(value),
             t => panic!("not aggregation: {t}"),
         }
     }
 
+    /// Retracts a value from an agg state, where `non_null_count` is the number of
+    /// non-null values left.
+    fn agg_retract(&self, state: DataValue, value: DataValue, non_null_count: i64) -> Result<DataValue, ConvertError> {
+        use Expr::*;
+        match self.node() {
+            Count(_) => state.sub_nullable(DataValue::Int32(!value.is_null() as _)),
+            // the sum of no values is null
+            Sum(_) if non_null_count == 0 => Ok(DataValue::Null),
+            Sum(_) => state.sub_nullable(value),
+            t => panic!("not aggregation: {t}"),
+        }
+    }
+
 }
\ No newline at end of file
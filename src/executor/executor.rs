@@ -34,11 +34,16 @@ impl WrapExecutor {
                         .notify_barrier_complete(barrier.epoch, self.actor_id);
                     yield Message::Barrier(barrier);
                 },
+                Message::Watermark(watermark) => {
+                    yield Message::Watermark(watermark)
+                },
                 Message::Chunk(_chunk) => {
                     yield Message::Chunk(_chunk);
                 }
             }
         }
+        // an ended input, e.g. one side of a join, must not block the following barriers
+        self.barrier_manager.lock().unwrap().finish_actor(self.actor_id);
     }
 }
 
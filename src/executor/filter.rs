@@ -0,0 +1,94 @@
+use futures_async_stream::try_stream;
+use crate::executor::BoxedExecutor;
+use crate::stream::Message;
+use crate::executor::ExecuteError;
+use crate::executor::evaluator::Evaluator;
+use crate::planner::RecExpr;
+
+/// The executor of filter operation.
+pub struct FilterExecutor {
+    pub condition: RecExpr,
+    pub child: BoxedExecutor,
+}
+
+impl FilterExecutor {
+
+    #[try_stream(boxed, ok = Message, error = ExecuteError)]
+    pub async fn execute(self) {
+        #[for_await]
+        for batch in self.child {
+            let batch = batch?;
+            match batch {
+                Message::Barrier(barrier) => {
+                    yield Message::Barrier(barrier)
+                },
+                Message::Watermark(watermark) => {
+                    yield Message::Watermark(watermark)
+                },
+                Message::Chunk(chunk) => {
+                    let visibility = Evaluator::new(&self.condition).eval_condition(&chunk)?;
+                    let chunk = chunk.filter(&visibility);
+                    if chunk.cardinality() > 0 {
+                        yield Message::Chunk(chunk)
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{StreamExt, TryStreamExt};
+    use crate::array::{DataChunk, DataChunkBuilder};
+    use crate::executor::PROCESSING_WINDOW_SIZE;
+    use crate::planner::Expr;
+    use crate::stream::Barrier;
+    use crate::types::{ColumnIndex, DataType, DataTypeKind, DataValue};
+    use super::*;
+
+    async fn filter(condition: RecExpr, chunk: DataChunk) -> Result<Vec<Message>, ExecuteError> {
+        let child = futures::stream::iter([
+            Ok(Message::Chunk(chunk)),
+            Ok(Message::Barrier(Barrier { epoch: 1, timestamp: 0 })),
+        ]);
+        FilterExecutor { condition, child: child.boxed() }.execute().try_collect().await
+    }
+
+    #[tokio::test]
+    async fn test_filter() {
+        let types = vec![DataType::new_nullable(DataTypeKind::Int32)];
+        let mut builder = DataChunkBuilder::new(&types, PROCESSING_WINDOW_SIZE);
+        for v in [DataValue::Int32(1), DataValue::Null, DataValue::Int32(3)] {
+            builder.push_row(vec![v]);
+        }
+        let chunk = builder.take().unwrap();
+        // (> #0 1)
+        let mut condition = RecExpr::default();
+        let column = condition.add(Expr::ColumnIndex(ColumnIndex(0)));
+        let one = condition.add(Expr::Constant(DataValue::Int32(1)));
+        condition.add(Expr::Gt([column, one]));
+
+        // the row whose condition is null is filtered out as false
+        let output = filter(condition, chunk.clone()).await.unwrap();
+        let [Message::Chunk(output), Message::Barrier(_)] = output.as_slice() else {
+            panic!("unexpected output: {:?}", output);
+        };
+        assert_eq!(output.cardinality(), 1);
+        assert_eq!(output.row(0).to_owned(), vec![DataValue::Int32(3)]);
+
+        // (> #0 null) filters out all rows, and only the barrier is passed
+        let mut condition = RecExpr::default();
+        let column = condition.add(Expr::ColumnIndex(ColumnIndex(0)));
+        let null = condition.add(Expr::null());
+        condition.add(Expr::Gt([column, null]));
+        let output = filter(condition, chunk.clone()).await.unwrap();
+        assert!(matches!(output.as_slice(), [Message::Barrier(_)]));
+
+        // a condition not boolean is an error
+        let mut condition = RecExpr::default();
+        condition.add(Expr::ColumnIndex(ColumnIndex(0)));
+        assert!(matches!(filter(condition, chunk).await,
+                         Err(ExecuteError::NotBoolCondition(_, DataTypeKind::Int32))));
+    }
+}
@@ -1,17 +1,28 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use bytes::{Buf, BufMut, BytesMut};
 use futures_async_stream::try_stream;
 use smallvec::SmallVec;
 use crate::executor::BoxedExecutor;
 use crate::planner::RecExpr;
 use crate::types::{DataType, DataValue};
-use crate::array::DataChunk;
+use crate::array::{DataChunk, RowKind};
 use crate::stream::Message;
 use crate::executor::ExecuteError;
 use crate::executor::evaluator::Evaluator;
 use crate::array::DataChunkBuilder;
+use crate::state::{StateError, StateRef};
+use crate::state::serde::{deserialize_row, serialize_key, serialize_row};
 
 const PROCESSING_WINDOW_SIZE: usize = 1024;
 
+/// The number of groups cached in memory beyond the ones updated in the epoch,
+/// the others are read from the state when they're updated.
+const GROUP_CACHE_SIZE: usize = 1 << 16;
+
+/// The executor of aggregations by group keys, whose groups are flushed to the state at each barrier.
+///
+/// The state of a new job is empty, so the groups of an earlier job are only reloaded
+/// when the job is restored from a checkpoint by `RESTORE JOB`.
 pub struct HashAggExecutor {
     pub child: BoxedExecutor,
     inner: ExecutorInner
@@ -21,7 +32,14 @@ pub struct ExecutorInner {
     pub aggs: RecExpr,
     pub group_keys: RecExpr,
     pub types: Vec<DataType>,
-    pub state_entries: HashMap<GroupKeys, AggValue>,
+    /// The cached groups, including all the groups updated since the last barrier.
+    pub state_entries: HashMap<GroupKeys, GroupState>,
+    /// The number of groups kept in the cache after a barrier.
+    pub cache_size: usize,
+    /// The persistent state of groups, which is keyed by the group keys.
+    pub state: StateRef,
+    /// The groups updated since the last barrier, which are deleted if not cached.
+    pub dirty_keys: HashSet<GroupKeys>,
 }
 
 
@@ -29,39 +47,145 @@ pub type GroupKeys = SmallVec<[DataValue; 4]>;
 
 pub type AggValue = SmallVec<[DataValue; 16]>;
 
+/// The numbers of non-null values aggregated by each aggregation.
+pub type AggCounts = SmallVec<[i64; 16]>;
+
+/// The state of a group.
+#[derive(Clone)]
+pub struct GroupState {
+    /// The number of rows in the group, the group is deleted once it drops to zero.
+    pub row_count: i64,
+    pub aggs: AggValue,
+    /// The numbers of non-null values aggregated, so the aggs of no values are null.
+    pub non_null_counts: AggCounts,
+}
+
 impl ExecutorInner {
 
-    pub fn new(aggs: RecExpr, group_keys: RecExpr, types: Vec<DataType>) -> Self{
+    pub fn new(aggs: RecExpr, group_keys: RecExpr, types: Vec<DataType>, state: StateRef) -> Self{
         ExecutorInner {
             aggs,
             group_keys,
             types,
-            state_entries: HashMap::new()
+            state_entries: HashMap::new(),
+            cache_size: GROUP_CACHE_SIZE,
+            state,
+            dirty_keys: HashSet::new(),
         }
     }
 
+    /// Reads a group into the cache from the state if it's not cached.
+    ///
+    /// The output types are the agg values followed by the group keys,
+    /// the state value is the row count followed by the numbers of non-null values
+    /// aggregated and the agg values.
+    fn load_group(&mut self, keys: &GroupKeys) -> Result<(), ExecuteError> {
+        // an updated group not cached is deleted, but not yet in the state
+        if self.state_entries.contains_key(keys) || self.dirty_keys.contains(keys) {
+            return Ok(());
+        }
+        let Some(value) = self.state.get(&serialize_key(keys))? else {
+            return Ok(());
+        };
+        let num_aggs = self.aggs.as_ref().last().unwrap().as_list().len();
+        let value = &mut BytesMut::from(value.as_slice());
+        if value.len() < 8 * (1 + num_aggs) {
+            return Err(StateError::Corrupted("invalid group state".into()).into());
+        }
+        let row_count = value.get_i64_le();
+        let non_null_counts = (0..num_aggs).map(|_| value.get_i64_le()).collect();
+        let aggs = deserialize_row(&self.types[..num_aggs], value)?;
+        self.state_entries.insert(keys.clone(), GroupState {
+            row_count,
+            aggs: aggs.into_iter().collect(),
+            non_null_counts,
+        });
+        Ok(())
+    }
+
+    /// Writes the updated groups to the state, and commits them with the epoch.
+    fn flush(&mut self, epoch: u64) -> Result<(), ExecuteError> {
+        for keys in self.dirty_keys.drain() {
+            let key = serialize_key(&keys);
+            match self.state_entries.get(&keys) {
+                Some(group) => {
+                    let mut value = vec![];
+                    value.put_i64_le(group.row_count);
+                    for count in &group.non_null_counts {
+                        value.put_i64_le(*count);
+                    }
+                    value.extend(serialize_row(&group.aggs));
+                    self.state.put(key, value)?;
+                }
+                None => self.state.delete(key)?,
+            }
+        }
+        self.state.commit(epoch)?;
+        // all the groups are written, so any of them can be evicted
+        if self.state_entries.len() > self.cache_size {
+            let mut num_evicted = self.state_entries.len() - self.cache_size;
+            self.state_entries.retain(|_, _| {
+                let evicted = num_evicted > 0;
+                num_evicted = num_evicted.saturating_sub(1);
+                !evicted
+            });
+        }
+        Ok(())
+    }
+
     #[try_stream(boxed, ok = Message, error = ExecuteError)]
     async fn execute_inner(&mut self, chunk: DataChunk) {
         let keys_chunk = Evaluator::new(&self.group_keys).eval_list(&chunk)?;
         let args_chunk = Evaluator::new(&self.aggs).eval_list(&chunk)?;
+        let num_aggs = self.aggs.as_ref().last().unwrap().as_list().len();
 
-        let mut entries_to_flush = HashMap::new();
+        // the results of the updated groups before this chunk
+        let mut entries_to_flush: HashMap<GroupKeys, Option<AggValue>> = HashMap::new();
 
         for i in 0..chunk.cardinality() {
             let keys: GroupKeys = keys_chunk.row(i).values().collect();
-            let states = self.state_entries
-                .entry(keys.clone())
-                .or_insert_with(|| Evaluator::new(&self.aggs).init_agg_states());
-            Evaluator::new(&self.aggs).agg_list_append(states, args_chunk.row(i).values());
-            entries_to_flush.insert(keys.clone(), states.clone());
+            self.load_group(&keys)?;
+            let old = self.state_entries.get(&keys).map(|state| state.aggs.clone());
+            entries_to_flush.entry(keys.clone()).or_insert(old);
+            self.dirty_keys.insert(keys.clone());
+            let state = self.state_entries
+                .entry(keys)
+                .or_insert_with(|| GroupState {
+                    row_count: 0,
+                    aggs: Evaluator::new(&self.aggs).init_agg_states(),
+                    non_null_counts: smallvec::smallvec![0; num_aggs],
+                });
+            if chunk.op(i).is_retract() {
+                state.row_count -= 1;
+                Evaluator::new(&self.aggs).agg_list_retract(&mut state.aggs, &mut state.non_null_counts, args_chunk.row(i).values())?;
+            } else {
+                state.row_count += 1;
+                Evaluator::new(&self.aggs).agg_list_append(&mut state.aggs, &mut state.non_null_counts, args_chunk.row(i).values());
+            }
         }
 
-
+        // retract the old result and emit the new one of each updated group
         let mut builder = DataChunkBuilder::new(&self.types, PROCESSING_WINDOW_SIZE);
-        for (key, aggs) in entries_to_flush {
-            let row = aggs.into_iter().chain(key.into_iter());
-            if let Some(chunk) = builder.push_row(row) {
-                yield Message::Chunk(chunk)
+        for (key, old) in entries_to_flush {
+            let new = match self.state_entries.get(&key) {
+                Some(state) if state.row_count > 0 => Some(state.aggs.clone()),
+                _ => {
+                    self.state_entries.remove(&key);
+                    None
+                }
+            };
+            let changes = match (old, new) {
+                (Some(old), Some(new)) if old == new => vec![],
+                (Some(old), Some(new)) => vec![(RowKind::UpdateBefore, old), (RowKind::UpdateAfter, new)],
+                (None, Some(new)) => vec![(RowKind::Insert, new)],
+                (Some(old), None) => vec![(RowKind::Delete, old)],
+                (None, None) => vec![],
+            };
+            for (op, aggs) in changes {
+                let row = aggs.into_iter().chain(key.iter().cloned());
+                if let Some(chunk) = builder.push_row_with_op(op, row) {
+                    yield Message::Chunk(chunk)
+                }
             }
         }
         if let Some(chunk) = builder.take() {
@@ -74,13 +198,14 @@ impl HashAggExecutor {
 
     pub fn new(aggs: RecExpr,
                group_keys: RecExpr,
-               types: Vec<DataType>, child: BoxedExecutor) -> Self {
+               types: Vec<DataType>, state: StateRef, child: BoxedExecutor) -> Self {
         HashAggExecutor {
             child,
             inner: ExecutorInner::new(
                 aggs,
                 group_keys,
-                types)
+                types,
+                state)
         }
     }
 
@@ -96,8 +221,12 @@ impl HashAggExecutor {
             let chunk = chunk?;
             match chunk {
                 Message::Barrier(barrier) => {
+                    this.flush(barrier.epoch)?;
                     yield Message::Barrier(barrier)
                 },
+                Message::Watermark(watermark) => {
+                    yield Message::Watermark(watermark)
+                },
                 Message::Chunk(chunk) => {
                     #[for_await]
                     for chunk in this.execute_inner(chunk) {
@@ -107,4 +236,146 @@ impl HashAggExecutor {
             }
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use futures::{StreamExt, TryStreamExt};
+    use crate::array::Row;
+    use crate::planner::Expr;
+    use crate::state::{DiskState, MemoryState};
+    use crate::types::{ColumnIndex, ConvertError, DataTypeKind};
+    use super::*;
+
+    /// Returns the executor of `SELECT count(#0), #0 GROUP BY #0` on a column of int.
+    fn count_by_key(state: StateRef) -> ExecutorInner {
+        let mut aggs = RecExpr::default();
+        let column = aggs.add(Expr::ColumnIndex(ColumnIndex(0)));
+        let count = aggs.add(Expr::Count(column));
+        aggs.add(Expr::List([count].into()));
+        let mut group_keys = RecExpr::default();
+        let column = group_keys.add(Expr::ColumnIndex(ColumnIndex(0)));
+        group_keys.add(Expr::List([column].into()));
+        let types = vec![DataType::new_nullable(DataTypeKind::Int32); 2];
+        ExecutorInner::new(aggs, group_keys, types, state)
+    }
+
+    /// Processes the rows of keys, returning the changes of the groups in the order of keys.
+    async fn process(this: &mut ExecutorInner, rows: &[(RowKind, i32)]) -> Vec<(RowKind, Row)> {
+        let mut builder = DataChunkBuilder::new(&[DataType::new_nullable(DataTypeKind::Int32)], PROCESSING_WINDOW_SIZE);
+        for (op, key) in rows {
+            builder.push_row_with_op(*op, [DataValue::Int32(*key)]);
+        }
+        let output: Vec<Message> = this.execute_inner(builder.take().unwrap()).try_collect().await.unwrap();
+        let mut changes: Vec<(RowKind, Row)> = output.iter()
+            .flat_map(|msg| match msg {
+                Message::Chunk(chunk) => (0..chunk.cardinality())
+                    .map(|i| (chunk.op(i), chunk.row(i).to_owned()))
+                    .collect(),
+                _ => vec![],
+            })
+            .collect();
+        changes.sort_by_key(|(_, row)| row[1].clone());
+        changes
+    }
+
+    fn group(count: i32, key: i32) -> Row {
+        vec![DataValue::Int32(count), DataValue::Int32(key)]
+    }
+
+    #[tokio::test]
+    async fn test_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let chunk = |op| {
+            let mut builder = DataChunkBuilder::new(&[DataType::new_nullable(DataTypeKind::Int32)], PROCESSING_WINDOW_SIZE);
+            builder.push_row_with_op(op, [DataValue::Int32(1)]);
+            Ok(Message::Chunk(builder.take().unwrap()))
+        };
+        let barrier = |epoch| Ok(Message::Barrier(crate::stream::Barrier { epoch, timestamp: 0 }));
+        let run = |input: Vec<Result<Message, ExecuteError>>| {
+            let ExecutorInner { aggs, group_keys, types, state, .. } =
+                count_by_key(std::sync::Arc::new(DiskState::open(dir.path()).unwrap()));
+            HashAggExecutor::new(aggs, group_keys, types, state, futures::stream::iter(input).boxed())
+                .execute()
+                .try_filter_map(|msg| async move {
+                    Ok(match msg {
+                        Message::Chunk(chunk) => Some((0..chunk.cardinality())
+                            .map(|i| (chunk.op(i), chunk.row(i).to_owned()))
+                            .collect::<Vec<_>>()),
+                        _ => None,
+                    })
+                })
+                .try_concat()
+        };
+
+        // the group is flushed at the barrier, but not the row after it
+        let output = run(vec![chunk(RowKind::Insert), chunk(RowKind::Insert), barrier(1), chunk(RowKind::Insert)]).await;
+        assert_eq!(output.unwrap().last(), Some(&(RowKind::UpdateAfter, group(3, 1))));
+        // the executor on the same state resumes from the barrier
+        let output = run(vec![chunk(RowKind::Delete)]).await;
+        assert_eq!(output.unwrap(), vec![(RowKind::UpdateBefore, group(2, 1)), (RowKind::UpdateAfter, group(1, 1))]);
+    }
+
+    #[tokio::test]
+    async fn test_sum_retract() {
+        // SELECT sum(#1), #0 GROUP BY #0
+        let mut aggs = RecExpr::default();
+        let column = aggs.add(Expr::ColumnIndex(ColumnIndex(1)));
+        let sum = aggs.add(Expr::Sum(column));
+        aggs.add(Expr::List([sum].into()));
+        let mut group_keys = RecExpr::default();
+        let column = group_keys.add(Expr::ColumnIndex(ColumnIndex(0)));
+        group_keys.add(Expr::List([column].into()));
+        let types = vec![DataType::new_nullable(DataTypeKind::Int32); 2];
+        let mut this = ExecutorInner::new(aggs, group_keys, types.clone(), std::sync::Arc::new(MemoryState::new()));
+
+        let mut changes = vec![];
+        for rows in [
+            vec![(RowKind::Insert, DataValue::Int32(5)), (RowKind::Insert, DataValue::Null)],
+            vec![(RowKind::Delete, DataValue::Int32(5))],
+        ] {
+            let mut builder = DataChunkBuilder::new(&types, PROCESSING_WINDOW_SIZE);
+            for (op, value) in rows {
+                builder.push_row_with_op(op, [DataValue::Int32(1), value]);
+            }
+            let output: Vec<Message> = this.execute_inner(builder.take().unwrap()).try_collect().await.unwrap();
+            for msg in output {
+                let Message::Chunk(chunk) = msg else { continue };
+                changes.extend((0..chunk.cardinality()).map(|i| (chunk.op(i), chunk.row(i).to_owned())));
+            }
+        }
+        // the null is ignored, and the sum of the group left with only the null is null
+        assert_eq!(changes, vec![
+            (RowKind::Insert, vec![DataValue::Int32(5), DataValue::Int32(1)]),
+            (RowKind::UpdateBefore, vec![DataValue::Int32(5), DataValue::Int32(1)]),
+            (RowKind::UpdateAfter, vec![DataValue::Null, DataValue::Int32(1)]),
+        ]);
+
+        // the overflow of a retraction fails the executor
+        let mut builder = DataChunkBuilder::new(&types, PROCESSING_WINDOW_SIZE);
+        builder.push_row_with_op(RowKind::Insert, [DataValue::Int32(2), DataValue::Int32(i32::MIN)]);
+        builder.push_row_with_op(RowKind::Insert, [DataValue::Int32(2), DataValue::Int32(0)]);
+        builder.push_row_with_op(RowKind::Delete, [DataValue::Int32(2), DataValue::Int32(1)]);
+        let output: Result<Vec<Message>, _> = this.execute_inner(builder.take().unwrap()).try_collect().await;
+        assert!(matches!(output, Err(ExecuteError::Convert(ConvertError::Overflow))));
+    }
+
+    #[tokio::test]
+    async fn test_group_cache() {
+        let mut this = count_by_key(std::sync::Arc::new(MemoryState::new()));
+        this.cache_size = 1;
+        let (insert, delete) = (RowKind::Insert, RowKind::Delete);
+        assert_eq!(process(&mut this, &[(insert, 1), (insert, 2)]).await,
+                   vec![(insert, group(1, 1)), (insert, group(1, 2))]);
+        this.flush(1).unwrap();
+        assert_eq!(this.state_entries.len(), 1);
+
+        // the evicted group is read from the state
+        assert_eq!(process(&mut this, &[(insert, 1), (insert, 2)]).await, vec![
+            (RowKind::UpdateBefore, group(1, 1)), (RowKind::UpdateAfter, group(2, 1)),
+            (RowKind::UpdateBefore, group(1, 2)), (RowKind::UpdateAfter, group(2, 2)),
+        ]);
+        // the deleted group isn't read from the state before it's flushed
+        assert_eq!(process(&mut this, &[(delete, 1), (delete, 1)]).await, vec![(delete, group(2, 1))]);
+        assert_eq!(process(&mut this, &[(insert, 1)]).await, vec![(insert, group(1, 1))]);
+    }
+}
@@ -0,0 +1,346 @@
+use std::collections::HashMap;
+use futures_async_stream::try_stream;
+use smallvec::SmallVec;
+use crate::array::{DataChunk, DataChunkBuilder, Row, RowKind};
+use crate::executor::align::{barrier_align, AlignedMessage, Side};
+use crate::executor::evaluator::Evaluator;
+use crate::executor::row_store::{state_key, RowStore};
+use crate::executor::{BoxedExecutor, ExecuteError, PROCESSING_WINDOW_SIZE};
+use crate::planner::RecExpr;
+use crate::state::StateRef;
+use crate::stream::Message;
+use crate::types::{DataType, DataValue};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinType {
+    Inner,
+    LeftOuter,
+    RightOuter,
+    FullOuter,
+}
+
+impl JoinType {
+    /// Returns true if the rows of the input without any match are emitted padded with nulls.
+    fn is_outer(self, side: Side) -> bool {
+        match side {
+            Side::Left => matches!(self, JoinType::LeftOuter | JoinType::FullOuter),
+            Side::Right => matches!(self, JoinType::RightOuter | JoinType::FullOuter),
+        }
+    }
+}
+
+pub type JoinKey = SmallVec<[DataValue; 4]>;
+
+/// The state of the identical rows of an input.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct JoinRowState {
+    /// The number of the identical rows, which are deleted once it drops to zero.
+    count: i64,
+    /// The number of rows of the other input matching the row.
+    degree: i64,
+}
+
+/// The executor of an equi-join of two streams.
+///
+/// Both inputs are kept in the state by the join keys, so each row is joined with
+/// the rows of the other input received before it, and a retracted row retracts
+/// what it was joined into.
+///
+/// For outer joins, the degree of each row tracks its matches, so a row is emitted
+/// padded with nulls while it has no match, and the padded row is retracted once
+/// a match arrives.
+pub struct HashJoinExecutor {
+    pub left: BoxedExecutor,
+    pub right: BoxedExecutor,
+    inner: ExecutorInner,
+}
+
+/// The rows of both inputs of a join, which is shared with
+/// [`IntervalJoinExecutor`](super::interval_join::IntervalJoinExecutor).
+pub(super) struct ExecutorInner {
+    join_type: JoinType,
+    /// The join keys of the left and right inputs.
+    keys: [RecExpr; 2],
+    /// The condition other than the join keys over the joined rows, `None` if there is none.
+    condition: Option<RecExpr>,
+    /// The output types of the left and right inputs.
+    types: [Vec<DataType>; 2],
+    /// The rows of the left and right inputs by join keys.
+    pub rows: [HashMap<JoinKey, HashMap<Row, JoinRowState>>; 2],
+    /// The persistent rows, which are keyed by the input and the row.
+    pub store: RowStore<(Side, JoinKey, Row)>,
+}
+
+impl HashJoinExecutor {
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(join_type: JoinType,
+               left_keys: RecExpr,
+               right_keys: RecExpr,
+               condition: Option<RecExpr>,
+               left_types: Vec<DataType>,
+               right_types: Vec<DataType>,
+               state: StateRef,
+               left: BoxedExecutor,
+               right: BoxedExecutor) -> Self {
+        HashJoinExecutor {
+            left,
+            right,
+            inner: ExecutorInner::new(join_type, [left_keys, right_keys], condition,
+                                      [left_types, right_types], state),
+        }
+    }
+
+    #[try_stream(boxed, ok = Message, error = ExecuteError)]
+    pub async fn execute(self) {
+        let HashJoinExecutor { left, right, inner: mut this } = self;
+        this.load()?;
+        #[for_await]
+        for msg in barrier_align(left, right) {
+            match msg? {
+                AlignedMessage::Chunk(side, chunk) => {
+                    let keys = this.eval_keys(side, &chunk)?;
+                    for chunk in this.process(side, &chunk, keys)? {
+                        yield Message::Chunk(chunk)
+                    }
+                }
+                AlignedMessage::Barrier(barrier) => {
+                    this.flush(barrier.epoch)?;
+                    yield Message::Barrier(barrier)
+                }
+                AlignedMessage::Watermark(watermark) => {
+                    yield Message::Watermark(watermark)
+                }
+            }
+        }
+    }
+}
+
+impl ExecutorInner {
+
+    pub fn new(join_type: JoinType, keys: [RecExpr; 2], condition: Option<RecExpr>,
+               types: [Vec<DataType>; 2], state: StateRef) -> Self {
+        ExecutorInner {
+            join_type,
+            keys,
+            condition,
+            types,
+            rows: Default::default(),
+            store: RowStore::new(state),
+        }
+    }
+
+    /// Returns the join keys of the rows of an input.
+    pub fn eval_keys(&self, side: Side, chunk: &DataChunk) -> Result<Vec<JoinKey>, ExecuteError> {
+        let keys = &self.keys[side as usize];
+        if keys.as_ref().last().unwrap().as_list().is_empty() {
+            return Ok(vec![JoinKey::new(); chunk.cardinality()]);
+        }
+        let keys_chunk = Evaluator::new(keys).eval_list(chunk)?;
+        Ok((0..chunk.cardinality()).map(|i| keys_chunk.row(i).values().collect()).collect())
+    }
+
+    /// Loads the rows from the state.
+    ///
+    /// The state key is the input followed by the row, the state value is the count
+    /// followed by the degree.
+    pub fn load(&mut self) -> Result<(), ExecuteError> {
+        for side in [Side::Left, Side::Right] {
+            let loaded = self.store.load::<2>(&[side as u8], &self.types[side as usize])?;
+            if loaded.is_empty() {
+                continue;
+            }
+            let chunk = build_chunk(&self.types[side as usize], loaded.iter().map(|(row, _)| row.clone()));
+            let keys = self.eval_keys(side, &chunk)?;
+            for ((row, [count, degree]), key) in loaded.into_iter().zip(keys) {
+                self.rows[side as usize].entry(key).or_default().insert(row, JoinRowState { count, degree });
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the updated rows to the state, and commits them with the epoch.
+    pub fn flush(&mut self, epoch: u64) -> Result<(), ExecuteError> {
+        let rows = &self.rows;
+        self.store.flush(epoch, |(side, key, row)| {
+            let state = rows[side as usize].get(&key).and_then(|rows| rows.get(&row));
+            (state_key(&[side as u8], &row), state.map(|state| [state.count, state.degree]))
+        })
+    }
+
+    /// Returns the rows of the other input joined with a row, along with their counts.
+    fn matches(&self, side: Side, key: &JoinKey, row: &Row) -> Result<Vec<(Row, i64)>, ExecuteError> {
+        // a null never equals anything
+        if key.iter().any(|v| v.is_null()) {
+            return Ok(vec![]);
+        }
+        let Some(others) = self.rows[side.other() as usize].get(key) else {
+            return Ok(vec![]);
+        };
+        let candidates: Vec<(Row, i64)> = others.iter()
+            .map(|(other, state)| (other.clone(), state.count))
+            .collect();
+        let Some(condition) = &self.condition else {
+            return Ok(candidates);
+        };
+        if candidates.is_empty() {
+            return Ok(candidates);
+        }
+        let chunk = build_chunk(self.types.iter().flatten(),
+                                candidates.iter().map(|(other, _)| self.join_row(side, row, Some(other))));
+        let visible = Evaluator::new(condition).eval_condition(&chunk)?;
+        Ok(candidates.into_iter()
+            .enumerate()
+            .filter(|(i, _)| visible[*i])
+            .map(|(_, candidate)| candidate)
+            .collect())
+    }
+
+    /// Returns the output row of a row joined with a row of the other input,
+    /// or padded with nulls if `other` is `None`.
+    fn join_row(&self, side: Side, row: &Row, other: Option<&Row>) -> Row {
+        let other = match other {
+            Some(other) => other.clone(),
+            None => vec![DataValue::Null; self.types[side.other() as usize].len()],
+        };
+        match side {
+            Side::Left => row.iter().cloned().chain(other).collect(),
+            Side::Right => other.into_iter().chain(row.iter().cloned()).collect(),
+        }
+    }
+
+    /// Joins the rows of an input with the other input, returning the output changes.
+    pub fn process(&mut self, side: Side, chunk: &DataChunk, keys: Vec<JoinKey>)
+        -> Result<Vec<DataChunk>, ExecuteError> {
+        let other_side = side.other();
+        let mut output = vec![];
+        let mut builder = DataChunkBuilder::new(self.types.iter().flatten(), PROCESSING_WINDOW_SIZE);
+        let mut emit = |op: RowKind, row: Row, count: i64| {
+            for _ in 0..count {
+                if let Some(chunk) = builder.push_row_with_op(op, row.iter().cloned()) {
+                    output.push(chunk);
+                }
+            }
+        };
+
+        for (i, key) in keys.into_iter().enumerate() {
+            let op = chunk.op(i);
+            let row = chunk.row(i).to_owned();
+            let matches = self.matches(side, &key, &row)?;
+            if op.is_retract() {
+                // a retraction of a row never received, e.g. before the job is restored
+                let Some(rows) = self.rows[side as usize].get_mut(&key) else {
+                    continue;
+                };
+                let Some(state) = rows.get_mut(&row) else {
+                    continue;
+                };
+                state.count -= 1;
+                if state.count == 0 {
+                    rows.remove(&row);
+                }
+                if rows.is_empty() {
+                    self.rows[side as usize].remove(&key);
+                }
+            }
+            let mut degree = 0;
+            for (other, count) in matches {
+                let joined = self.join_row(side, &row, Some(&other));
+                // the padded row of the other input is retracted on its first match,
+                // and emitted again once it has no match
+                let padded = self.join_type.is_outer(other_side).then(|| self.join_row(other_side, &other, None));
+                let other_state = self.rows[other_side as usize].get_mut(&key).unwrap()
+                    .get_mut(&other).unwrap();
+                if op.is_retract() {
+                    emit(op, joined, count);
+                    other_state.degree -= 1;
+                    if let (Some(padded), 0) = (padded, other_state.degree) {
+                        emit(RowKind::Insert, padded, count);
+                    }
+                } else {
+                    if let (Some(padded), 0) = (padded, other_state.degree) {
+                        emit(RowKind::Delete, padded, count);
+                    }
+                    other_state.degree += 1;
+                    emit(op, joined, count);
+                }
+                self.store.mark_dirty((other_side, key.clone(), other));
+                degree += count;
+            }
+            if degree == 0 && self.join_type.is_outer(side) {
+                emit(op, self.join_row(side, &row, None), 1);
+            }
+            if !op.is_retract() {
+                let state = self.rows[side as usize].entry(key.clone()).or_default()
+                    .entry(row.clone())
+                    .or_insert(JoinRowState { count: 0, degree });
+                state.count += 1;
+            }
+            self.store.mark_dirty((side, key, row));
+        }
+        if let Some(chunk) = builder.take() {
+            output.push(chunk);
+        }
+        Ok(output)
+    }
+}
+
+/// Builds a single chunk of the rows, which must not be empty.
+pub(super) fn build_chunk<'a>(types: impl IntoIterator<Item = &'a DataType>, rows: impl ExactSizeIterator<Item = Row>) -> DataChunk {
+    let mut builder = DataChunkBuilder::new(types, rows.len());
+    let mut chunk = None;
+    for row in rows {
+        // the chunk is returned once it's full
+        chunk = builder.push_row(row);
+    }
+    chunk.expect("no rows to build a chunk")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::MemoryState;
+    use crate::planner::Expr;
+    use crate::types::{ColumnIndex, DataTypeKind};
+
+    #[test]
+    fn test_outer_join_retraction() {
+        let types = vec![DataType::new_nullable(DataTypeKind::Int32)];
+        // (list #0)
+        let mut keys = RecExpr::default();
+        let column = keys.add(Expr::ColumnIndex(ColumnIndex(0)));
+        keys.add(Expr::List([column].into()));
+        let mut join = ExecutorInner::new(JoinType::LeftOuter, [keys.clone(), keys], None,
+                                          [types.clone(), types], std::sync::Arc::new(MemoryState::new()));
+        let chunk = |ops: &[RowKind]| DataChunk::single(1).with_ops(ops.iter().copied());
+        let mut process = |side, chunk: DataChunk| {
+            let keys = join.eval_keys(side, &chunk).unwrap();
+            join.process(side, &chunk, keys).unwrap()
+        };
+        let changes = |chunks: Vec<DataChunk>| -> Vec<(RowKind, Row)> {
+            chunks.iter()
+                .flat_map(|chunk| (0..chunk.cardinality()).map(|i| (chunk.op(i), chunk.row(i).to_owned())))
+                .collect()
+        };
+        let (one, null) = (DataValue::Int32(1), DataValue::Null);
+
+        // the left row without match is padded with nulls
+        assert_eq!(changes(process(Side::Left, chunk(&[RowKind::Insert]))),
+                   vec![(RowKind::Insert, vec![one.clone(), null.clone()])]);
+        // the padded row is retracted by the first match
+        assert_eq!(changes(process(Side::Right, chunk(&[RowKind::Insert]))), vec![
+            (RowKind::Delete, vec![one.clone(), null.clone()]),
+            (RowKind::Insert, vec![one.clone(), one.clone()]),
+        ]);
+        // and emitted again once the match is retracted
+        assert_eq!(changes(process(Side::Right, chunk(&[RowKind::Delete]))), vec![
+            (RowKind::Delete, vec![one.clone(), one.clone()]),
+            (RowKind::Insert, vec![one.clone(), null.clone()]),
+        ]);
+        assert_eq!(changes(process(Side::Left, chunk(&[RowKind::Delete]))),
+                   vec![(RowKind::Delete, vec![one, null])]);
+        // a retraction of a row never received is ignored, and no key is kept
+        assert!(process(Side::Right, chunk(&[RowKind::Delete])).is_empty());
+        assert!(join.rows.iter().all(|rows| rows.is_empty()));
+    }
+}
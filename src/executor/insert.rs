@@ -1,12 +1,13 @@
 use futures_async_stream::try_stream;
-use crate::connector::{StreamConnector};
-use crate::executor::BoxedExecutor;
+use crate::connector::BoxedSink;
+use crate::executor::{BarrierManagerRef, BoxedExecutor};
 use crate::stream::Message;
 use crate::executor::ExecuteError;
 
 pub struct TableInsertExecutor {
-    pub sink_connector: Box<dyn StreamConnector + Sync + Send>,
+    pub sink_connector: BoxedSink,
     pub child: BoxedExecutor,
+    pub barrier_manager: BarrierManagerRef,
 }
 
 
@@ -18,13 +19,20 @@ impl TableInsertExecutor {
         for batch in self.child {
             match batch? {
                 Message::Barrier(barrier) => {
-                    self.sink_connector.on_receive_barrier(barrier.clone());
-                    yield Message::Barrier(barrier.clone())
+                    // the output of the epoch is committed after it's checkpointed
+                    if let Some(commit) = self.sink_connector.on_barrier(&barrier).await? {
+                        self.barrier_manager.lock().unwrap().register_commit(barrier.epoch, commit);
+                    }
+                    yield Message::Barrier(barrier)
+                },
+                Message::Watermark(watermark) => {
+                    yield Message::Watermark(watermark)
                 },
                 Message::Chunk(chunk) => {
-                    self.sink_connector.write(chunk);
+                    self.sink_connector.write(chunk).await?;
                 }
             }
         }
+        self.sink_connector.close().await?;
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,177 @@
+use std::collections::BTreeMap;
+use futures_async_stream::try_stream;
+use crate::array::{DataChunk, Row};
+use crate::executor::align::{barrier_align, AlignedMessage, Side};
+use crate::executor::hash_join::{ExecutorInner, JoinKey, JoinType};
+use crate::executor::{BoxedExecutor, ExecuteError};
+use crate::planner::RecExpr;
+use crate::state::StateRef;
+use crate::stream::Message;
+use crate::types::{DataType, DataValue};
+
+/// The bounds of `left.time - right.time` in an interval join, in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IntervalBounds {
+    pub lower: i64,
+    pub upper: i64,
+}
+
+/// The executor of a join whose condition bounds the event times of both inputs,
+/// like `a.ts BETWEEN b.ts - INTERVAL '10' MINUTE AND b.ts`.
+///
+/// It joins like [`HashJoinExecutor`](super::hash_join::HashJoinExecutor), but a row is
+/// expired from the state once the watermark shows that no row of the other input can
+/// match it anymore, so the state is bounded by the interval instead of growing forever.
+pub struct IntervalJoinExecutor {
+    pub left: BoxedExecutor,
+    pub right: BoxedExecutor,
+    /// The indexes of the event time columns of the left and right inputs.
+    pub time_cols: [usize; 2],
+    pub bounds: IntervalBounds,
+    inner: ExecutorInner,
+}
+
+/// The rows of an input by event time, to be expired in order.
+/// A row with a null event time never matches, so it's expired first.
+type ExpiryQueue = BTreeMap<i64, Vec<(JoinKey, Row)>>;
+
+impl IntervalJoinExecutor {
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(join_type: JoinType,
+               left_keys: RecExpr,
+               right_keys: RecExpr,
+               condition: Option<RecExpr>,
+               left_types: Vec<DataType>,
+               right_types: Vec<DataType>,
+               time_cols: [usize; 2],
+               bounds: IntervalBounds,
+               state: StateRef,
+               left: BoxedExecutor,
+               right: BoxedExecutor) -> Self {
+        IntervalJoinExecutor {
+            left,
+            right,
+            time_cols,
+            bounds,
+            inner: ExecutorInner::new(join_type, [left_keys, right_keys], condition,
+                                      [left_types, right_types], state),
+        }
+    }
+
+    #[try_stream(boxed, ok = Message, error = ExecuteError)]
+    pub async fn execute(self) {
+        let IntervalJoinExecutor { left, right, time_cols, bounds, inner: mut this } = self;
+        this.load()?;
+        let mut queues: [ExpiryQueue; 2] = Default::default();
+        for side in [Side::Left, Side::Right] {
+            for (key, rows) in &this.rows[side as usize] {
+                for row in rows.keys() {
+                    push_expiry(&mut queues[side as usize], time_cols[side as usize], key.clone(), row.clone());
+                }
+            }
+        }
+        #[for_await]
+        for msg in barrier_align(left, right) {
+            match msg? {
+                AlignedMessage::Chunk(side, chunk) => {
+                    let keys = this.eval_keys(side, &chunk)?;
+                    push_chunk_expiry(&mut queues[side as usize], time_cols[side as usize], &chunk, &keys);
+                    for chunk in this.process(side, &chunk, keys)? {
+                        yield Message::Chunk(chunk)
+                    }
+                }
+                AlignedMessage::Barrier(barrier) => {
+                    this.flush(barrier.epoch)?;
+                    yield Message::Barrier(barrier)
+                }
+                AlignedMessage::Watermark(watermark) => {
+                    // a left row matches the right rows in [time - upper, time - lower],
+                    // and a right row matches the left rows in [time + lower, time + upper],
+                    // while the rows of both inputs before the watermark are not expected
+                    let expired_before = [
+                        watermark.timestamp.saturating_add(bounds.lower),
+                        watermark.timestamp.saturating_sub(bounds.upper),
+                    ];
+                    for side in [Side::Left, Side::Right] {
+                        expire(&mut this, side, &mut queues[side as usize], expired_before[side as usize]);
+                    }
+                    yield Message::Watermark(watermark)
+                }
+            }
+        }
+    }
+}
+
+fn push_expiry(queue: &mut ExpiryQueue, time_col: usize, key: JoinKey, row: Row) {
+    let time = match row[time_col] {
+        DataValue::Timestamp(time) => time,
+        _ => i64::MIN,
+    };
+    queue.entry(time).or_default().push((key, row));
+}
+
+/// Queues the rows added by a chunk to be expired.
+fn push_chunk_expiry(queue: &mut ExpiryQueue, time_col: usize, chunk: &DataChunk, keys: &[JoinKey]) {
+    for (i, key) in keys.iter().enumerate() {
+        if !chunk.op(i).is_retract() {
+            push_expiry(queue, time_col, key.clone(), chunk.row(i).to_owned());
+        }
+    }
+}
+
+/// Removes the rows of an input whose event time is before `expired_before` from the state.
+fn expire(this: &mut ExecutorInner, side: Side, queue: &mut ExpiryQueue, expired_before: i64) {
+    let unexpired = queue.split_off(&expired_before);
+    let expired = std::mem::replace(queue, unexpired);
+    let rows = &mut this.rows[side as usize];
+    for (key, row) in expired.into_values().flatten() {
+        // the row may be queued more than once, or retracted already
+        let Some(key_rows) = rows.get_mut(&key) else {
+            continue;
+        };
+        if key_rows.remove(&row).is_some() {
+            this.store.mark_dirty((side, key.clone(), row));
+        }
+        if key_rows.is_empty() {
+            rows.remove(&key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::array::{ArrayImpl, I64Array};
+    use crate::state::MemoryState;
+    use crate::planner::Expr;
+    use crate::types::DataTypeKind;
+
+    #[test]
+    fn test_expire() {
+        let types = vec![DataType::new_nullable(DataTypeKind::Timestamp)];
+        // (list)
+        let mut keys = RecExpr::default();
+        keys.add(Expr::List([].into()));
+        let mut join = ExecutorInner::new(JoinType::Inner, [keys.clone(), keys], None,
+                                          [types.clone(), types], std::sync::Arc::new(MemoryState::new()));
+        let mut queue = ExpiryQueue::new();
+        let chunk = DataChunk::from_array(ArrayImpl::Timestamp(I64Array::from_iter([0, 100])));
+        let keys = join.eval_keys(Side::Left, &chunk).unwrap();
+        push_chunk_expiry(&mut queue, 0, &chunk, &keys);
+        join.process(Side::Left, &chunk, keys).unwrap();
+
+        // only the rows before the bound are expired
+        expire(&mut join, Side::Left, &mut queue, 100);
+        let rows: Vec<&Row> = join.rows[Side::Left as usize].values().flat_map(|rows| rows.keys()).collect();
+        assert_eq!(rows, vec![&vec![DataValue::Timestamp(100)]]);
+        assert!(join.store.is_dirty(&(Side::Left, JoinKey::new(), vec![DataValue::Timestamp(0)])));
+        assert_eq!(queue.len(), 1);
+
+        // the expired rows no longer match
+        let chunk = DataChunk::from_array(ArrayImpl::Timestamp(I64Array::from_iter([50])));
+        let keys = join.eval_keys(Side::Right, &chunk).unwrap();
+        let output = join.process(Side::Right, &chunk, keys).unwrap();
+        assert_eq!(output.iter().map(DataChunk::cardinality).sum::<usize>(), 1);
+    }
+}
@@ -0,0 +1,93 @@
+use futures_async_stream::try_stream;
+use crate::executor::BoxedExecutor;
+use crate::stream::Message;
+use crate::executor::ExecuteError;
+
+/// The executor of limit operation.
+///
+/// It skips the first `offset` rows and emits at most `limit` rows,
+/// then ends the stream so that the job can finish by itself.
+/// The input must be append-only, as a retraction can't be matched to the rows emitted.
+pub struct LimitExecutor {
+    pub limit: usize,
+    pub offset: usize,
+    pub child: BoxedExecutor,
+}
+
+impl LimitExecutor {
+
+    #[try_stream(boxed, ok = Message, error = ExecuteError)]
+    pub async fn execute(self) {
+        let end_of_limit = self.offset.saturating_add(self.limit);
+        if end_of_limit == self.offset {
+            return Ok(());
+        }
+        // the number of rows received from child
+        let mut processed = 0;
+        #[for_await]
+        for batch in self.child {
+            let batch = batch?;
+            match batch {
+                Message::Barrier(barrier) => {
+                    yield Message::Barrier(barrier)
+                },
+                Message::Watermark(watermark) => {
+                    yield Message::Watermark(watermark)
+                },
+                Message::Chunk(chunk) => {
+                    let cardinality = chunk.cardinality();
+                    let start = processed.max(self.offset) - processed;
+                    let end = (processed + cardinality).min(end_of_limit) - processed;
+                    processed += cardinality;
+                    if start == 0 && end == cardinality {
+                        yield Message::Chunk(chunk);
+                    } else if start < end {
+                        yield Message::Chunk(chunk.slice(start..end));
+                    }
+                    if processed >= end_of_limit {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{StreamExt, TryStreamExt};
+    use crate::array::DataChunkBuilder;
+    use crate::executor::PROCESSING_WINDOW_SIZE;
+    use crate::stream::Barrier;
+    use crate::types::{DataType, DataTypeKind, DataValue};
+    use super::*;
+
+    /// Runs a limit on the chunks of 0..3 and 3..6 followed by a barrier, which never end.
+    async fn limit(limit: usize, offset: usize) -> Vec<Message> {
+        let chunk = |start: i32| {
+            let mut builder = DataChunkBuilder::new(&[DataType::new_nullable(DataTypeKind::Int32)], PROCESSING_WINDOW_SIZE);
+            for v in start..start + 3 {
+                builder.push_row([DataValue::Int32(v)]);
+            }
+            Ok(Message::Chunk(builder.take().unwrap()))
+        };
+        let child = futures::stream::iter([chunk(0), chunk(3), Ok(Message::Barrier(Barrier { epoch: 1, timestamp: 0 }))])
+            .chain(futures::stream::pending());
+        LimitExecutor { limit, offset, child: child.boxed() }.execute().try_collect().await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_limit() {
+        let output = limit(3, 2).await;
+        // ends once the limit is reached without waiting for more input
+        let [Message::Chunk(first), Message::Chunk(second)] = output.as_slice() else {
+            panic!("unexpected output: {:?}", output);
+        };
+        let values: Vec<_> = [first, second].iter()
+            .flat_map(|chunk| (0..chunk.cardinality()).map(|i| chunk.row(i).get(0)))
+            .collect();
+        assert_eq!(values, [2, 3, 4].map(DataValue::Int32));
+
+        assert!(limit(0, 2).await.is_empty());
+    }
+}
@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+use futures_async_stream::try_stream;
+use crate::array::{DataChunk, DataChunkBuilder, Row};
+use crate::connector::{BoxedLookup, LookupCache};
+use crate::executor::evaluator::Evaluator;
+use crate::executor::hash_join::{build_chunk, JoinType};
+use crate::executor::{BoxedExecutor, ExecuteError, PROCESSING_WINDOW_SIZE};
+use crate::planner::RecExpr;
+use crate::stream::Message;
+use crate::types::{DataType, DataValue};
+
+/// The executor of a lookup join, which joins each row of the input with the rows
+/// of a dimension table looked up by the join keys at the time the row is processed.
+///
+/// The dimension table is not kept in the state, so later changes of it don't update
+/// the rows joined before, and a retracted row is joined with the current rows of the
+/// dimension table. The lookup results are cached by the keys to reduce the lookups.
+pub struct LookupJoinExecutor {
+    pub child: BoxedExecutor,
+    inner: ExecutorInner,
+}
+
+struct ExecutorInner {
+    /// `Inner` or `LeftOuter`.
+    join_type: JoinType,
+    /// The join keys of the input, compared with the lookup key columns of the connector.
+    keys: RecExpr,
+    /// The condition other than the join keys over the joined rows, `None` if there is none.
+    condition: Option<RecExpr>,
+    /// The output types of the input and the dimension table.
+    types: [Vec<DataType>; 2],
+    lookup: BoxedLookup,
+    cache: LookupCache,
+}
+
+impl LookupJoinExecutor {
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(join_type: JoinType,
+               keys: RecExpr,
+               condition: Option<RecExpr>,
+               left_types: Vec<DataType>,
+               right_types: Vec<DataType>,
+               lookup: BoxedLookup,
+               cache: LookupCache,
+               child: BoxedExecutor) -> Self {
+        LookupJoinExecutor {
+            child,
+            inner: ExecutorInner {
+                join_type,
+                keys,
+                condition,
+                types: [left_types, right_types],
+                lookup,
+                cache,
+            },
+        }
+    }
+
+    #[try_stream(boxed, ok = Message, error = ExecuteError)]
+    pub async fn execute(self) {
+        let LookupJoinExecutor { child, inner: mut this } = self;
+        #[for_await]
+        for msg in child {
+            match msg? {
+                Message::Chunk(chunk) => {
+                    let keys_chunk = Evaluator::new(&this.keys).eval_list(&chunk)?;
+                    let keys: Vec<Row> = (0..chunk.cardinality())
+                        .map(|i| keys_chunk.row(i).to_owned())
+                        .collect();
+                    let rows = this.lookup_keys(&keys).await?;
+                    for chunk in this.join(&chunk, &keys, &rows)? {
+                        yield Message::Chunk(chunk)
+                    }
+                }
+                Message::Barrier(barrier) => yield Message::Barrier(barrier),
+                Message::Watermark(watermark) => yield Message::Watermark(watermark),
+            }
+        }
+    }
+}
+
+impl ExecutorInner {
+
+    /// Returns the rows of the dimension table of the keys, from the cache or looked up
+    /// at once for the keys not cached. A null never equals anything, so the keys with
+    /// nulls match no rows.
+    async fn lookup_keys(&mut self, keys: &[Row]) -> Result<HashMap<Row, Vec<Row>>, ExecuteError> {
+        let mut rows = HashMap::new();
+        let mut missed = vec![];
+        for key in keys {
+            if rows.contains_key(key) || key.iter().any(DataValue::is_null) {
+                continue;
+            }
+            match self.cache.get(key) {
+                Some(cached) => {
+                    rows.insert(key.clone(), cached.to_vec());
+                }
+                None => {
+                    rows.insert(key.clone(), vec![]);
+                    missed.push(key.clone());
+                }
+            }
+        }
+        if missed.is_empty() {
+            return Ok(rows);
+        }
+        let looked_up = self.lookup.lookup(&missed).await?;
+        for (key, key_rows) in missed.into_iter().zip(looked_up) {
+            self.cache.put(key.clone(), key_rows.clone());
+            rows.insert(key, key_rows);
+        }
+        Ok(rows)
+    }
+
+    /// Joins the rows of the input with their rows of the dimension table, padding
+    /// the rows without any match with nulls if it's a left outer join.
+    fn join(&self, chunk: &DataChunk, keys: &[Row], rows: &HashMap<Row, Vec<Row>>)
+        -> Result<Vec<DataChunk>, ExecuteError> {
+        let mut output = vec![];
+        let mut builder = DataChunkBuilder::new(self.types.iter().flatten(), PROCESSING_WINDOW_SIZE);
+        for (i, key) in keys.iter().enumerate() {
+            let op = chunk.op(i);
+            let row = chunk.row(i).to_owned();
+            let joined = self.matches(&row, rows.get(key).map_or(&[], Vec::as_slice))?;
+            let padded = (joined.is_empty() && self.join_type == JoinType::LeftOuter)
+                .then(|| row.iter().cloned()
+                    .chain(std::iter::repeat(DataValue::Null).take(self.types[1].len()))
+                    .collect());
+            for joined in joined.into_iter().chain(padded) {
+                if let Some(chunk) = builder.push_row_with_op(op, joined.into_iter()) {
+                    output.push(chunk);
+                }
+            }
+        }
+        if let Some(chunk) = builder.take() {
+            output.push(chunk);
+        }
+        Ok(output)
+    }
+
+    /// Returns the joined rows of a row and its rows of the dimension table satisfying the condition.
+    fn matches(&self, row: &Row, others: &[Row]) -> Result<Vec<Row>, ExecuteError> {
+        let joined: Vec<Row> = others.iter()
+            .map(|other| row.iter().chain(other).cloned().collect())
+            .collect();
+        let Some(condition) = &self.condition else {
+            return Ok(joined);
+        };
+        if joined.is_empty() {
+            return Ok(joined);
+        }
+        let chunk = build_chunk(self.types.iter().flatten(), joined.iter().cloned());
+        let visible = Evaluator::new(condition).eval_condition(&chunk)?;
+        Ok(joined.into_iter()
+            .enumerate()
+            .filter(|(i, _)| visible[*i])
+            .map(|(_, row)| row)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use futures::{StreamExt, TryStreamExt};
+    use futures::future::{self, BoxFuture, FutureExt};
+    use crate::array::{ArrayImpl, I32Array};
+    use crate::connector::{LookupCacheOptions, LookupConnector};
+    use crate::planner::Expr;
+    use crate::types::{ColumnIndex, DataTypeKind};
+    use super::*;
+
+    /// A dimension table of the row `(1, 10)` keyed by the first column,
+    /// which records the keys of each lookup.
+    struct MockLookup {
+        lookups: Arc<Mutex<Vec<Vec<Row>>>>,
+    }
+
+    impl LookupConnector for MockLookup {
+        fn lookup<'a>(&'a self, keys: &'a [Row]) -> BoxFuture<'a, Result<Vec<Vec<Row>>, ExecuteError>> {
+            self.lookups.lock().unwrap().push(keys.to_vec());
+            let rows = keys.iter()
+                .map(|key| match key[0] {
+                    DataValue::Int32(1) => vec![vec![DataValue::Int32(1), DataValue::Int32(10)]],
+                    _ => vec![],
+                })
+                .collect();
+            future::ready(Ok(rows)).boxed()
+        }
+    }
+
+    /// Joins the chunks of keys, each of which is received after its delay, with the
+    /// dimension table cached for the ttl, returning the output rows and the lookups.
+    async fn lookup_join(join_type: JoinType, ttl: Duration, input: Vec<(Duration, &'static [i32])>)
+        -> (Vec<Row>, Vec<Vec<Row>>) {
+        let int = DataType::new_nullable(DataTypeKind::Int32);
+        // (list #0)
+        let mut keys = RecExpr::default();
+        let column = keys.add(Expr::ColumnIndex(ColumnIndex(0)));
+        keys.add(Expr::List([column].into()));
+        let lookups = Arc::new(Mutex::new(vec![]));
+        let lookup = Box::new(MockLookup { lookups: lookups.clone() });
+        let cache = LookupCache::new(LookupCacheOptions { max_rows: 10, ttl: Some(ttl) });
+        let child = futures::stream::iter(input)
+            .then(|(delay, keys)| async move {
+                tokio::time::sleep(delay).await;
+                let chunk = DataChunk::from_array(ArrayImpl::Int32(I32Array::from_iter(keys.iter().copied())));
+                Ok(Message::Chunk(chunk))
+            });
+        let output: Vec<Message> = LookupJoinExecutor::new(join_type, keys, None, vec![int.clone()],
+                                                           vec![int.clone(), int], lookup, cache, child.boxed())
+            .execute().try_collect().await.unwrap();
+        let rows = output.iter()
+            .flat_map(|msg| match msg {
+                Message::Chunk(chunk) => (0..chunk.cardinality()).map(|i| chunk.row(i).to_owned()).collect(),
+                _ => vec![],
+            })
+            .collect();
+        let lookups = lookups.lock().unwrap().clone();
+        (rows, lookups)
+    }
+
+    #[tokio::test]
+    async fn test_lookup_join() {
+        let key = |v| vec![DataValue::Int32(v)];
+        let joined = vec![DataValue::Int32(1), DataValue::Int32(1), DataValue::Int32(10)];
+
+        // the key 2 matches no row, and the key 1 is looked up once while it's cached
+        let input = vec![(Duration::ZERO, &[1, 2][..]), (Duration::ZERO, &[1][..])];
+        let (rows, lookups) = lookup_join(JoinType::Inner, Duration::from_secs(60), input).await;
+        assert_eq!(rows, vec![joined.clone(), joined.clone()]);
+        assert_eq!(lookups, vec![vec![key(1), key(2)]]);
+
+        // the key is looked up again once its result expires
+        let input = vec![(Duration::ZERO, &[1][..]), (Duration::from_millis(100), &[1][..])];
+        let (rows, lookups) = lookup_join(JoinType::Inner, Duration::from_millis(50), input).await;
+        assert_eq!(rows, vec![joined.clone(), joined.clone()]);
+        assert_eq!(lookups, vec![vec![key(1)], vec![key(1)]]);
+
+        // the rows without match are padded with nulls in a left outer join
+        let input = vec![(Duration::ZERO, &[1, 2][..])];
+        let (rows, _) = lookup_join(JoinType::LeftOuter, Duration::from_secs(60), input).await;
+        assert_eq!(rows, vec![joined, vec![DataValue::Int32(2), DataValue::Null, DataValue::Null]]);
+    }
+}
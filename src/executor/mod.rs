@@ -1,31 +1,55 @@
 mod create;
 pub(crate) mod evaluator;
 mod projection;
+mod filter;
+mod limit;
+mod window;
+mod window_agg;
 mod table_scan;
 mod executor;
 mod insert;
 mod hash_agg;
+mod hash_join;
+mod interval_join;
+mod lookup_join;
+mod top_n;
+mod sort;
+mod distinct;
+mod row_store;
+mod align;
 mod drop;
 
-use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use egg::{Id, Language};
-use futures::stream::BoxStream;
+use futures::stream::{BoxStream, StreamExt};
 use tokio::sync::mpsc;
 use crate::catalog::{CatalogRef, ColumnId, TableCatalog, TableRefId};
-use crate::checkpoint::BarrierManager;
+use crate::checkpoint::{BarrierManager, Manifest};
 use crate::executor::executor::WrapExecutor;
 use crate::executor::create::CreateTableExecutor;
 use crate::stream::{Message, Job};
-use crate::types::{ColumnIndex, ConvertError, DataType};
+use crate::types::{ColumnIndex, ConvertError, DataType, DataTypeKind, DataValue};
 use crate::connector::data_gen::DataGenSource;
-use crate::connector::{BlackHole, FileSystemConnector, Print, StreamConnector, ValueConnector};
+use crate::connector::{BoxedSource, ConnectorContext, ConnectorFactory, ConnectorRegistry, ConnectorRegistryRef,
+                       LookupCache, LookupCacheOptions, ValueConnector};
 use crate::executor::drop::DropExecutor;
 use crate::executor::insert::TableInsertExecutor;
 use crate::executor::hash_agg::HashAggExecutor;
+use crate::executor::hash_join::{HashJoinExecutor, JoinType};
+use crate::executor::interval_join::{IntervalBounds, IntervalJoinExecutor};
+use crate::executor::lookup_join::LookupJoinExecutor;
+use crate::executor::top_n::TopNExecutor;
+use crate::executor::sort::SortExecutor;
+use crate::executor::distinct::DistinctExecutor;
 use crate::executor::projection::ProjectionExecutor;
-use crate::executor::table_scan::TableScanExecutor;
+use crate::executor::filter::FilterExecutor;
+use crate::executor::limit::LimitExecutor;
+use crate::executor::window::WindowExecutor;
+use crate::executor::window_agg::WindowAggExecutor;
+use crate::executor::table_scan::{TableScanExecutor, WatermarkGenerator};
 use crate::planner::{RecExpr, TypeSchemaAnalysis};
+use crate::state::{StateBackend, StateError, StateRef};
 
 
 
@@ -39,11 +63,30 @@ pub const PROCESSING_WINDOW_SIZE: usize = 1024;
 pub enum ExecuteError {
     #[error("conversion error: {0}")]
     Convert(#[from] ConvertError),
+    #[error("state error: {0}")]
+    State(#[from] StateError),
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("malformed row {0:?}: {1}")]
+    MalformedRow(String, String),
+    #[error("sink error: {0}")]
+    Sink(String),
+    #[error("condition {0} must be boolean, not {1}")]
+    NotBoolCondition(String, DataTypeKind),
+    #[error("not supported: {0}")]
+    NotSupported(String),
+    #[error("invalid options: {0}")]
+    InvalidOptions(String),
 }
 
 pub type BoxedExecutor = BoxStream<'static, Result<Message, ExecuteError>>;
 pub type BarrierManagerRef = Arc<Mutex<BarrierManager>>;
 
+/// Returns an executor failing with the error, for one failing to be built.
+fn failed_executor(e: ExecuteError) -> BoxedExecutor {
+    futures::stream::once(async { Err(e) }).boxed()
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum ExecutorError {
 
@@ -56,6 +99,13 @@ pub struct ExecutorBuilder {
     current_executor_id: u32,
     egraph: egg::EGraph<Expr, TypeSchemaAnalysis>,
     root: Id,
+    state_backend: StateBackend,
+    /// How long the state of an unused key is kept, forever if `None`.
+    state_ttl: Option<Duration>,
+    job_id: String,
+    /// The checkpoint the job is restored from.
+    restore_from: Option<Manifest>,
+    connectors: ConnectorRegistryRef,
 }
 
 impl ExecutorBuilder {
@@ -71,11 +121,50 @@ impl ExecutorBuilder {
             catalog: catalog.clone(),
             current_executor_id: 0,
             egraph,
-            root
+            root,
+            state_backend: StateBackend::default(),
+            state_ttl: None,
+            job_id: String::new(),
+            restore_from: None,
+            connectors: Arc::new(ConnectorRegistry::new()),
         }
     }
 
+    pub fn with_state_backend(mut self, state_backend: StateBackend) -> Self {
+        self.state_backend = state_backend;
+        self
+    }
+
+    /// Expires the state of the keys not updated within the ttl, in the executors supporting it.
+    pub fn with_state_ttl(mut self, state_ttl: Option<Duration>) -> Self {
+        self.state_ttl = state_ttl;
+        self
+    }
+
+    /// Creates the connectors of tables with the factories of the registry.
+    pub fn with_connectors(mut self, connectors: ConnectorRegistryRef) -> Self {
+        self.connectors = connectors;
+        self
+    }
+
+    /// Restores the states and source positions of the job from a checkpoint.
+    pub fn with_restore_from(mut self, manifest: Manifest) -> Self {
+        self.restore_from = Some(manifest);
+        self
+    }
+
+    /// Opens the state of an executor in the job being built,
+    /// registering it to be checkpointed.
+    pub fn open_state(&self, executor_id: u32, barrier_manager: &BarrierManagerRef) -> Result<StateRef, StateError> {
+        let handle = self.restore_from.as_ref()
+            .and_then(|manifest| manifest.report.state_handles.get(&executor_id));
+        let state = self.state_backend.open(&self.job_id, executor_id, handle)?;
+        barrier_manager.lock().unwrap().register_state(executor_id, state.clone());
+        Ok(state)
+    }
+
     pub fn build_job(&mut self, job_id: String) -> Job {
+        self.job_id.clone_from(&job_id);
         let is_ddl_job = self.is_ddl(self.node(self.root));
         let barrier_manager = Arc::new(Mutex::new(BarrierManager::new()));
         let executor = self.build(self.root,
@@ -85,7 +174,10 @@ impl ExecutorBuilder {
             current_job_id: job_id,
             barrier_manager: barrier_manager.clone(),
             result_executor: executor,
-            is_ddl_job
+            is_ddl_job,
+            start_epoch: self.restore_from.as_ref().map_or(0, |manifest| manifest.epoch),
+            checkpoint_store: None,
+            sql: String::new(),
         }
     }
 
@@ -120,11 +212,23 @@ impl ExecutorBuilder {
                     };
                     self.build_table_scan_executor(executor_id,
                                                    barrier_manager.clone(),
-                                                   Box::new(connector))
+                                                   Box::new(connector),
+                                                   None)
                 },
             Limit([limit, offset, child]) => {
-                // todo: implement limit/offset
-                self.build(child, executor_id +1, barrier_manager.clone())
+                let child_executor = self.build(child, executor_id +1, barrier_manager.clone());
+                let limit = self.node(limit).as_const().as_usize().unwrap();
+                let offset = self.node(offset).as_const().as_usize().unwrap().unwrap_or(0);
+                if limit.is_none() && offset == 0 {
+                    return child_executor;
+                }
+                self.build_executor(|| {
+                    LimitExecutor {
+                        limit: limit.unwrap_or(usize::MAX),
+                        offset,
+                        child: child_executor,
+                    }.execute()
+                }, executor_id, barrier_manager.clone())
             }
 
             Insert([table, cols, child]) => {
@@ -136,13 +240,16 @@ impl ExecutorBuilder {
                         self.catalog.get_table(self.node(table)
                         .as_table());
                     let column_ids =  self.column_ids(cols);
-                    let connector = self.get_connector(
-                        table.clone().unwrap(),
-                        column_ids,
-                        table.clone().unwrap().get_options(), false);
+                    let context = self.connector_context(table.unwrap(), column_ids);
+                    let sink = match self.connector_factory(&context.table)
+                        .and_then(|factory| factory.create_sink(context)) {
+                        Ok(sink) => sink,
+                        Err(e) => return failed_executor(e),
+                    };
                     TableInsertExecutor {
-                        sink_connector: connector,
+                        sink_connector: sink,
                         child,
+                        barrier_manager: barrier_manager.clone(),
                     }.execute()
                 }, executor_id,barrier_manager.clone())
             },
@@ -151,15 +258,21 @@ impl ExecutorBuilder {
                 self.build_executor(|| {
                     let table = self.catalog.get_table(self.node(table).as_table());
                     let column_ids =  self.column_ids(cols);
-                    let connector =
-                        self.get_connector(
-                        table.clone().unwrap(),
-                        column_ids,
-                        table.clone().unwrap().get_options(),
-                        true);
+                    let watermark_generator = table.as_ref().unwrap().watermark()
+                        .and_then(|watermark| {
+                            let col_idx = column_ids.iter().position(|id| *id == watermark.column_id)?;
+                            Some(WatermarkGenerator::new(col_idx, watermark.delay))
+                        });
+                    let context = self.connector_context(table.unwrap(), column_ids);
+                    let source = match self.connector_factory(&context.table)
+                        .and_then(|factory| factory.create_source(context)) {
+                        Ok(source) => source,
+                        Err(e) => return failed_executor(e),
+                    };
                     self.build_table_scan_executor(executor_id,
                                                    barrier_manager.clone(),
-                                                   connector)
+                                                   source,
+                                                   watermark_generator)
                 }, executor_id, barrier_manager.clone())
             },
 
@@ -175,24 +288,195 @@ impl ExecutorBuilder {
             },
 
             Filter([cond, child]) => {
-                self.build(child, executor_id + 1, barrier_manager.clone())
+                let child_executor = self.build(child,
+                                                executor_id + 1, barrier_manager.clone());
+                if self.node(cond) == &Expr::true_() {
+                    return child_executor;
+                }
+                self.build_executor(|| {
+                    FilterExecutor {
+                        condition: self.resolve_column_index(cond, child),
+                        child: child_executor,
+                    }.execute()
+                }, executor_id, barrier_manager.clone())
             }
 
+            Window([windows, child]) => {
+                let child_executor = self.build(child,
+                                                executor_id +1, barrier_manager.clone());
+                let WindowStart([time_col, slide, size]) = self.node(self.node(windows).as_list()[0]).clone() else {
+                    panic!("not a window start: {}", self.node(windows));
+                };
+                self.build_executor(|| {
+                    WindowExecutor {
+                        time_col: self.resolve_column_index(time_col, child),
+                        slide: self.interval(slide),
+                        size: self.interval(size),
+                        types: self.schema_types(id),
+                        child: child_executor,
+                    }.execute()
+                }, executor_id, barrier_manager.clone())
+            },
+
             Agg([aggs, group_keys, child]) => {
+                // grouping by window_end makes it an aggregation over windows
+                let window_end = self.node(group_keys).as_list().iter()
+                    .find(|id| matches!(self.node(**id), WindowEnd(_)))
+                    .copied();
                 let aggs = self.resolve_column_index(aggs, child);
                 let group_keys = self.resolve_column_index(group_keys, child);
                 let child_executor = self.build(child,
                                                 executor_id +1, barrier_manager.clone());
-                self.build_executor(|| {
+                if let Some(window_end) = window_end {
+                    return self.build_stateful_executor(|state| {
+                        WindowAggExecutor::new(
+                            aggs,
+                            group_keys,
+                            self.resolve_column_index(window_end, child),
+                            self.plan_types(id).to_vec(),
+                            state,
+                            child_executor,
+                        ).execute()
+                    }, executor_id, barrier_manager.clone());
+                }
+                self.build_stateful_executor(|state| {
                     HashAggExecutor::new(
                         aggs,
                         group_keys,
                         self.plan_types(id).to_vec(),
+                        state,
                         child_executor,
                     ).execute()
                 }, executor_id, barrier_manager.clone())
 
             },
+
+            Join([join_type, cond, left, right]) => {
+                let join_type = match self.node(join_type) {
+                    Inner => JoinType::Inner,
+                    LeftOuter => JoinType::LeftOuter,
+                    RightOuter => JoinType::RightOuter,
+                    FullOuter => JoinType::FullOuter,
+                    node => panic!("not a join type: {}", node),
+                };
+                let (left_keys, right_keys, cond) = self.split_join_condition(cond, left, right);
+                let interval = self.interval_join_bounds(cond, left, right);
+                let left_keys = self.resolve_column_index(left_keys, left);
+                let right_keys = self.resolve_column_index(right_keys, right);
+                let condition = (self.node(cond) != &Expr::true_())
+                    .then(|| self.resolve_column_index(cond, id));
+                // the executors of the right input follow the ones of the left input
+                let left_executor = self.build(left, executor_id + 1, barrier_manager.clone());
+                let right_executor = self.build(right,
+                                                executor_id + 1 + self.plan_size(left), barrier_manager.clone());
+                self.build_stateful_executor(|state| match interval {
+                    // bounding the event times of both inputs makes it an interval join
+                    Some((time_cols, bounds)) => IntervalJoinExecutor::new(
+                        join_type,
+                        left_keys,
+                        right_keys,
+                        condition,
+                        self.schema_types(left),
+                        self.schema_types(right),
+                        time_cols,
+                        bounds,
+                        state,
+                        left_executor,
+                        right_executor,
+                    ).execute(),
+                    None => HashJoinExecutor::new(
+                        join_type,
+                        left_keys,
+                        right_keys,
+                        condition,
+                        self.schema_types(left),
+                        self.schema_types(right),
+                        state,
+                        left_executor,
+                        right_executor,
+                    ).execute(),
+                }, executor_id, barrier_manager.clone())
+            },
+
+            TopN([limit, offset, order_keys, partition_keys, child]) => {
+                let limit = self.node(limit).as_const().as_usize().unwrap().unwrap();
+                let offset = self.node(offset).as_const().as_usize().unwrap().unwrap_or(0);
+                let order_keys = self.resolve_order_keys(order_keys, child);
+                let partition_keys = self.resolve_column_index(partition_keys, child);
+                let append_only = self.is_append_only(child);
+                let child_executor = self.build(child, executor_id + 1, barrier_manager.clone());
+                self.build_stateful_executor(|state| {
+                    TopNExecutor::new(
+                        limit,
+                        offset,
+                        order_keys,
+                        partition_keys,
+                        self.schema_types(child),
+                        append_only,
+                        state,
+                        child_executor,
+                    ).execute()
+                }, executor_id, barrier_manager.clone())
+            },
+
+            Distinct(child) => {
+                let child_executor = self.build(child, executor_id + 1, barrier_manager.clone());
+                self.build_stateful_executor(|state| {
+                    DistinctExecutor::new(self.schema_types(child), self.state_ttl, state, child_executor).execute()
+                }, executor_id, barrier_manager.clone())
+            },
+
+            Order([order_keys, child]) => {
+                let order_keys = self.resolve_order_keys(order_keys, child);
+                let child_executor = self.build(child, executor_id + 1, barrier_manager.clone());
+                self.build_stateful_executor(|state| {
+                    SortExecutor::new(order_keys, self.schema_types(child), state, child_executor).execute()
+                }, executor_id, barrier_manager.clone())
+            },
+
+            LookupJoin([join_type, cond, left, right]) => {
+                let join_type = match self.node(join_type) {
+                    Inner => JoinType::Inner,
+                    LeftOuter => JoinType::LeftOuter,
+                    node => panic!("not a lookup join type: {}", node),
+                };
+                let Scan([table, cols, _]) = *self.node(right) else {
+                    panic!("not a dimension table: {}", self.node(right));
+                };
+                let (left_keys, right_keys, cond) = self.split_lookup_condition(cond, left, right);
+                let right_schema = &self.egraph[right].data.schema;
+                let key_indexes = right_keys.iter()
+                    .map(|key| right_schema.iter().position(|id| id == key).unwrap())
+                    .collect();
+                let left_keys = self.egraph.add(Expr::List(left_keys.into()));
+                let keys = self.resolve_column_index(left_keys, left);
+                let condition = (self.node(cond) != &Expr::true_())
+                    .then(|| self.resolve_column_index(cond, id));
+                let child = self.build(left, executor_id + 1, barrier_manager.clone());
+                self.build_executor(|| {
+                    let table = self.catalog.get_table(self.node(table).as_table()).unwrap();
+                    let context = self.connector_context(table, self.column_ids(cols));
+                    let cache = match LookupCacheOptions::from_options(&context.options) {
+                        Ok(cache) => cache,
+                        Err(e) => return failed_executor(ExecuteError::InvalidOptions(e)),
+                    };
+                    let lookup = match self.connector_factory(&context.table)
+                        .and_then(|factory| factory.create_lookup(context, key_indexes)) {
+                        Ok(lookup) => lookup,
+                        Err(e) => return failed_executor(e),
+                    };
+                    LookupJoinExecutor::new(
+                        join_type,
+                        keys,
+                        condition,
+                        self.schema_types(left),
+                        self.schema_types(right),
+                        lookup,
+                        LookupCache::new(cache),
+                        child,
+                    ).execute()
+                }, executor_id, barrier_manager.clone())
+            },
             _ => {
                 println!("{:?}", self.node(id).clone().to_string());
                 todo!()
@@ -202,17 +486,199 @@ impl ExecutorBuilder {
 
     fn build_table_scan_executor(&self, executor_id: u32,
                                  barrier_manager: BarrierManagerRef,
-                                 connector: Box<dyn StreamConnector + Sync + Send>) -> BoxedExecutor {
+                                 mut connector: BoxedSource,
+                                 watermark_generator: Option<WatermarkGenerator>) -> BoxedExecutor {
         let (sender, rx) = mpsc::unbounded_channel();
         barrier_manager.lock().unwrap().register_sender(executor_id, sender);
+        if let Some(position) = self.restore_from.as_ref()
+            .and_then(|manifest| manifest.report.source_positions.get(&executor_id)) {
+            connector.seek(position);
+        }
         self.build_executor(|| {
             TableScanExecutor {
                 data_source: connector,
                 rx: Some(rx),
+                watermark_generator,
+                actor_id: executor_id,
+                barrier_manager: barrier_manager.clone(),
             }.execute()
         }, executor_id,barrier_manager.clone())
     }
 
+    /// Returns the number of executor ids taken by a plan, so that the executors of
+    /// the two inputs of a join have different ids.
+    fn plan_size(&self, id: Id) -> u32 {
+        use Expr::*;
+        match self.node(id) {
+            Join([_, _, left, right]) => 1 + self.plan_size(*left) + self.plan_size(*right),
+            // the dimension table of a lookup join is not read by an executor
+            LookupJoin([_, _, left, _]) => 1 + self.plan_size(*left),
+            Proj([_, child]) | Filter([_, child]) | Order([_, child]) | Window([_, child])
+            | Limit([_, _, child]) | Agg([_, _, child]) | Insert([_, _, child])
+            | TopN([_, _, _, _, child]) | Distinct(child) => 1 + self.plan_size(*child),
+            _ => 1,
+        }
+    }
+
+    /// Splits the condition of a join into the equal join keys of the left and right inputs,
+    /// and the rest of the condition.
+    ///
+    /// # Example
+    /// - `(and (= $1.1 $2.1) (> $1.2 $2.2))` => `(list $1.1)`, `(list $2.1)`, `(> $1.2 $2.2)`
+    fn split_join_condition(&mut self, cond: Id, left: Id, right: Id) -> (Id, Id, Id) {
+        let mut conjuncts = vec![cond];
+        let (mut left_keys, mut right_keys, mut rest) = (vec![], vec![], vec![]);
+        while let Some(id) = conjuncts.pop() {
+            match *self.node(id) {
+                Expr::And([a, b]) => conjuncts.extend([b, a]),
+                Expr::Eq([a, b]) if self.is_resolvable(a, left) && self.is_resolvable(b, right) => {
+                    left_keys.push(a);
+                    right_keys.push(b);
+                }
+                Expr::Eq([a, b]) if self.is_resolvable(a, right) && self.is_resolvable(b, left) => {
+                    left_keys.push(b);
+                    right_keys.push(a);
+                }
+                _ => rest.push(id),
+            }
+        }
+        let left_keys = self.egraph.add(Expr::List(left_keys.into()));
+        let right_keys = self.egraph.add(Expr::List(right_keys.into()));
+        let rest = rest.into_iter()
+            .reduce(|a, b| self.egraph.add(Expr::And([a, b])))
+            .unwrap_or_else(|| self.egraph.add(Expr::true_()));
+        (left_keys, right_keys, rest)
+    }
+
+    /// Returns the expressions of the order keys over the output of a plan,
+    /// each of which is followed by whether it's descending.
+    fn resolve_order_keys(&self, order_keys: Id, plan: Id) -> Vec<(RecExpr, bool)> {
+        self.node(order_keys).as_list().iter()
+            .map(|key| match *self.node(*key) {
+                Expr::Asc(expr) => (self.resolve_column_index(expr, plan), false),
+                Expr::Desc(expr) => (self.resolve_column_index(expr, plan), true),
+                ref node => panic!("not an order key: {}", node),
+            })
+            .collect()
+    }
+
+    /// Returns true if the output of a plan never retracts rows.
+    fn is_append_only(&self, id: Id) -> bool {
+        crate::planner::is_append_only(&self.egraph, id)
+    }
+
+    /// Splits the condition of a lookup join into the join keys of the input, the columns
+    /// of the dimension table they equal, and the rest of the condition.
+    ///
+    /// # Example
+    /// - `(and (= (+ $1.1 1) $2.1) (> $1.2 $2.2))` => `[(+ $1.1 1)]`, `[$2.1]`, `(> $1.2 $2.2)`
+    fn split_lookup_condition(&mut self, cond: Id, left: Id, right: Id) -> (Vec<Id>, Vec<Id>, Id) {
+        let (left_keys, right_keys, rest) = self.split_join_condition(cond, left, right);
+        let keys: Vec<(Id, Id)> = self.node(left_keys).as_list().iter().copied()
+            .zip(self.node(right_keys).as_list().iter().copied())
+            .collect();
+        let mut rest: Vec<Id> = (self.node(rest) != &Expr::true_()).then_some(rest).into_iter().collect();
+        let (mut lookup_left_keys, mut lookup_right_keys) = (vec![], vec![]);
+        // only the columns of the dimension table can be looked up
+        for (left_key, right_key) in keys {
+            if self.egraph[right].data.schema.contains(&right_key) {
+                lookup_left_keys.push(left_key);
+                lookup_right_keys.push(right_key);
+            } else {
+                rest.push(self.egraph.add(Expr::Eq([left_key, right_key])));
+            }
+        }
+        let rest = rest.into_iter()
+            .reduce(|a, b| self.egraph.add(Expr::And([a, b])))
+            .unwrap_or_else(|| self.egraph.add(Expr::true_()));
+        (lookup_left_keys, lookup_right_keys, rest)
+    }
+
+    /// Returns the indexes of the event time columns of the inputs of a join, and the bounds
+    /// of their difference, if the condition bounds it from both sides.
+    /// An event time column is a column with a watermark.
+    ///
+    /// # Example
+    /// - `(and (>= $1.2 (- $2.2 10min)) (<= $1.2 $2.2))` => `[1, 1]`, `-10min..=0`
+    fn interval_join_bounds(&self, cond: Id, left: Id, right: Id) -> Option<([usize; 2], IntervalBounds)> {
+        let mut conjuncts = vec![cond];
+        let mut time_cols: Option<[Id; 2]> = None;
+        let (mut lower, mut upper) = (None, None);
+        while let Some(id) = conjuncts.pop() {
+            let (a, b, greater) = match *self.node(id) {
+                Expr::And([a, b]) => {
+                    conjuncts.extend([b, a]);
+                    continue;
+                }
+                Expr::Gt([a, b]) | Expr::GtEq([a, b]) => (a, b, true),
+                Expr::Lt([a, b]) | Expr::LtEq([a, b]) => (a, b, false),
+                _ => continue,
+            };
+            let (Some((a, a_offset)), Some((b, b_offset))) = (self.time_offset(a), self.time_offset(b)) else {
+                continue;
+            };
+            // normalize to `left - right > diff` or `left - right < diff`
+            let (cols, diff, greater) = if self.is_time_column(a, left) && self.is_time_column(b, right) {
+                ([a, b], b_offset - a_offset, greater)
+            } else if self.is_time_column(a, right) && self.is_time_column(b, left) {
+                ([b, a], a_offset - b_offset, !greater)
+            } else {
+                continue;
+            };
+            if *time_cols.get_or_insert(cols) != cols {
+                continue;
+            }
+            if greater {
+                lower = lower.max(Some(diff));
+            } else {
+                upper = Some(upper.map_or(diff, |upper: i64| upper.min(diff)));
+            }
+        }
+        let [left_col, right_col] = time_cols?;
+        let bounds = IntervalBounds { lower: lower?, upper: upper? };
+        let position = |col, plan| self.egraph[plan].data.schema.iter().position(|id| *id == col);
+        Some(([position(left_col, left)?, position(right_col, right)?], bounds))
+    }
+
+    /// Splits an expression like `col`, `col + interval` or `col - interval` into the column
+    /// and the offset in milliseconds.
+    fn time_offset(&self, expr: Id) -> Option<(Id, i64)> {
+        let interval = |id| match self.node(id) {
+            Expr::Constant(DataValue::Interval(v)) => Some(*v),
+            _ => None,
+        };
+        match *self.node(expr) {
+            Expr::Column(_) => Some((expr, 0)),
+            Expr::Add([col, offset]) if matches!(self.node(col), Expr::Column(_)) =>
+                Some((col, interval(offset)?)),
+            Expr::Sub([col, offset]) if matches!(self.node(col), Expr::Column(_)) =>
+                Some((col, interval(offset)?.checked_neg()?)),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the column is an output column of a plan with a watermark.
+    fn is_time_column(&self, col: Id, plan: Id) -> bool {
+        if !self.egraph[plan].data.schema.contains(&col) {
+            return false;
+        }
+        let column = self.node(col).as_column();
+        self.catalog.get_table(TableRefId::new(column.schema_id, column.table_id))
+            .and_then(|table| table.watermark())
+            .is_some_and(|watermark| watermark.column_id == column.column_id)
+    }
+
+    /// Returns true if the expression only refers to the output columns of a plan.
+    fn is_resolvable(&self, expr: Id, plan: Id) -> bool {
+        if self.egraph[plan].data.schema.contains(&expr) {
+            return true;
+        }
+        match self.node(expr) {
+            Expr::Column(_) => false,
+            node => node.children().iter().all(|child| self.is_resolvable(*child, plan)),
+        }
+    }
+
     fn column_ids(&self, col_id: Id) -> Vec<ColumnId> {
         self.node(col_id)
             .as_list().iter().map(|id| self.node(*id).as_column().column_id)
@@ -226,6 +692,17 @@ impl ExecutorBuilder {
         self.wrap_executor(inner,executor_id, barrier_manager.clone())
     }
 
+    /// Builds an executor over its state, see [`build_executor`](Self::build_executor),
+    /// returning an executor failing with the error if the state fails to open.
+    fn build_stateful_executor<F>(&self, inner_executor_f: F, executor_id: u32,
+                                  barrier_manager: Arc<Mutex<BarrierManager>>) -> BoxedExecutor
+        where F: FnOnce(StateRef) -> BoxedExecutor {
+        match self.open_state(executor_id, &barrier_manager) {
+            Ok(state) => self.build_executor(|| inner_executor_f(state), executor_id, barrier_manager),
+            Err(e) => failed_executor(e.into()),
+        }
+    }
+
     pub fn is_ddl(&self, expr: &Expr) -> bool {
         match expr {
             Expr::CreateTable(_) => true,
@@ -239,6 +716,20 @@ impl ExecutorBuilder {
         ty.kind.as_struct()
     }
 
+    /// Returns the data types of the output columns of a plan node.
+    fn schema_types(&self, id: Id) -> Vec<DataType> {
+        self.egraph[id].data.schema.iter()
+            .map(|id| self.egraph[*id].data.type_.clone().unwrap())
+            .collect()
+    }
+
+    fn interval(&self, id: Id) -> i64 {
+        let DataValue::Interval(v) = self.node(id).as_const() else {
+            panic!("not an interval: {}", self.node(id));
+        };
+        *v
+    }
+
     fn node(&self, id: Id) -> &Expr {
         &self.egraph[id].nodes[0]
     }
@@ -249,6 +740,9 @@ impl ExecutorBuilder {
 
     fn resolve_column_index(&self, expr: Id, plan: Id) -> RecExpr {
         let schema = &self.egraph[plan].data.schema;
+        if let Some(idx) = schema.iter().position(|x| *x == expr) {
+            return [Expr::ColumnIndex(ColumnIndex(idx as _))].to_vec().into();
+        }
         self.node(expr).build_recexpr(|id| {
             if let Some(idx) = schema.iter().position(|x| *x == id) {
                 return Expr::ColumnIndex(ColumnIndex(idx as _))
@@ -260,41 +754,19 @@ impl ExecutorBuilder {
         })
     }
 
-    fn get_connector(&self, table: Arc<TableCatalog>, column_ids: Vec<ColumnId>,
-                     options: HashMap<String, String>, is_source: bool) -> Box<dyn StreamConnector + Send + Sync> {
-        let value = table.clone().get_option("connector").unwrap();
-        match value.to_lowercase().as_str() {
-            "datagen" => {
-                Box::new(DataGenSource {
-                    column_ids,
-                    table,
-                })
-            },
-            "print" => {
-                Box::new(Print {
-                    column_ids,
-                    table
-                })
-            },
-            "blackhole" => {
-              Box::new(BlackHole {
-                  column_ids,
-                  table,
-              })
-            },
-            "filesystem" => {
-                match is_source {
-                    true => {
-                        Box::new(FileSystemConnector::new_source(column_ids, table, options))
-                    }
-                    false => {
-                        Box::new(FileSystemConnector::new_sink(column_ids, table, options))
-                    }
-                }
-            },
-            _ => {
-                unimplemented!("connector {} is not supported", value);
-            }
+    fn connector_factory(&self, table: &TableCatalog) -> Result<Arc<dyn ConnectorFactory>, ExecuteError> {
+        let name = table.get_option("connector").unwrap();
+        self.connectors.get(&name)
+            .ok_or_else(|| ExecuteError::InvalidOptions(format!("connector {} is not registered", name)))
+    }
+
+    fn connector_context(&self, table: Arc<TableCatalog>, column_ids: Vec<ColumnId>) -> ConnectorContext {
+        ConnectorContext {
+            options: table.get_options(),
+            table,
+            column_ids,
+            job_id: &self.job_id,
+            restored_epoch: self.restore_from.as_ref().map(|manifest| manifest.epoch),
         }
     }
 
@@ -304,10 +776,7 @@ impl ExecutorBuilder {
     }
 
     fn build_stream_source(&self, table_ref_id: TableRefId, column_ids: Vec<ColumnId>) -> DataGenSource {
-        let table = self.catalog.get_table(table_ref_id);
-        return DataGenSource {
-            column_ids,
-            table: table.unwrap()
-        };
+        let table = self.catalog.get_table(table_ref_id).unwrap();
+        DataGenSource::new(column_ids, table.clone(), &table.get_options())
     }
 }
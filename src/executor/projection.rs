@@ -22,9 +22,13 @@ impl ProjectionExecutor {
                 Message::Barrier(barrier) => {
                     yield Message::Barrier(barrier)
                 },
+                Message::Watermark(watermark) => {
+                    yield Message::Watermark(watermark)
+                },
                 Message::Chunk(_chunk) => {
                     let chunk = Evaluator::new(&self.exprs)
-                        .eval_list(&_chunk)?;
+                        .eval_list(&_chunk)?
+                        .with_ops(_chunk.ops());
                     yield Message::Chunk(chunk)
                 }
             }
@@ -0,0 +1,112 @@
+use std::collections::HashSet;
+use std::hash::Hash;
+use std::ops::Bound;
+use bytes::{Buf, BufMut, BytesMut};
+use crate::array::Row;
+use crate::executor::ExecuteError;
+use crate::state::serde::{deserialize_key, serialize_key};
+use crate::state::{KeyRange, StateError, StateRef};
+use crate::types::DataType;
+
+/// The persistent rows of an executor, which is shared by the joins, Top-N, sort, distinct
+/// and window aggregation.
+///
+/// The state key is a prefix followed by the row, the state value is `N` integers kept
+/// along with the row, like its count, or the values encoded by the executor. The rows updated are tracked by keys of `K` until
+/// the next barrier, when they're written to the state.
+pub(super) struct RowStore<K> {
+    state: StateRef,
+    /// The rows updated since the last barrier.
+    dirty_rows: HashSet<K>,
+}
+
+impl<K: Eq + Hash> RowStore<K> {
+
+    pub fn new(state: StateRef) -> Self {
+        RowStore { state, dirty_rows: HashSet::new() }
+    }
+
+    /// Marks a row updated, which is written at the next barrier.
+    pub fn mark_dirty(&mut self, key: K) {
+        self.dirty_rows.insert(key);
+    }
+
+    /// Returns whether a row is updated since the last barrier.
+    #[cfg(test)]
+    pub fn is_dirty(&self, key: &K) -> bool {
+        self.dirty_rows.contains(key)
+    }
+
+    /// Loads the rows of the types whose state keys start with `prefix`, along with their values.
+    pub fn load<const N: usize>(&self, prefix: &[u8], types: &[DataType])
+        -> Result<Vec<(Row, [i64; N])>, ExecuteError> {
+        let mut rows = vec![];
+        for (row, mut value) in self.load_values(prefix, types)? {
+            if value.len() < N * 8 {
+                return Err(StateError::Corrupted("invalid row state".into()).into());
+            }
+            rows.push((row, std::array::from_fn(|_| value.get_i64_le())));
+        }
+        Ok(rows)
+    }
+
+    /// Loads the rows like [`load`](Self::load), along with their values encoded by the executor.
+    pub fn load_values(&self, prefix: &[u8], types: &[DataType]) -> Result<Vec<(Row, BytesMut)>, ExecuteError> {
+        let mut rows = vec![];
+        for (key, value) in self.state.scan(prefix_range(prefix))? {
+            let row = deserialize_key(types, &mut BytesMut::from(&key[prefix.len()..]))?;
+            rows.push((row.into_vec(), BytesMut::from(value.as_slice())));
+        }
+        Ok(rows)
+    }
+
+    /// Writes the updated rows to the state, and commits them with the epoch.
+    ///
+    /// `entry` returns the state key of an updated row, see [`state_key`], and its values,
+    /// `None` if the row is deleted.
+    pub fn flush<const N: usize>(&mut self, epoch: u64, entry: impl Fn(K) -> (Vec<u8>, Option<[i64; N]>))
+        -> Result<(), ExecuteError> {
+        self.flush_values(epoch, |key| {
+            let (key, values) = entry(key);
+            (key, values.map(|values| {
+                let mut value = vec![];
+                for v in values {
+                    value.put_i64_le(v);
+                }
+                value
+            }))
+        })
+    }
+
+    /// Writes the updated rows like [`flush`](Self::flush), whose values are encoded by the executor.
+    pub fn flush_values(&mut self, epoch: u64, entry: impl Fn(K) -> (Vec<u8>, Option<Vec<u8>>))
+        -> Result<(), ExecuteError> {
+        for key in self.dirty_rows.drain() {
+            match entry(key) {
+                (key, Some(value)) => self.state.put(key, value)?,
+                (key, None) => self.state.delete(key)?,
+            }
+        }
+        self.state.commit(epoch)?;
+        Ok(())
+    }
+}
+
+/// Returns the state key of a row, which is the prefix followed by the row.
+pub(super) fn state_key(prefix: &[u8], row: &Row) -> Vec<u8> {
+    let mut key = prefix.to_vec();
+    key.extend(serialize_key(row));
+    key
+}
+
+/// Returns the range of the keys starting with `prefix`.
+fn prefix_range(prefix: &[u8]) -> KeyRange {
+    let mut end = prefix.to_vec();
+    while let Some(last) = end.pop() {
+        if last < u8::MAX {
+            end.push(last + 1);
+            return (Bound::Included(prefix.to_vec()), Bound::Excluded(end));
+        }
+    }
+    (Bound::Included(prefix.to_vec()), Bound::Unbounded)
+}
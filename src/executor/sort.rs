@@ -0,0 +1,131 @@
+use futures_async_stream::try_stream;
+use crate::array::{DataChunkBuilder, RowKind};
+use crate::executor::top_n::ExecutorInner;
+use crate::executor::{BoxedExecutor, ExecuteError, PROCESSING_WINDOW_SIZE};
+use crate::planner::{Expr, RecExpr};
+use crate::state::StateRef;
+use crate::stream::Message;
+use crate::types::DataType;
+
+/// The executor of `ORDER BY` without `LIMIT` over a bounded input, which keeps
+/// all the rows in order and emits them once the input is finished.
+pub struct SortExecutor {
+    pub child: BoxedExecutor,
+    inner: ExecutorInner,
+}
+
+impl SortExecutor {
+
+    pub fn new(order_keys: Vec<(RecExpr, bool)>,
+               types: Vec<DataType>,
+               state: StateRef,
+               child: BoxedExecutor) -> Self {
+        SortExecutor {
+            child,
+            // all the rows are in the same partition
+            inner: ExecutorInner::new(order_keys, [Expr::List([].into())].to_vec().into(), types, state),
+        }
+    }
+
+    #[try_stream(boxed, ok = Message, error = ExecuteError)]
+    pub async fn execute(self) {
+        let SortExecutor { child, inner: mut this } = self;
+        this.load()?;
+        #[for_await]
+        for msg in child {
+            match msg? {
+                Message::Chunk(chunk) => {
+                    let partition_keys = this.eval_partition_keys(&chunk)?;
+                    this.apply(&chunk, &partition_keys)?;
+                }
+                Message::Barrier(barrier) => {
+                    this.flush(barrier.epoch)?;
+                    yield Message::Barrier(barrier)
+                }
+                Message::Watermark(watermark) => yield Message::Watermark(watermark),
+            }
+        }
+        let mut builder = DataChunkBuilder::new(this.types(), PROCESSING_WINDOW_SIZE);
+        for row in this.rows_in(&vec![], 0..usize::MAX) {
+            if let Some(chunk) = builder.push_row_with_op(RowKind::Insert, row) {
+                yield Message::Chunk(chunk)
+            }
+        }
+        if let Some(chunk) = builder.take() {
+            yield Message::Chunk(chunk)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use futures::{StreamExt, TryStreamExt};
+    use crate::array::Row;
+    use crate::state::MemoryState;
+    use crate::stream::Barrier;
+    use crate::types::{ColumnIndex, DataTypeKind, DataValue};
+    use super::*;
+
+    fn row(a: i32, b: i32) -> Row {
+        vec![DataValue::Int32(a), DataValue::Int32(b)]
+    }
+
+    /// Sorts the rows of `(a, b)` by `a` and `b`, each of which is descending if specified,
+    /// returning the output messages.
+    async fn sort(desc: [bool; 2], input: Vec<Message>) -> Vec<Message> {
+        let order_keys = desc.iter().enumerate().map(|(i, desc)| {
+            let mut key = RecExpr::default();
+            key.add(Expr::ColumnIndex(ColumnIndex(i as u32)));
+            (key, *desc)
+        }).collect();
+        let types = vec![DataType::new_nullable(DataTypeKind::Int32); 2];
+        SortExecutor::new(order_keys, types, Arc::new(MemoryState::new()),
+                          futures::stream::iter(input.into_iter().map(Ok)).boxed())
+            .execute().try_collect().await.unwrap()
+    }
+
+    fn chunk(rows: &[(RowKind, i32, i32)]) -> Message {
+        let types = [DataType::new_nullable(DataTypeKind::Int32), DataType::new_nullable(DataTypeKind::Int32)];
+        let mut builder = DataChunkBuilder::new(&types, PROCESSING_WINDOW_SIZE);
+        for (op, a, b) in rows {
+            builder.push_row_with_op(*op, row(*a, *b));
+        }
+        Message::Chunk(builder.take().unwrap())
+    }
+
+    fn input() -> Vec<Message> {
+        use RowKind::*;
+        vec![
+            chunk(&[(Insert, 2, 1), (Insert, 1, 2), (Insert, 3, 1)]),
+            Message::Barrier(Barrier { epoch: 1, timestamp: 0 }),
+            chunk(&[(Insert, 1, 1), (Insert, 2, 2), (Delete, 3, 1), (Insert, 2, 1)]),
+        ]
+    }
+
+    /// Returns the rows of the output after the barrier, all of which are inserts.
+    fn rows_after_barrier(output: Vec<Message>) -> Vec<Row> {
+        let mut output = output.into_iter();
+        // nothing is emitted before the end of the input
+        assert!(matches!(output.next(), Some(Message::Barrier(Barrier { epoch: 1, .. }))));
+        output.flat_map(|msg| {
+            let Message::Chunk(chunk) = msg else {
+                panic!("unexpected message after the barrier");
+            };
+            assert!(chunk.is_append_only());
+            (0..chunk.cardinality()).map(|i| chunk.row(i).to_owned()).collect::<Vec<_>>()
+        }).collect()
+    }
+
+    #[tokio::test]
+    async fn test_sort() {
+        let output = rows_after_barrier(sort([false, false], input()).await);
+        assert_eq!(output, vec![row(1, 1), row(1, 2), row(2, 1), row(2, 1), row(2, 2)]);
+
+        let output = rows_after_barrier(sort([true, false], input()).await);
+        assert_eq!(output, vec![row(2, 1), row(2, 1), row(2, 2), row(1, 1), row(1, 2)]);
+
+        let output = rows_after_barrier(sort([false, true], input()).await);
+        assert_eq!(output, vec![row(1, 2), row(1, 1), row(2, 2), row(2, 1), row(2, 1)]);
+    }
+}
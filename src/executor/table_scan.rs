@@ -1,16 +1,57 @@
 use futures::future::Either;
-use futures::stream::{PollNext, select_with_strategy, StreamExt};
+use futures::future;
+use futures::stream::{self, PollNext, select_with_strategy, StreamExt};
 use futures_async_stream::try_stream;
 use itertools::Itertools;
 use tokio::sync::mpsc::UnboundedReceiver;
-use crate::executor::ExecuteError;
-use crate::stream::{Barrier, Message};
-use crate::connector::StreamConnector;
+use crate::executor::{BarrierManagerRef, ExecuteError};
+use crate::array::DataChunk;
+use crate::stream::{Barrier, Message, Watermark};
+use crate::types::DataValue;
+use crate::connector::BoxedSource;
 use futures::TryStreamExt;
 
 pub struct TableScanExecutor {
-    pub data_source: Box<dyn StreamConnector + Sync + Send>,
-    pub rx: Option<UnboundedReceiver<Barrier>>
+    pub data_source: BoxedSource,
+    pub rx: Option<UnboundedReceiver<Barrier>>,
+    pub watermark_generator: Option<WatermarkGenerator>,
+    pub actor_id: u32,
+    pub barrier_manager: BarrierManagerRef,
+}
+
+/// Generates watermarks from the maximum event time seen so far minus the allowed delay.
+pub struct WatermarkGenerator {
+    /// The index of the event time column.
+    col_idx: usize,
+    delay: i64,
+    current: Option<Watermark>,
+}
+
+impl WatermarkGenerator {
+    pub fn new(col_idx: usize, delay: i64) -> Self {
+        WatermarkGenerator {
+            col_idx,
+            delay,
+            current: None,
+        }
+    }
+
+    /// Returns a new watermark if it's advanced by the chunk.
+    fn on_chunk(&mut self, chunk: &DataChunk) -> Option<Watermark> {
+        let array = chunk.array_at(self.col_idx);
+        let max = (0..chunk.cardinality())
+            .filter_map(|i| match array.get(i) {
+                DataValue::Timestamp(ts) => Some(ts),
+                _ => None,
+            })
+            .max()?;
+        let watermark = Watermark { timestamp: max.saturating_sub(self.delay) };
+        if self.current.map_or(true, |current| current < watermark) {
+            self.current = Some(watermark);
+            return Some(watermark);
+        }
+        None
+    }
 }
 
 impl TableScanExecutor {
@@ -18,7 +59,11 @@ impl TableScanExecutor {
     pub async fn execute(mut self) {
         let control_message_stream = Self::barrier_to_message_stream(self.rx.take().unwrap())
             .map_ok(Either::Left).boxed();
-        let data_message_stream = self.data_source.read().map_ok(Either::Right).boxed();
+        // the end of the source is marked by `None`, which ends the scan
+        let data_message_stream = self.data_source.read()
+            .map_ok(|msg| Either::Right(Some(msg)))
+            .chain(stream::once(future::ready(Ok(Either::Right(None)))))
+            .boxed();
         let strategy = |_: &mut PollNext| PollNext::Left;
         let mut stream = select_with_strategy(control_message_stream, data_message_stream, strategy)
             .boxed();
@@ -28,13 +73,32 @@ impl TableScanExecutor {
                 Either::Left(msg) => {
                     match msg {
                         Message::Barrier(_barrier) => {
+                            // the position of the rows before the barrier
+                            if let Some(position) = self.data_source.position() {
+                                self.barrier_manager.lock().unwrap()
+                                    .report_source_position(_barrier.epoch, self.actor_id, position);
+                            }
                             yield Message::Barrier(_barrier);
                         }
                         _ => {}
                     }
                 },
-                Either::Right(msg) => {
+                Either::Right(None) => {
+                    // the position of all rows, for the checkpoints after the scan ends
+                    if let Some(position) = self.data_source.position() {
+                        self.barrier_manager.lock().unwrap().report_source_end(self.actor_id, position);
+                    }
+                    break;
+                }
+                Either::Right(Some(msg)) => {
+                    let watermark = match (&msg, &mut self.watermark_generator) {
+                        (Message::Chunk(chunk), Some(generator)) => generator.on_chunk(chunk),
+                        _ => None,
+                    };
                     yield msg;
+                    if let Some(watermark) = watermark {
+                        yield Message::Watermark(watermark);
+                    }
                 }
             }
         }
@@ -48,4 +112,105 @@ impl TableScanExecutor {
             yield Message::Barrier(barrier);
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use tokio::sync::mpsc;
+    use crate::array::DataChunkBuilder;
+    use crate::checkpoint::BarrierManager;
+    use crate::connector::SourceConnector;
+    use crate::executor::{BoxedExecutor, PROCESSING_WINDOW_SIZE};
+    use crate::executor::executor::WrapExecutor;
+    use crate::executor::filter::FilterExecutor;
+    use crate::executor::hash_agg::HashAggExecutor;
+    use crate::executor::projection::ProjectionExecutor;
+    use crate::planner::{Expr, RecExpr};
+    use crate::state::MemoryState;
+    use crate::types::{ColumnIndex, DataType, DataTypeKind};
+    use super::*;
+
+    /// A source of chunks of `(ts, v)`, one chunk for each slice of rows.
+    struct ChunksSource(Vec<Vec<(i64, i32)>>);
+
+    impl SourceConnector for ChunksSource {
+        #[try_stream(boxed, ok = Message, error = ExecuteError)]
+        async fn read(&self) {
+            let types = [DataType::new_nullable(DataTypeKind::Timestamp), DataType::new_nullable(DataTypeKind::Int32)];
+            for rows in &self.0 {
+                let mut builder = DataChunkBuilder::new(&types, PROCESSING_WINDOW_SIZE);
+                for (ts, v) in rows {
+                    builder.push_row([DataValue::Timestamp(*ts), DataValue::Int32(*v)]);
+                }
+                yield Message::Chunk(builder.take().unwrap());
+            }
+        }
+    }
+
+    fn scan(chunks: Vec<Vec<(i64, i32)>>, barrier_manager: BarrierManagerRef) -> BoxedExecutor {
+        let (_tx, rx) = mpsc::unbounded_channel();
+        TableScanExecutor {
+            data_source: Box::new(ChunksSource(chunks)),
+            rx: Some(rx),
+            watermark_generator: Some(WatermarkGenerator::new(0, 5)),
+            actor_id: 1,
+            barrier_manager,
+        }.execute()
+    }
+
+    fn column(expr: &mut RecExpr, i: u32) -> egg::Id {
+        expr.add(Expr::ColumnIndex(ColumnIndex(i)))
+    }
+
+    fn chunks() -> Vec<Vec<(i64, i32)>> {
+        // the watermark isn't advanced by the second chunk
+        vec![vec![(10, 1), (30, 2), (20, 3)], vec![(25, 4)], vec![(40, 5)]]
+    }
+
+    #[tokio::test]
+    async fn test_watermark_generation() {
+        let barrier_manager = Arc::new(Mutex::new(BarrierManager::new()));
+        let output: Vec<Message> = scan(chunks(), barrier_manager).try_collect().await.unwrap();
+        let output = output.iter().map(|msg| match msg {
+            Message::Chunk(chunk) => Err(chunk.cardinality()),
+            Message::Watermark(watermark) => Ok(watermark.timestamp),
+            Message::Barrier(_) => unreachable!(),
+        }).collect_vec();
+        // a watermark of the max event time minus the delay follows the chunk advancing it
+        assert_eq!(output, vec![Err(3), Ok(25), Err(1), Err(1), Ok(35)]);
+    }
+
+    #[tokio::test]
+    async fn test_watermark_forwarding() {
+        let barrier_manager = Arc::new(Mutex::new(BarrierManager::new()));
+        // SELECT count(v), ts FROM (SELECT ts, v FROM t WHERE v > 0) GROUP BY ts
+        let mut condition = RecExpr::default();
+        let v = column(&mut condition, 1);
+        let zero = condition.add(Expr::Constant(DataValue::Int32(0)));
+        condition.add(Expr::Gt([v, zero]));
+        let mut exprs = RecExpr::default();
+        let ts = column(&mut exprs, 0);
+        let v = column(&mut exprs, 1);
+        exprs.add(Expr::List([ts, v].into()));
+        let mut aggs = RecExpr::default();
+        let v = column(&mut aggs, 1);
+        let count = aggs.add(Expr::Count(v));
+        aggs.add(Expr::List([count].into()));
+        let mut group_keys = RecExpr::default();
+        let ts = column(&mut group_keys, 0);
+        group_keys.add(Expr::List([ts].into()));
+        let types = vec![DataType::new_nullable(DataTypeKind::Int32), DataType::new_nullable(DataTypeKind::Timestamp)];
+
+        let filter = FilterExecutor { condition, child: scan(chunks(), barrier_manager.clone()) }.execute();
+        let projection = ProjectionExecutor { exprs, child: filter }.execute();
+        let agg = HashAggExecutor::new(aggs, group_keys, types, Arc::new(MemoryState::new()), projection).execute();
+        let output: Vec<Message> = WrapExecutor::new(agg, 2, barrier_manager).execute()
+            .try_collect().await.unwrap();
+        let watermarks = output.iter().filter_map(|msg| match msg {
+            Message::Watermark(watermark) => Some(watermark.timestamp),
+            _ => None,
+        }).collect_vec();
+        assert_eq!(watermarks, vec![25, 35]);
+    }
+}
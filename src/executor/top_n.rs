@@ -0,0 +1,361 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use futures_async_stream::try_stream;
+use crate::array::{DataChunk, DataChunkBuilder, Row, RowKind};
+use crate::executor::evaluator::Evaluator;
+use crate::executor::hash_join::build_chunk;
+use crate::executor::row_store::{state_key, RowStore};
+use crate::executor::{BoxedExecutor, ExecuteError, PROCESSING_WINDOW_SIZE};
+use crate::planner::RecExpr;
+use crate::state::StateRef;
+use crate::stream::Message;
+use crate::types::{DataType, DataValue};
+
+/// A value of an order key, where nulls are the smallest values.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum OrderValue {
+    Asc(DataValue),
+    Desc(Reverse<DataValue>),
+}
+
+/// The order keys of a row followed by the row, so that the identical rows are
+/// adjacent and the rows of the same order keys are in a deterministic order.
+type SortKey = (Vec<OrderValue>, Row);
+
+/// The executor of `ORDER BY ... LIMIT`, which keeps the top rows of each partition
+/// in order, and emits the changes of the rows in the range of `offset` and `limit`.
+///
+/// A row entering the range is inserted and a row leaving it is deleted, so an insert
+/// or a retraction of the input may move other rows into or out of the range.
+pub struct TopNExecutor {
+    pub child: BoxedExecutor,
+    pub limit: usize,
+    pub offset: usize,
+    /// Whether the input never retracts rows, in which case the rows after
+    /// the range are dropped, as they can never enter it.
+    pub append_only: bool,
+    inner: ExecutorInner,
+}
+
+/// The rows of a partition in the order.
+type SortedRows = BTreeMap<SortKey, i64>;
+
+/// The rows of the input kept in order by partitions, which is shared with
+/// [`SortExecutor`](super::sort::SortExecutor).
+pub(super) struct ExecutorInner {
+    /// The order keys, each of which is followed by whether it's descending.
+    order_keys: Vec<(RecExpr, bool)>,
+    partition_keys: RecExpr,
+    types: Vec<DataType>,
+    partitions: HashMap<Row, SortedRows>,
+    /// The persistent rows, whose key is the row and value is the count, which are
+    /// updated along with their partition keys.
+    store: RowStore<(Row, SortKey)>,
+}
+
+impl TopNExecutor {
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(limit: usize,
+               offset: usize,
+               order_keys: Vec<(RecExpr, bool)>,
+               partition_keys: RecExpr,
+               types: Vec<DataType>,
+               append_only: bool,
+               state: StateRef,
+               child: BoxedExecutor) -> Self {
+        TopNExecutor {
+            child,
+            limit,
+            offset,
+            append_only,
+            inner: ExecutorInner::new(order_keys, partition_keys, types, state),
+        }
+    }
+
+    #[try_stream(boxed, ok = Message, error = ExecuteError)]
+    pub async fn execute(self) {
+        let TopNExecutor { child, limit, offset, append_only, inner: mut this } = self;
+        let range = offset..offset.saturating_add(limit);
+        this.load()?;
+        #[for_await]
+        for msg in child {
+            match msg? {
+                Message::Chunk(chunk) => {
+                    let partition_keys = this.eval_partition_keys(&chunk)?;
+                    // the top rows of the updated partitions before the chunk
+                    let mut old_tops: HashMap<Row, Vec<Row>> = HashMap::new();
+                    for key in &partition_keys {
+                        if !old_tops.contains_key(key) {
+                            old_tops.insert(key.clone(), this.rows_in(key, range.clone()));
+                        }
+                    }
+                    this.apply(&chunk, &partition_keys)?;
+
+                    let mut builder = DataChunkBuilder::new(&this.types, PROCESSING_WINDOW_SIZE);
+                    for (key, old_top) in old_tops {
+                        let new_top = this.rows_in(&key, range.clone());
+                        if append_only {
+                            this.truncate(&key, range.end);
+                        }
+                        for (op, row) in diff(old_top, new_top) {
+                            if let Some(chunk) = builder.push_row_with_op(op, row) {
+                                yield Message::Chunk(chunk)
+                            }
+                        }
+                    }
+                    if let Some(chunk) = builder.take() {
+                        yield Message::Chunk(chunk)
+                    }
+                }
+                Message::Barrier(barrier) => {
+                    this.flush(barrier.epoch)?;
+                    yield Message::Barrier(barrier)
+                }
+                Message::Watermark(watermark) => yield Message::Watermark(watermark),
+            }
+        }
+    }
+}
+
+/// Returns the changes from the old rows to the new rows, deleting the rows only in
+/// the old ones and inserting the rows only in the new ones.
+fn diff(old: Vec<Row>, new: Vec<Row>) -> Vec<(RowKind, Row)> {
+    let mut counts: HashMap<Row, i64> = HashMap::new();
+    for row in &old {
+        *counts.entry(row.clone()).or_default() -= 1;
+    }
+    for row in &new {
+        *counts.entry(row.clone()).or_default() += 1;
+    }
+    let mut changes = vec![];
+    for row in old {
+        if let Some(count) = counts.get_mut(&row).filter(|count| **count < 0) {
+            *count += 1;
+            changes.push((RowKind::Delete, row));
+        }
+    }
+    for row in new {
+        if let Some(count) = counts.get_mut(&row).filter(|count| **count > 0) {
+            *count -= 1;
+            changes.push((RowKind::Insert, row));
+        }
+    }
+    changes
+}
+
+impl ExecutorInner {
+
+    pub fn new(order_keys: Vec<(RecExpr, bool)>, partition_keys: RecExpr, types: Vec<DataType>,
+               state: StateRef) -> Self {
+        ExecutorInner {
+            order_keys,
+            partition_keys,
+            types,
+            partitions: HashMap::new(),
+            store: RowStore::new(state),
+        }
+    }
+
+    pub fn types(&self) -> &[DataType] {
+        &self.types
+    }
+
+    pub fn eval_partition_keys(&self, chunk: &DataChunk) -> Result<Vec<Row>, ExecuteError> {
+        if self.partition_keys.as_ref().last().unwrap().as_list().is_empty() {
+            return Ok(vec![vec![]; chunk.cardinality()]);
+        }
+        let keys_chunk = Evaluator::new(&self.partition_keys).eval_list(chunk)?;
+        Ok((0..chunk.cardinality()).map(|i| keys_chunk.row(i).to_owned()).collect())
+    }
+
+    fn eval_order_keys(&self, chunk: &DataChunk) -> Result<Vec<Vec<OrderValue>>, ExecuteError> {
+        let mut keys = vec![vec![]; chunk.cardinality()];
+        for (expr, desc) in &self.order_keys {
+            let array = Evaluator::new(expr).eval(chunk)?;
+            for (i, key) in keys.iter_mut().enumerate() {
+                key.push(match desc {
+                    true => OrderValue::Desc(Reverse(array.get(i))),
+                    false => OrderValue::Asc(array.get(i)),
+                });
+            }
+        }
+        Ok(keys)
+    }
+
+    /// Inserts or retracts the rows of the chunk, whose partition keys are given.
+    pub fn apply(&mut self, chunk: &DataChunk, partition_keys: &[Row]) -> Result<(), ExecuteError> {
+        let order_keys = self.eval_order_keys(chunk)?;
+        for (i, (partition_key, order_key)) in partition_keys.iter().zip(order_keys).enumerate() {
+            let row = chunk.row(i).to_owned();
+            let sort_key = (order_key, row);
+            if chunk.op(i).is_retract() {
+                // a retraction of a row never received or dropped is ignored
+                let Some(rows) = self.partitions.get_mut(partition_key) else {
+                    continue;
+                };
+                let Some(count) = rows.get_mut(&sort_key) else {
+                    continue;
+                };
+                *count -= 1;
+                if *count == 0 {
+                    rows.remove(&sort_key);
+                }
+                if rows.is_empty() {
+                    self.partitions.remove(partition_key);
+                }
+            } else {
+                let rows = self.partitions.entry(partition_key.clone()).or_default();
+                *rows.entry(sort_key.clone()).or_default() += 1;
+            }
+            self.store.mark_dirty((partition_key.clone(), sort_key));
+        }
+        Ok(())
+    }
+
+    /// Returns the rows of a partition at the positions in the range, in order.
+    pub fn rows_in(&self, partition_key: &Row, range: std::ops::Range<usize>) -> Vec<Row> {
+        let Some(rows) = self.partitions.get(partition_key) else {
+            return vec![];
+        };
+        rows.iter()
+            .flat_map(|((_, row), count)| std::iter::repeat(row).take(*count as usize))
+            .skip(range.start)
+            .take(range.len())
+            .cloned()
+            .collect()
+    }
+
+    /// Drops the rows of a partition at the positions from `len`.
+    fn truncate(&mut self, partition_key: &Row, len: usize) {
+        let Some(rows) = self.partitions.get_mut(partition_key) else {
+            return;
+        };
+        let mut kept = 0;
+        let mut dropped = vec![];
+        for (key, count) in rows.iter_mut() {
+            if kept >= len {
+                dropped.push(key.clone());
+            } else if kept + *count as usize > len {
+                *count = (len - kept) as i64;
+                self.store.mark_dirty((partition_key.clone(), key.clone()));
+            }
+            kept += *count as usize;
+        }
+        for key in dropped {
+            rows.remove(&key);
+            self.store.mark_dirty((partition_key.clone(), key));
+        }
+    }
+
+    /// Loads the rows from the state.
+    pub fn load(&mut self) -> Result<(), ExecuteError> {
+        let (rows, counts): (Vec<_>, Vec<_>) = self.store.load::<1>(&[], &self.types)?.into_iter()
+            .map(|(row, [count])| (row, count))
+            .unzip();
+        if rows.is_empty() {
+            return Ok(());
+        }
+        let chunk = build_chunk(&self.types, rows.into_iter());
+        let partition_keys = self.eval_partition_keys(&chunk)?;
+        let order_keys = self.eval_order_keys(&chunk)?;
+        for (i, ((partition_key, order_key), count)) in partition_keys.into_iter().zip(order_keys).zip(counts).enumerate() {
+            let row = chunk.row(i).to_owned();
+            self.partitions.entry(partition_key).or_default().insert((order_key, row), count);
+        }
+        Ok(())
+    }
+
+    /// Writes the updated rows to the state, and commits them with the epoch.
+    pub fn flush(&mut self, epoch: u64) -> Result<(), ExecuteError> {
+        let partitions = &self.partitions;
+        self.store.flush(epoch, |(partition_key, sort_key)| {
+            let count = partitions.get(&partition_key).and_then(|rows| rows.get(&sort_key));
+            (state_key(&[], &sort_key.1), count.map(|count| [*count]))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use futures::{StreamExt, TryStreamExt};
+    use crate::planner::Expr;
+    use crate::state::MemoryState;
+    use crate::types::{ColumnIndex, DataTypeKind};
+    use super::*;
+
+    #[test]
+    fn test_diff() {
+        let row = |v| vec![DataValue::Int32(v)];
+        assert_eq!(diff(vec![row(1), row(2), row(2)], vec![row(2), row(3)]), vec![
+            (RowKind::Delete, row(1)),
+            (RowKind::Delete, row(2)),
+            (RowKind::Insert, row(3)),
+        ]);
+        assert!(diff(vec![row(1), row(2)], vec![row(1), row(2)]).is_empty());
+    }
+
+    /// Keeps a row of `(k, ts)` for each `k` by the Top-1 ordered by `ts`, which is
+    /// `ROW_NUMBER() OVER (PARTITION BY k ORDER BY ts) = 1`, returning the changes of each chunk.
+    async fn dedup(desc: bool, input: Vec<Vec<(i32, i32)>>) -> Vec<Vec<(RowKind, Row)>> {
+        let mut order_key = RecExpr::default();
+        order_key.add(Expr::ColumnIndex(ColumnIndex(1)));
+        let mut partition_keys = RecExpr::default();
+        let k = partition_keys.add(Expr::ColumnIndex(ColumnIndex(0)));
+        partition_keys.add(Expr::List([k].into()));
+        let types = vec![DataType::new_nullable(DataTypeKind::Int32); 2];
+        let messages: Vec<_> = input.into_iter().map(|rows| {
+            let mut builder = DataChunkBuilder::new(&types, PROCESSING_WINDOW_SIZE);
+            for (k, ts) in rows {
+                builder.push_row([DataValue::Int32(k), DataValue::Int32(ts)]);
+            }
+            Ok(Message::Chunk(builder.take().unwrap()))
+        }).collect();
+        let output: Vec<Message> = TopNExecutor::new(1, 0, vec![(order_key, desc)], partition_keys, types.clone(),
+                                                     true, Arc::new(MemoryState::new()),
+                                                     futures::stream::iter(messages).boxed())
+            .execute().try_collect().await.unwrap();
+        output.into_iter().map(|msg| {
+            let Message::Chunk(chunk) = msg else {
+                panic!("unexpected message");
+            };
+            let mut changes: Vec<_> = (0..chunk.cardinality()).map(|i| (chunk.op(i), chunk.row(i).to_owned())).collect();
+            changes.sort_by(|a, b| a.1.cmp(&b.1));
+            changes
+        }).collect()
+    }
+
+    #[tokio::test]
+    async fn test_dedup() {
+        let row = |k, ts| vec![DataValue::Int32(k), DataValue::Int32(ts)];
+        let input = || vec![vec![(1, 20), (2, 10), (1, 10)], vec![(1, 5), (2, 30), (1, 15)]];
+        // keeping the first row of each key replaces it by an earlier one
+        assert_eq!(dedup(false, input()).await, vec![
+            vec![(RowKind::Insert, row(1, 10)), (RowKind::Insert, row(2, 10))],
+            vec![(RowKind::Insert, row(1, 5)), (RowKind::Delete, row(1, 10))],
+        ]);
+        // keeping the last row of each key replaces it by a later one
+        assert_eq!(dedup(true, input()).await, vec![
+            vec![(RowKind::Insert, row(1, 20)), (RowKind::Insert, row(2, 10))],
+            vec![(RowKind::Delete, row(2, 10)), (RowKind::Insert, row(2, 30))],
+        ]);
+    }
+
+    #[test]
+    fn test_retract_unknown_row() {
+        let mut order_key = RecExpr::default();
+        order_key.add(Expr::ColumnIndex(ColumnIndex(0)));
+        let mut partition_keys = RecExpr::default();
+        let column = partition_keys.add(Expr::ColumnIndex(ColumnIndex(0)));
+        partition_keys.add(Expr::List([column].into()));
+        let types = vec![DataType::new_nullable(DataTypeKind::Int32)];
+        let mut this = ExecutorInner::new(vec![(order_key, false)], partition_keys, types,
+                                          Arc::new(MemoryState::new()));
+        // the retraction is ignored without keeping an empty partition
+        let chunk = DataChunk::single(1).with_ops([RowKind::Delete]);
+        let keys = this.eval_partition_keys(&chunk).unwrap();
+        this.apply(&chunk, &keys).unwrap();
+        assert!(this.partitions.is_empty());
+    }
+}
@@ -0,0 +1,84 @@
+use futures_async_stream::try_stream;
+use crate::array::DataChunkBuilder;
+use crate::executor::{BoxedExecutor, PROCESSING_WINDOW_SIZE};
+use crate::stream::Message;
+use crate::executor::ExecuteError;
+use crate::executor::evaluator::Evaluator;
+use crate::planner::RecExpr;
+use crate::types::{DataType, DataValue};
+
+/// The executor of windowing table-valued functions `TUMBLE` and `HOP`.
+///
+/// Each row is emitted once for every window containing its time column,
+/// with `window_start` and `window_end` appended.
+pub struct WindowExecutor {
+    pub time_col: RecExpr,
+    pub slide: i64,
+    pub size: i64,
+    pub types: Vec<DataType>,
+    pub child: BoxedExecutor,
+}
+
+impl WindowExecutor {
+
+    #[try_stream(boxed, ok = Message, error = ExecuteError)]
+    pub async fn execute(self) {
+        #[for_await]
+        for batch in self.child {
+            let batch = batch?;
+            match batch {
+                Message::Barrier(barrier) => {
+                    yield Message::Barrier(barrier)
+                },
+                Message::Watermark(watermark) => {
+                    yield Message::Watermark(watermark)
+                },
+                Message::Chunk(chunk) => {
+                    let time_col = Evaluator::new(&self.time_col).eval(&chunk)?;
+                    let mut builder = DataChunkBuilder::new(&self.types, PROCESSING_WINDOW_SIZE);
+                    for i in 0..chunk.cardinality() {
+                        // a row without time doesn't belong to any window
+                        let DataValue::Timestamp(ts) = time_col.get(i) else {
+                            continue;
+                        };
+                        for start in window_starts(ts, self.slide, self.size) {
+                            let row = chunk.row(i);
+                            let row = row.values().chain([
+                                DataValue::Timestamp(start),
+                                DataValue::Timestamp(start + self.size),
+                            ]);
+                            if let Some(chunk) = builder.push_row_with_op(chunk.op(i), row) {
+                                yield Message::Chunk(chunk)
+                            }
+                        }
+                    }
+                    if let Some(chunk) = builder.take() {
+                        yield Message::Chunk(chunk)
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Returns the start of all windows containing `ts`, from the latest to the earliest.
+fn window_starts(ts: i64, slide: i64, size: i64) -> impl Iterator<Item = i64> {
+    let last_start = ts - ts.rem_euclid(slide);
+    (0..).map(move |i| last_start - i * slide)
+        .take_while(move |start| start + size > ts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_starts() {
+        // tumble
+        assert_eq!(window_starts(12, 5, 5).collect::<Vec<_>>(), vec![10]);
+        assert_eq!(window_starts(-3, 5, 5).collect::<Vec<_>>(), vec![-5]);
+        // hop
+        assert_eq!(window_starts(12, 5, 15).collect::<Vec<_>>(), vec![10, 5, 0]);
+        assert_eq!(window_starts(10, 5, 10).collect::<Vec<_>>(), vec![10, 5]);
+    }
+}
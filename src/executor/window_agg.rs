@@ -0,0 +1,353 @@
+use std::collections::{BTreeMap, HashMap};
+use bytes::{Buf, BufMut};
+use futures_async_stream::try_stream;
+use crate::executor::{BoxedExecutor, PROCESSING_WINDOW_SIZE};
+use crate::executor::hash_agg::{GroupKeys, GroupState};
+use crate::executor::row_store::{state_key, RowStore};
+use crate::planner::RecExpr;
+use crate::state::serde::{deserialize_row, serialize_row};
+use crate::state::{StateError, StateRef};
+use crate::types::{DataType, DataTypeKind, DataValue};
+use crate::stream::Message;
+use crate::executor::ExecuteError;
+use crate::executor::evaluator::Evaluator;
+use crate::array::{DataChunk, DataChunkBuilder};
+
+/// The state key prefix of the groups of windows.
+const GROUP_PREFIX: u8 = 0;
+/// The state key of the end of the closed windows.
+const CLOSED_UNTIL_KEY: [u8; 1] = [1];
+
+/// The executor of aggregation grouped by `window_end`, along with `window_start` usually.
+///
+/// Unlike [`HashAggExecutor`](super::hash_agg::HashAggExecutor) which emits the updated groups
+/// for every chunk, the result of a window is emitted only once when the window is closed,
+/// and then its state is purged.
+///
+/// A window is closed once the watermark reaches its end,
+/// rows of a closed window arriving afterwards are dropped.
+/// The groups of the open windows are flushed to the state at each barrier.
+pub struct WindowAggExecutor {
+    pub child: BoxedExecutor,
+    inner: ExecutorInner,
+}
+
+struct ExecutorInner {
+    aggs: RecExpr,
+    group_keys: RecExpr,
+    window_end: RecExpr,
+    /// The agg values followed by the group keys.
+    types: Vec<DataType>,
+    /// window_end -> group keys -> the state of the group.
+    windows: BTreeMap<i64, HashMap<GroupKeys, GroupState>>,
+    /// The windows ending at or before it are closed.
+    closed_until: i64,
+    state: StateRef,
+    /// The persistent groups, whose key is the window end followed by the group keys,
+    /// and value is the row count, the numbers of non-null values aggregated and the agg values.
+    store: RowStore<(i64, GroupKeys)>,
+}
+
+impl WindowAggExecutor {
+
+    pub fn new(aggs: RecExpr,
+               group_keys: RecExpr,
+               window_end: RecExpr,
+               types: Vec<DataType>,
+               state: StateRef,
+               child: BoxedExecutor) -> Self {
+        WindowAggExecutor {
+            child,
+            inner: ExecutorInner {
+                aggs,
+                group_keys,
+                window_end,
+                types,
+                windows: BTreeMap::new(),
+                closed_until: i64::MIN,
+                state: state.clone(),
+                store: RowStore::new(state),
+            },
+        }
+    }
+
+    #[try_stream(boxed, ok = Message, error = ExecuteError)]
+    pub async fn execute(self) {
+        let WindowAggExecutor { child, inner: mut this } = self;
+        this.load()?;
+        #[for_await]
+        for batch in child {
+            let batch = batch?;
+            match batch {
+                Message::Barrier(barrier) => {
+                    this.flush(barrier.epoch)?;
+                    yield Message::Barrier(barrier)
+                },
+                Message::Watermark(watermark) => {
+                    for chunk in this.close_windows(watermark.timestamp) {
+                        yield Message::Chunk(chunk)
+                    }
+                    yield Message::Watermark(watermark)
+                },
+                Message::Chunk(chunk) => this.process(&chunk)?,
+            }
+        }
+    }
+}
+
+impl ExecutorInner {
+
+    fn num_aggs(&self) -> usize {
+        self.aggs.as_ref().last().unwrap().as_list().len()
+    }
+
+    /// Aggregates the rows into the groups of their open windows, dropping the groups
+    /// whose rows are all retracted.
+    fn process(&mut self, chunk: &DataChunk) -> Result<(), ExecuteError> {
+        let keys_chunk = Evaluator::new(&self.group_keys).eval_list(chunk)?;
+        let args_chunk = Evaluator::new(&self.aggs).eval_list(chunk)?;
+        let window_end = Evaluator::new(&self.window_end).eval(chunk)?;
+        let num_aggs = self.num_aggs();
+
+        for i in 0..chunk.cardinality() {
+            let DataValue::Timestamp(end) = window_end.get(i) else {
+                continue;
+            };
+            if end <= self.closed_until {
+                // late row of a closed window
+                continue;
+            }
+            let keys: GroupKeys = keys_chunk.row(i).values().collect();
+            self.store.mark_dirty((end, keys.clone()));
+            let groups = self.windows.entry(end).or_default();
+            let group = groups
+                .entry(keys.clone())
+                .or_insert_with(|| GroupState {
+                    row_count: 0,
+                    aggs: Evaluator::new(&self.aggs).init_agg_states(),
+                    non_null_counts: smallvec::smallvec![0; num_aggs],
+                });
+            if chunk.op(i).is_retract() {
+                group.row_count -= 1;
+                Evaluator::new(&self.aggs).agg_list_retract(&mut group.aggs, &mut group.non_null_counts, args_chunk.row(i).values())?;
+            } else {
+                group.row_count += 1;
+                Evaluator::new(&self.aggs).agg_list_append(&mut group.aggs, &mut group.non_null_counts, args_chunk.row(i).values());
+            }
+            if group.row_count <= 0 {
+                groups.remove(&keys);
+                if groups.is_empty() {
+                    self.windows.remove(&end);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Closes the windows ending at or before the watermark, returning their results,
+    /// and purges their groups.
+    fn close_windows(&mut self, watermark: i64) -> Vec<DataChunk> {
+        self.closed_until = self.closed_until.max(watermark);
+        let mut output = vec![];
+        let mut builder = DataChunkBuilder::new(&self.types, PROCESSING_WINDOW_SIZE);
+        while let Some(window) = self.windows.first_entry() {
+            if *window.key() > self.closed_until {
+                break;
+            }
+            let end = *window.key();
+            for (keys, group) in window.remove() {
+                let row = group.aggs.into_iter().chain(keys.iter().cloned());
+                if let Some(chunk) = builder.push_row(row) {
+                    output.push(chunk);
+                }
+                self.store.mark_dirty((end, keys));
+            }
+        }
+        if let Some(chunk) = builder.take() {
+            output.push(chunk);
+        }
+        output
+    }
+
+    /// The types of the state keys, which are the window end followed by the group keys.
+    fn key_types(&self) -> Vec<DataType> {
+        std::iter::once(DataType::new_nullable(DataTypeKind::Timestamp))
+            .chain(self.types[self.num_aggs()..].iter().cloned())
+            .collect()
+    }
+
+    /// Loads the groups of the open windows and the end of the closed windows from the state.
+    fn load(&mut self) -> Result<(), ExecuteError> {
+        if let Some(value) = self.state.get(&CLOSED_UNTIL_KEY)? {
+            let value: [u8; 8] = value.try_into()
+                .map_err(|_| StateError::Corrupted("invalid closed window end".into()))?;
+            self.closed_until = i64::from_le_bytes(value);
+        }
+        let num_aggs = self.num_aggs();
+        for (row, mut value) in self.store.load_values(&[GROUP_PREFIX], &self.key_types())? {
+            let DataValue::Timestamp(end) = row[0] else {
+                return Err(StateError::Corrupted("invalid window end".into()).into());
+            };
+            if value.len() < 8 * (1 + num_aggs) {
+                return Err(StateError::Corrupted("invalid group state".into()).into());
+            }
+            let row_count = value.get_i64_le();
+            let non_null_counts = (0..num_aggs).map(|_| value.get_i64_le()).collect();
+            let aggs = deserialize_row(&self.types[..num_aggs], &mut value)?;
+            self.windows.entry(end).or_default().insert(row[1..].iter().cloned().collect(), GroupState {
+                row_count,
+                aggs: aggs.into_iter().collect(),
+                non_null_counts,
+            });
+        }
+        Ok(())
+    }
+
+    /// Writes the updated groups and the end of the closed windows to the state,
+    /// and commits them with the epoch.
+    fn flush(&mut self, epoch: u64) -> Result<(), ExecuteError> {
+        self.state.put(CLOSED_UNTIL_KEY.to_vec(), self.closed_until.to_le_bytes().to_vec())?;
+        let windows = &self.windows;
+        self.store.flush_values(epoch, |(end, keys)| {
+            let row: Vec<DataValue> = std::iter::once(DataValue::Timestamp(end)).chain(keys.iter().cloned()).collect();
+            let group = windows.get(&end).and_then(|groups| groups.get(&keys));
+            (state_key(&[GROUP_PREFIX], &row), group.map(|group| {
+                let mut value = vec![];
+                value.put_i64_le(group.row_count);
+                for count in &group.non_null_counts {
+                    value.put_i64_le(*count);
+                }
+                value.extend(serialize_row(&group.aggs));
+                value
+            }))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use futures::{StreamExt, TryStreamExt};
+    use crate::array::{Row, RowKind};
+    use crate::planner::Expr;
+    use crate::state::{DiskState, MemoryState};
+    use crate::stream::{Barrier, Watermark};
+    use crate::types::ColumnIndex;
+    use super::*;
+
+    /// The input of a row of `(window_end, v)`, or a watermark or barrier.
+    enum Input {
+        Row(RowKind, i64, i32),
+        Watermark(i64),
+        Barrier(u64),
+    }
+
+    /// Runs `SELECT count(#1), #0 GROUP BY #0` with the window end `#0` on the input,
+    /// returning the rows emitted before each watermark, along with the watermark.
+    async fn count_by_window(state: StateRef, input: Vec<Input>) -> Vec<(Vec<Row>, i64)> {
+        let mut aggs = RecExpr::default();
+        let column = aggs.add(Expr::ColumnIndex(ColumnIndex(1)));
+        let count = aggs.add(Expr::Count(column));
+        aggs.add(Expr::List([count].into()));
+        let mut group_keys = RecExpr::default();
+        let column = group_keys.add(Expr::ColumnIndex(ColumnIndex(0)));
+        group_keys.add(Expr::List([column].into()));
+        let mut window_end = RecExpr::default();
+        window_end.add(Expr::ColumnIndex(ColumnIndex(0)));
+        let types = vec![DataType::new_nullable(DataTypeKind::Int32), DataType::new_nullable(DataTypeKind::Timestamp)];
+
+        let input_types = [DataType::new_nullable(DataTypeKind::Timestamp), DataType::new_nullable(DataTypeKind::Int32)];
+        let messages: Vec<_> = input.into_iter().map(|input| Ok(match input {
+            Input::Row(op, end, v) => {
+                let mut builder = DataChunkBuilder::new(&input_types, PROCESSING_WINDOW_SIZE);
+                builder.push_row_with_op(op, [DataValue::Timestamp(end), DataValue::Int32(v)]);
+                Message::Chunk(builder.take().unwrap())
+            }
+            Input::Watermark(timestamp) => Message::Watermark(Watermark { timestamp }),
+            Input::Barrier(epoch) => Message::Barrier(Barrier { epoch, timestamp: 0 }),
+        })).collect();
+        let output: Vec<Message> = WindowAggExecutor::new(aggs, group_keys, window_end, types, state,
+                                                          futures::stream::iter(messages).boxed())
+            .execute().try_collect().await.unwrap();
+        let mut emitted = vec![];
+        let mut rows = vec![];
+        for msg in output {
+            match msg {
+                Message::Chunk(chunk) => {
+                    assert!(chunk.is_append_only());
+                    rows.extend((0..chunk.cardinality()).map(|i| chunk.row(i).to_owned()));
+                }
+                Message::Watermark(watermark) => {
+                    rows.sort();
+                    emitted.push((std::mem::take(&mut rows), watermark.timestamp));
+                }
+                Message::Barrier(_) => {}
+            }
+        }
+        assert!(rows.is_empty(), "rows emitted without a watermark: {:?}", rows);
+        emitted
+    }
+
+    fn window(count: i32, end: i64) -> Row {
+        vec![DataValue::Int32(count), DataValue::Timestamp(end)]
+    }
+
+    #[tokio::test]
+    async fn test_emit_on_watermark() {
+        use Input::*;
+        let output = count_by_window(Arc::new(MemoryState::new()), vec![
+            Row(RowKind::Insert, 10, 1), Row(RowKind::Insert, 20, 1), Row(RowKind::Insert, 10, 2),
+            Watermark(5), Watermark(10), Watermark(15), Watermark(20), Watermark(30),
+        ]).await;
+        // each window is emitted once when the watermark reaches its end, then purged
+        assert_eq!(output, vec![
+            (vec![], 5),
+            (vec![window(2, 10)], 10),
+            (vec![], 15),
+            (vec![window(1, 20)], 20),
+            (vec![], 30),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_late_rows() {
+        use Input::*;
+        let output = count_by_window(Arc::new(MemoryState::new()), vec![
+            Row(RowKind::Insert, 10, 1), Watermark(10),
+            Row(RowKind::Insert, 10, 2), Row(RowKind::Insert, 20, 2), Watermark(8), Watermark(20),
+        ]).await;
+        // the row of the closed window is dropped, and a smaller watermark doesn't reopen it
+        assert_eq!(output, vec![(vec![window(1, 10)], 10), (vec![], 8), (vec![window(1, 20)], 20)]);
+    }
+
+    #[tokio::test]
+    async fn test_retract_to_empty() {
+        use Input::*;
+        let output = count_by_window(Arc::new(MemoryState::new()), vec![
+            Row(RowKind::Insert, 10, 1), Row(RowKind::Insert, 20, 1),
+            Row(RowKind::Delete, 10, 1), Row(RowKind::Delete, 20, 1), Row(RowKind::Insert, 20, 2),
+            Watermark(20),
+        ]).await;
+        // the group whose rows are all retracted isn't emitted
+        assert_eq!(output, vec![(vec![window(1, 20)], 20)]);
+    }
+
+    #[tokio::test]
+    async fn test_reload() {
+        use Input::*;
+        let dir = tempfile::tempdir().unwrap();
+        let state = || -> StateRef { Arc::new(DiskState::open(dir.path()).unwrap()) };
+        let output = count_by_window(state(), vec![
+            Row(RowKind::Insert, 10, 1), Row(RowKind::Insert, 20, 1), Watermark(10), Barrier(1),
+            Row(RowKind::Insert, 20, 2),
+        ]).await;
+        assert_eq!(output, vec![(vec![window(1, 10)], 10)]);
+
+        // the open window is reloaded without the row after the barrier, and the closed one
+        // is neither reloaded nor reopened by a late row
+        let output = count_by_window(state(), vec![
+            Row(RowKind::Insert, 10, 3), Row(RowKind::Insert, 20, 3), Watermark(20),
+        ]).await;
+        assert_eq!(output, vec![(vec![window(2, 20)], 20)]);
+    }
+}
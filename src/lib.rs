@@ -19,12 +19,13 @@ pub mod array;
 mod stream;
 pub mod checkpoint;
 mod db;
-mod connector;
+pub mod connector;
 mod state;
 mod row;
 mod planner;
 
 
 
-pub use self::db::{Database, Error};
+pub use self::db::{Database, Error, RunResult};
+pub use self::stream::{JobHandle, JobInfo, JobStatus};
 
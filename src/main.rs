@@ -1,15 +1,72 @@
+use std::io::{IsTerminal, Read};
 use anyhow::Result;
+use futures::future::join_all;
 use rustyline::DefaultEditor;
 use rustyline::error::ReadlineError;
 use tokio::select;
-use rulink::{Database};
+use futures::StreamExt;
+use rulink::{Database, JobHandle};
 use tokio::signal;
 
+/// Runs the script of `-f <file>` or piped to stdin if given, otherwise reads statements
+/// interactively.
 #[tokio::main]
 async fn main() -> Result<()> {
-    let mut rl = DefaultEditor::new()?;
+    let args: Vec<String> = std::env::args().skip(1).collect();
     let db = &mut Database::new();
+    let script = match args.as_slice() {
+        [flag, file] if flag == "-f" => Some(std::fs::read_to_string(file)?),
+        [] if !std::io::stdin().is_terminal() => {
+            let mut script = String::new();
+            std::io::stdin().read_to_string(&mut script)?;
+            Some(script)
+        }
+        [] => None,
+        _ => {
+            eprintln!("usage: rulink [-f <file.sql>]");
+            std::process::exit(2);
+        }
+    };
+    match script {
+        Some(script) => std::process::exit(run_script(db, &script).await),
+        None => run_interactive(db).await,
+    }
+}
 
+/// Runs a script and waits for its jobs to end, returning the exit code,
+/// which is 1 if any statement or job failed.
+async fn run_script(db: &mut Database, script: &str) -> i32 {
+    let mut failed = false;
+    let mut jobs = vec![];
+    for (sql, result) in db.run_script(script).await {
+        match result {
+            Ok(result) => {
+                println!("{}", result.result_chunk);
+                jobs.extend(result.job);
+            }
+            Err(err) => {
+                eprintln!("failed to run `{}`: {}", sql, err);
+                failed = true;
+            }
+        }
+    }
+    let job_ids: Vec<String> = jobs.iter().map(|job| job.job_id()).collect();
+    select! {
+        succeeded = join_all(jobs.into_iter().map(print_job_output)) => {
+            failed |= succeeded.contains(&false);
+        }
+        _ = signal::ctrl_c() => {
+            for job_id in &job_ids {
+                db.stop_job(job_id).await;
+            }
+            return 130;
+        }
+    }
+    failed as i32
+}
+
+async fn run_interactive(db: &mut Database) -> Result<()> {
+    let mut rl = DefaultEditor::new()?;
     loop {
         let read_sql = read_sql(&mut rl);
         match read_sql {
@@ -71,7 +128,7 @@ fn read_sql(rl: &mut DefaultEditor) -> Result<String, ReadlineError> {
 
 
 async fn run_sql(db: &mut Database, sql: String) {
-    let result = db.run(sql.as_str());
+    let results = db.run_script(sql.as_str());
     select! {
          _ = signal::ctrl_c() => {
             if let Some(job_id) = db.get_last_running_job_id() {
@@ -82,16 +139,54 @@ async fn run_sql(db: &mut Database, sql: String) {
                  println!("Interrupted");
             }
         }
-        ret = result => {
-            match ret {
-                Ok(result) => {
-                    if let Some(job_id) = result.job_id {
-                         db.set_last_running_job_id(job_id);
+        results = results => {
+            for (_, ret) in results {
+                match ret {
+                    Ok(result) => {
+                        if let Some(job_id) = result.job_id {
+                             db.set_last_running_job_id(job_id);
+                        }
+                        println!("{}", result.result_chunk);
+                        if let Some(job) = result.job {
+                            tokio::spawn(print_job_output(job));
+                        }
                     }
-                    println!("{}", result.result_chunk);
+                    Err(err) => println!("{}", err),
                 }
-                Err(err) => println!("{}", err),
             }
         }
     }
-}
\ No newline at end of file
+}
+
+/// Prints the output chunks of a job until it ends, returning whether it succeeded.
+async fn print_job_output(mut job: JobHandle) -> bool {
+    while let Some(chunk) = job.next().await {
+        match chunk {
+            Ok(chunk) => println!("{}", chunk),
+            Err(err) => {
+                eprintln!("job {} failed: {}", job.job_id(), err);
+                return false;
+            }
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_run_script_exit_code() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("t.csv"), "1\nx\n").unwrap();
+        let create = format!(
+            "create table t(a int) with ('connector' = 'filesystem', 'path' = '{}', 'format' = 'csv');",
+            dir.path().display());
+        assert_eq!(run_script(&mut Database::new(), &format!("{} drop table t;", create)).await, 0);
+        // a failed statement
+        assert_eq!(run_script(&mut Database::new(), &format!("{} select b from t;", create)).await, 1);
+        // a failed job, whose input is malformed
+        assert_eq!(run_script(&mut Database::new(), &format!("{} select a from t;", create)).await, 1);
+    }
+}
@@ -2,8 +2,309 @@ pub use sqlparser::ast::*;
 use sqlparser::dialect::PostgreSqlDialect;
 use sqlparser::parser::Parser;
 pub use sqlparser::parser::ParserError;
+use {
+    once_cell::sync::Lazy,
+    regex::{Captures, Regex},
+};
+
+/// The name prefix of the `CHECK` constraint that a watermark clause is rewritten into,
+/// which is reserved, so that such a constraint is only written by the rewrite.
+pub const WATERMARK_CONSTRAINT_PREFIX: &str = "watermark for ";
+
+/// The name of the table argument that `FOR SYSTEM_TIME AS OF` is rewritten into.
+pub const SYSTEM_TIME_ARG: &str = "for system_time as of";
+
+static WATERMARK_RE: Lazy<Regex> = Lazy::new(|| Regex::new(
+    r#"(?i)\bwatermark\s+for\s+("[^"]+"|\w+)\s+as\s+"#).unwrap());
+
+static CREATE_TABLE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(
+    r#"(?i)^\s*create\s+table\s"#).unwrap());
+
+static RESERVED_NAME_RE: Lazy<Regex> = Lazy::new(|| Regex::new(
+    &format!("\"{}", regex::escape(WATERMARK_CONSTRAINT_PREFIX))).unwrap());
+
+static SYSTEM_TIME_RE: Lazy<Regex> = Lazy::new(|| Regex::new(
+    r#"(?i)\bfor\s+system_time\s+as\s+of\s+((?:"[^"]+"|\w+)(?:\s*\.\s*(?:"[^"]+"|\w+))*(?:\s*\(\s*\))?)"#).unwrap());
 
 pub fn parse(sql: &str) -> Result<Vec<Statement>, ParserError> {
     let dialect = PostgreSqlDialect {};
-    Parser::parse_sql(&dialect, sql)
-}
\ No newline at end of file
+    // the constraints named by the prefix are only written by the rewrite of watermarks
+    if RESERVED_NAME_RE.find_iter(sql).any(|m| context_at(sql, m.start()).is_some()) {
+        return Err(ParserError::ParserError(format!(
+            "names starting with {:?} are reserved for watermarks", WATERMARK_CONSTRAINT_PREFIX)));
+    }
+    Parser::parse_sql(&dialect, &rewrite_system_time(&rewrite_watermark(sql)))
+}
+
+/// Rewrites `<table> FOR SYSTEM_TIME AS OF <time>` of a lookup join, which is not supported
+/// by sqlparser, into the table argument `<table>("for system_time as of" => <time>)`,
+/// where the time is a column or a function call without arguments like `PROCTIME()`.
+fn rewrite_system_time(sql: &str) -> String {
+    rewrite_matches(sql, &SYSTEM_TIME_RE, |_, cap, _| {
+        Some((format!("(\"{}\" => {})", SYSTEM_TIME_ARG, &cap[1]), cap.get(0).unwrap().end()))
+    })
+}
+
+/// Rewrites `WATERMARK FOR <column> AS <expr>` in the column list of `CREATE TABLE`, which is not
+/// supported by sqlparser, into the table constraint `CONSTRAINT "watermark for <column>" CHECK (<expr>)`.
+fn rewrite_watermark(sql: &str) -> String {
+    rewrite_matches(sql, &WATERMARK_RE, |sql, cap, ctx| {
+        if !ctx.in_create_table_columns(sql) {
+            return None;
+        }
+        let (clause, column) = (cap.get(0).unwrap(), cap[1].trim_matches('"'));
+        let end = expr_end(sql, clause.end());
+        let rewritten = format!("CONSTRAINT \"{}{}\" CHECK ({})",
+                                WATERMARK_CONSTRAINT_PREFIX, column, &sql[clause.end()..end]);
+        Some((rewritten, end))
+    })
+}
+
+/// Rewrites the matches of a regex out of quotes and comments, where `rewrite` returns
+/// the replacement of a match and the end of the text replaced, or `None` to keep it.
+fn rewrite_matches(
+    sql: &str, re: &Regex,
+    rewrite: impl Fn(&str, &Captures, &SqlContext) -> Option<(String, usize)>) -> String {
+    let mut sql = sql.to_string();
+    let mut pos = 0;
+    while let Some(cap) = re.captures_at(&sql, pos) {
+        let start = cap.get(0).unwrap().start();
+        match context_at(&sql, start).and_then(|ctx| rewrite(&sql, &cap, &ctx)) {
+            Some((rewritten, end)) => {
+                pos = start + rewritten.len();
+                sql.replace_range(start..end, &rewritten);
+            }
+            None => pos = start + sql[start..].chars().next().unwrap().len_utf8(),
+        }
+    }
+    sql
+}
+
+/// Where a position is in the SQL text.
+#[derive(Debug, Default)]
+struct SqlContext {
+    /// The start of the statement.
+    statement_start: usize,
+    /// The first `(` of the statement.
+    first_paren: Option<usize>,
+    /// The unmatched `(` before the position.
+    parens: Vec<usize>,
+}
+
+impl SqlContext {
+    /// Returns whether the position is directly in the column list of `CREATE TABLE`.
+    fn in_create_table_columns(&self, sql: &str) -> bool {
+        match (self.parens.as_slice(), self.first_paren) {
+            ([paren], Some(first)) if *paren == first => {
+                CREATE_TABLE_RE.is_match(&sql[self.statement_start..first])
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Returns where a position is, `None` if it's in a quoted string or identifier, or a comment.
+fn context_at(sql: &str, pos: usize) -> Option<SqlContext> {
+    let mut ctx = SqlContext::default();
+    let mut chars = sql.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if i >= pos {
+            break;
+        }
+        let end = match c {
+            '\'' | '"' => loop {
+                // a doubled quote in a quoted string is an escaped quote
+                match chars.next() {
+                    Some((j, next)) if next == c && chars.next_if(|(_, after)| *after == c).is_none() => break j,
+                    Some(_) => {}
+                    None => break sql.len(),
+                }
+            },
+            '-' if chars.next_if(|(_, next)| *next == '-').is_some() => loop {
+                match chars.next() {
+                    Some((j, '\n')) => break j,
+                    Some(_) => {}
+                    None => break sql.len(),
+                }
+            },
+            '/' if chars.next_if(|(_, next)| *next == '*').is_some() => loop {
+                match chars.next() {
+                    Some((_, '*')) => if let Some((j, _)) = chars.next_if(|(_, next)| *next == '/') {
+                        break j;
+                    },
+                    Some(_) => {}
+                    None => break sql.len(),
+                }
+            },
+            '(' => {
+                if ctx.first_paren.is_none() {
+                    ctx.first_paren = Some(i);
+                }
+                ctx.parens.push(i);
+                continue;
+            }
+            ')' => {
+                ctx.parens.pop();
+                continue;
+            }
+            ';' => {
+                ctx = SqlContext { statement_start: i + 1, ..Default::default() };
+                continue;
+            }
+            _ => continue,
+        };
+        if end >= pos {
+            return None;
+        }
+    }
+    Some(ctx)
+}
+
+/// Returns the end of the expression starting at `start`,
+/// which is the first `,` or unmatched `)` out of quotes and parentheses.
+fn expr_end(sql: &str, start: usize) -> usize {
+    let mut depth = 0;
+    let mut quote = None;
+    for (i, c) in sql[start..].char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') if depth == 0 => return start + i,
+            (None, ')') => depth -= 1,
+            (None, ',' | ';') if depth == 0 => return start + i,
+            _ => {}
+        }
+    }
+    sql.len()
+}
+
+/// Splits a script into statements by the `;` out of quotes and comments,
+/// removing the comments and skipping empty statements.
+pub fn split_statements(script: &str) -> Vec<String> {
+    let mut statements = vec![];
+    let mut statement = String::new();
+    let mut chars = script.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' => {
+                statement.push(c);
+                // a doubled quote in a quoted string is an escaped quote
+                while let Some(next) = chars.next() {
+                    statement.push(next);
+                    if next == c {
+                        match chars.next_if_eq(&c) {
+                            Some(after) => statement.push(after),
+                            None => break,
+                        }
+                    }
+                }
+            }
+            '-' if chars.next_if_eq(&'-').is_some() => {
+                while chars.next_if(|next| *next != '\n').is_some() {}
+            }
+            '/' if chars.next_if_eq(&'*').is_some() => {
+                while let Some(next) = chars.next() {
+                    if next == '*' && chars.next_if_eq(&'/').is_some() {
+                        break;
+                    }
+                }
+                statement.push(' ');
+            }
+            ';' => statements.push(std::mem::take(&mut statement)),
+            c => statement.push(c),
+        }
+    }
+    statements.push(statement);
+    statements.into_iter()
+        .map(|statement| statement.trim().to_string())
+        .filter(|statement| !statement.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_statements() {
+        let script = "-- the tables\n\
+                      create table t(a int) with ('connector' = 'datagen'); ;\n\
+                      /* a comment; */ insert into s select a from t where a <> ';''';\n\
+                      show jobs\n\
+                      -- end;";
+        assert_eq!(split_statements(script), [
+            "create table t(a int) with ('connector' = 'datagen')",
+            "insert into s select a from t where a <> ';'''",
+            "show jobs",
+        ]);
+        assert!(split_statements(" -- nothing;\n;").is_empty());
+    }
+
+    #[test]
+    fn test_rewrite_system_time() {
+        let sql = "select * from o join d for system_time as of o.proctime as d1 on o.k = d1.k \
+                   join e FOR SYSTEM_TIME AS OF PROCTIME() on o.k = e.k";
+        assert_eq!(
+            rewrite_system_time(sql),
+            "select * from o join d (\"for system_time as of\" => o.proctime) as d1 on o.k = d1.k \
+             join e (\"for system_time as of\" => PROCTIME()) on o.k = e.k");
+        let Statement::Query(query) = &parse(sql).unwrap()[0] else {
+            panic!("not a query");
+        };
+        let SetExpr::Select(select) = query.body.as_ref() else {
+            panic!("not a select");
+        };
+        assert!(matches!(&select.from[0].joins[0].relation, TableFactor::Table { args: Some(args), .. }
+            if matches!(&args[0], FunctionArg::Named { name, .. } if name.value == SYSTEM_TIME_ARG)));
+
+        // not in quotes or comments
+        for sql in [
+            "insert into p values ('for system_time as of x')",
+            "select \"for system_time as of x\" from t",
+            "select a from t -- join d for system_time as of x\n",
+            "select a from t /* for system_time as of x */",
+        ] {
+            assert_eq!(rewrite_system_time(sql), sql);
+        }
+    }
+
+    #[test]
+    fn test_rewrite_watermark() {
+        let sql = "create table t(a int, ts timestamp, watermark for ts as ts - interval '5' second) \
+                   with ('connector' = 'datagen')";
+        assert_eq!(
+            rewrite_watermark(sql),
+            "create table t(a int, ts timestamp, \
+             CONSTRAINT \"watermark for ts\" CHECK (ts - interval '5' second)) \
+             with ('connector' = 'datagen')");
+        let Statement::CreateTable { constraints, .. } = &parse(sql).unwrap()[0] else {
+            panic!("not a create table statement");
+        };
+        assert!(matches!(&constraints[0], TableConstraint::Check { name: Some(name), .. }
+            if name.value == "watermark for ts"));
+
+        // only the clauses in the column list of CREATE TABLE out of quotes and comments
+        for sql in [
+            "insert into p values ('watermark for a as b')",
+            "select 'it''s watermark for a as b' from t",
+            "select a from t -- watermark for a as b\n",
+            "create table t(a int /* watermark for a as b */, b varchar default 'watermark for a as b')",
+            "create table t(a int) with ('k' = 'v', watermark for a as b)",
+        ] {
+            assert_eq!(rewrite_watermark(sql), sql);
+        }
+        assert!(parse("insert into p values ('watermark for a as b')").is_ok());
+    }
+
+    #[test]
+    fn test_reserved_watermark_name() {
+        // a constraint written like a rewritten watermark isn't taken as one
+        let sql = "create table t(a int, ts timestamp, constraint \"watermark for ts\" check (ts > a))";
+        assert!(matches!(parse(sql), Err(ParserError::ParserError(_))));
+        // but the prefix may be written in strings and in other names
+        assert!(parse("insert into p values ('\"watermark for a')").is_ok());
+        assert!(parse("create table t(a int, \"a\"\"watermark for b\" int, \"Watermark for c\" int)").is_ok());
+    }
+}
@@ -11,6 +11,7 @@ use crate::catalog::{CatalogRef};
 use egg::Id;
 use crate::types::{DataType, DataValue, ColumnIndex};
 use crate::catalog::{TableRefId, ColumnRefId};
+use sqlparser::ast::{BinaryOperator, UnaryOperator};
 
 pub use crate::planner::type_::TypeError;
 
@@ -29,11 +30,31 @@ define_language! {
 
         "list" = List(Box<[Id]>),       // (list ...)
 
-         "over" = Over([Id; 3]),
+        "over" = Over([Id; 3]),         // (over window_function [partition_key..] [order_key..])
+            "row_number" = RowNumber,
 
         // utilities
         "ref" = Ref(Id),                // (ref expr)
 
+        // binary operations
+        "+" = Add([Id; 2]),
+        "-" = Sub([Id; 2]),
+        "*" = Mul([Id; 2]),
+        "/" = Div([Id; 2]),
+        "%" = Mod([Id; 2]),
+        "=" = Eq([Id; 2]),
+        "<>" = NotEq([Id; 2]),
+        ">" = Gt([Id; 2]),
+        "<" = Lt([Id; 2]),
+        ">=" = GtEq([Id; 2]),
+        "<=" = LtEq([Id; 2]),
+        "and" = And([Id; 2]),
+        "or" = Or([Id; 2]),
+
+        // unary operations
+        "-" = Neg(Id),
+        "not" = Not(Id),
+
         // plans
         "scan" = Scan([Id; 3]), // (scan table [column..] filter)
         "value" = Values(Box<[Id]>),           // (values [expr..]..)
@@ -44,9 +65,24 @@ define_language! {
         "order" = Order([Id; 2]),               // (order [order_key..] child)
             "asc" = Asc(Id),                        // (asc key)
             "desc" = Desc(Id),                      // (desc key)
+        "topn" = TopN([Id; 5]),                 // (topn limit offset [order_key..] [partition_key..] child)
+
+        "distinct" = Distinct(Id),              // (distinct child)
 
         "agg" = Agg([Id; 3]),                   // (agg aggs=[expr..] group_keys=[expr..] child)
 
+        "join" = Join([Id; 4]),                 // (join join_type condition left right)
+            "inner" = Inner,
+            "left_outer" = LeftOuter,
+            "right_outer" = RightOuter,
+            "full_outer" = FullOuter,
+        "lookup_join" = LookupJoin([Id; 4]),    // (lookup_join join_type condition left (scan dim ..))
+
+        // windowing table-valued functions: TUMBLE is a HOP whose slide equals its size
+        "window" = Window([Id; 2]),             // (window [window_start window_end] child)
+            "window_start" = WindowStart([Id; 3]),  // (window_start time_col slide size)
+            "window_end" = WindowEnd([Id; 3]),      // (window_end time_col slide size)
+
         // aggregations
         "count" = Count(Id),
         "sum" = Sum(Id),
@@ -71,6 +107,11 @@ impl Expr {
         Self::Constant(DataValue::Int32(0))
     }
 
+    pub fn as_const(&self) -> &DataValue {
+        let Self::Constant(v) = self else { panic!("not a constant: {self}") };
+        v
+    }
+
     pub fn as_list(&self) -> &[Id] {
         let Self::List(l) = self else { panic!("not a list: {self}") };
         l
@@ -86,6 +127,43 @@ impl Expr {
         *c
     }
 
+    pub fn binary_op(&self) -> Option<(BinaryOperator, Id, Id)> {
+        use Expr::*;
+        Some(match self {
+            Add([a, b]) => (BinaryOperator::Plus, *a, *b),
+            Sub([a, b]) => (BinaryOperator::Minus, *a, *b),
+            Mul([a, b]) => (BinaryOperator::Multiply, *a, *b),
+            Div([a, b]) => (BinaryOperator::Divide, *a, *b),
+            Mod([a, b]) => (BinaryOperator::Modulo, *a, *b),
+            Eq([a, b]) => (BinaryOperator::Eq, *a, *b),
+            NotEq([a, b]) => (BinaryOperator::NotEq, *a, *b),
+            Gt([a, b]) => (BinaryOperator::Gt, *a, *b),
+            Lt([a, b]) => (BinaryOperator::Lt, *a, *b),
+            GtEq([a, b]) => (BinaryOperator::GtEq, *a, *b),
+            LtEq([a, b]) => (BinaryOperator::LtEq, *a, *b),
+            And([a, b]) => (BinaryOperator::And, *a, *b),
+            Or([a, b]) => (BinaryOperator::Or, *a, *b),
+            _ => return None,
+        })
+    }
+
+    pub fn unary_op(&self) -> Option<(UnaryOperator, Id)> {
+        use Expr::*;
+        Some(match self {
+            Neg(a) => (UnaryOperator::Minus, *a),
+            Not(a) => (UnaryOperator::Not, *a),
+            _ => return None,
+        })
+    }
+
+    pub const fn is_comparison(&self) -> bool {
+        use Expr::*;
+        matches!(
+            self,
+            Eq(_) | NotEq(_) | Gt(_) | Lt(_) | GtEq(_) | LtEq(_)
+        )
+    }
+
     pub const fn is_aggregate_function(&self) -> bool {
         use Expr::*;
         matches!(
@@ -95,6 +173,24 @@ impl Expr {
     }
 }
 
+/// Returns true if the output of a plan never retracts rows.
+pub fn is_append_only(egraph: &EGraph<Expr, TypeSchemaAnalysis>, id: Id) -> bool {
+    use Expr::*;
+    let node = |id: Id| &egraph[id].nodes[0];
+    match node(id) {
+        Scan(_) | Values(_) => true,
+        Proj([_, child]) | Filter([_, child]) | Window([_, child]) | Limit([_, _, child])
+        | Distinct(child) => is_append_only(egraph, *child),
+        // the windows are emitted once closed by watermarks
+        Agg([_, group_keys, child]) => node(*group_keys).as_list().iter()
+            .any(|key| matches!(node(*key), WindowEnd(_))) && is_append_only(egraph, *child),
+        Join([join_type, _, left, right]) => matches!(node(*join_type), Inner)
+            && is_append_only(egraph, *left) && is_append_only(egraph, *right),
+        LookupJoin([_, _, left, _]) => is_append_only(egraph, *left),
+        _ => false,
+    }
+}
+
 /// Analysis used in binding and building executor.
 #[derive(Default)]
 pub struct TypeSchemaAnalysis {
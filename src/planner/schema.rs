@@ -10,16 +10,18 @@ pub fn analyze_schema(enode: &Expr, x: impl Fn(&Id) -> Schema) -> Schema {
     let concat = |v1: Vec<Id>, v2: Vec<Id>| v1.into_iter().chain(v2.into_iter()).collect();
     match enode {
         // equal to child
-        Filter([_, c]) | Order([_, c]) | Limit([_, _, c]) => x(c),
+        Filter([_, c]) | Order([_, c]) | Limit([_, _, c]) | TopN([_, _, _, _, c]) | Distinct(c) => x(c),
 
-        // // concat 2 children
-        // Join([_, _, l, r]) | HashJoin([_, _, _, l, r]) => concat(x(l), x(r)),
+        // concat 2 children
+        Join([_, _, l, r]) | LookupJoin([_, _, l, r]) => concat(x(l), x(r)),
 
         // list is the source for the following nodes
         List(ids) => ids.to_vec(),
         Agg([exprs, group_keys, _]) => {
             concat(x(exprs), x(group_keys))
         }
+        // append window_start and window_end
+        Window([windows, c]) => concat(x(c), x(windows)),
 
         // plans that change schema
         Scan([_, columns, _]) => x(columns),
@@ -40,6 +40,8 @@ pub fn analyze_type(enode: &Expr, x: impl Fn(&Id) -> Type, catalog: CatalogRef)
                 .ok_or_else(|| TypeError::Unavailable(enode.to_string()))?
                 .datatype())
         }
+        // the same as the wrapped agg or group key
+        Ref(a) => x(a),
         List(list) => {
             let types = list.iter().map(x).try_collect()?;
             Ok(DataType::new(DataTypeKind::Struct(types), false))
@@ -58,12 +60,58 @@ pub fn analyze_type(enode: &Expr, x: impl Fn(&Id) -> Type, catalog: CatalogRef)
             }
             Ok(type_)
         },
+        // timestamp or interval plus or minus an interval
+        Add([a, b]) | Sub([a, b]) if matches!(
+            (x(a)?.kind(), x(b)?.kind()),
+            (DataTypeKind::Timestamp | DataTypeKind::Interval, DataTypeKind::Interval)) => {
+            let (a, b) = (x(a)?, x(b)?);
+            Ok(DataType::new(a.kind(), a.is_nullable() || b.is_nullable()))
+        }
+        // arithmetic ops
+        Add([a, b]) | Sub([a, b]) | Mul([a, b]) | Div([a, b]) | Mod([a, b]) => {
+            let ty = union(enode, x(a)?, x(b)?)?;
+            check(enode, ty, |t| t.is_number() || t == DataTypeKind::Null)
+        }
+        Neg(a) => check(enode, x(a)?, |t| t.is_number() || t == DataTypeKind::Null),
+
+        // comparison ops
+        Eq([a, b]) | NotEq([a, b]) | Gt([a, b]) | Lt([a, b]) | GtEq([a, b]) | LtEq([a, b]) => {
+            let ty = union(enode, x(a)?, x(b)?)?;
+            Ok(DataType::new(DataTypeKind::Bool, ty.is_nullable()))
+        }
+        // logical ops
+        And([a, b]) | Or([a, b]) => {
+            let ty = union(enode, x(a)?, x(b)?)?;
+            let ty = check(enode, ty, |t| matches!(t, DataTypeKind::Bool | DataTypeKind::Null))?;
+            Ok(DataType::new(DataTypeKind::Bool, ty.is_nullable()))
+        }
+        Not(a) => {
+            let ty = check(enode, x(a)?, |t| matches!(t, DataTypeKind::Bool | DataTypeKind::Null))?;
+            Ok(DataType::new(DataTypeKind::Bool, ty.is_nullable()))
+        }
+
+        WindowStart([time_col, _, _]) | WindowEnd([time_col, _, _]) => {
+            let ty = check(enode, x(time_col)?, |t| t == DataTypeKind::Timestamp)?;
+            Ok(DataType::new(DataTypeKind::Timestamp, ty.is_nullable()))
+        }
+
         Sum(a) => check(enode, x(a)?, |a| a.is_number()),
+        Count(_) => Ok(DataType::new(DataTypeKind::Int32, false)),
+        RowNumber => Ok(DataType::new(DataTypeKind::Int32, false)),
+        Over([window_function, _, _]) => x(window_function),
         Agg([exprs, group_keys, _]) => concat_struct(x(exprs)?, x(group_keys)?),
         _ => Err(TypeError::Unavailable(enode.to_string())),
     }
 }
 
+/// Returns the minimum compatible type of the two operands.
+fn union(enode: &Expr, a: DataType, b: DataType) -> Type {
+    a.union(&b).ok_or_else(|| TypeError::NoFunction {
+        op: enode.to_string(),
+        operands: vec![a.kind(), b.kind()],
+    })
+}
+
 fn check(enode: &Expr, a: DataType, check: impl FnOnce(DataTypeKind) -> bool) -> Type {
     if check(a.kind()) {
         Ok(a)
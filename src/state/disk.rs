@@ -0,0 +1,379 @@
+//! A state stored on disk as an append-only log, with an in-memory index from keys to
+//! the positions of their values, so that values don't need to fit in memory.
+//!
+//! A committed batch is appended as a sequence of records ended by a commit record:
+//! - put: `[1][key_len: u32][value_len: u32][key][value]`
+//! - delete: `[2][key_len: u32][key]`
+//! - commit: `[3][epoch: u64]`
+//!
+//! A batch without the commit record, which is left by a crash while writing, is dropped
+//! when opening. The log is compacted by rewriting the live entries once most of it is garbage.
+
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use bytes::BufMut;
+use crate::state::{KeyRange, KeyValue, State, StateError, StateHandle};
+
+const LOG_FILE: &str = "state.log";
+const COMPACT_FILE: &str = "state.log.compact";
+/// The log is never compacted until it exceeds this size.
+const COMPACT_MIN_SIZE: u64 = 4 << 20;
+
+const PUT: u8 = 1;
+const DELETE: u8 = 2;
+const COMMIT: u8 = 3;
+const PUT_HEADER_LEN: u64 = 9;
+
+pub struct DiskState {
+    inner: Mutex<Inner>,
+}
+
+struct Inner {
+    dir: PathBuf,
+    file: File,
+    index: BTreeMap<Vec<u8>, ValuePos>,
+    /// The uncommitted writes, `None` for deletes.
+    batch: BTreeMap<Vec<u8>, Option<Vec<u8>>>,
+    log_size: u64,
+    /// The size of put records of live entries.
+    live_size: u64,
+    committed_epoch: Option<u64>,
+}
+
+/// The position of a value in the log.
+#[derive(Debug, Clone, Copy)]
+struct ValuePos {
+    offset: u64,
+    len: u32,
+}
+
+enum Record {
+    Put { key: Vec<u8>, value: ValuePos },
+    Delete { key: Vec<u8> },
+    Commit { epoch: u64 },
+}
+
+impl DiskState {
+
+    /// Opens the state in `dir`, creating it if not exists.
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self, StateError> {
+        let dir = dir.as_ref().to_path_buf();
+        std::fs::create_dir_all(&dir)?;
+        let file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(dir.join(LOG_FILE))?;
+        let mut inner = Inner {
+            dir,
+            file,
+            index: BTreeMap::new(),
+            batch: BTreeMap::new(),
+            log_size: 0,
+            live_size: 0,
+            committed_epoch: None,
+        };
+        inner.replay()?;
+        Ok(DiskState { inner: Mutex::new(inner) })
+    }
+
+    /// Restores the state in `dir` from a checkpoint, replacing the existing one.
+    pub fn restore(dir: impl AsRef<Path>, handle: &StateHandle) -> Result<Self, StateError> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir)?;
+        let mut src = File::open(&handle.path)?.take(handle.len);
+        // the live log may be linked by checkpoints, so it's replaced rather than overwritten
+        let path = dir.join(COMPACT_FILE);
+        let mut dst = File::create(&path)?;
+        if std::io::copy(&mut src, &mut dst)? != handle.len {
+            return Err(StateError::Corrupted(format!(
+                "{} is shorter than {} bytes", handle.path.display(), handle.len)));
+        }
+        dst.sync_all()?;
+        std::fs::rename(&path, dir.join(LOG_FILE))?;
+        Self::open(dir)
+    }
+}
+
+impl Inner {
+
+    /// Rebuilds the index from the log, and drops the uncommitted tail.
+    fn replay(&mut self) -> Result<(), StateError> {
+        let file = self.file.try_clone()?;
+        let mut reader = BufReader::new(&file);
+        let mut pos = 0;
+        let mut pending = vec![];
+        loop {
+            let record = match read_record(&mut reader, pos) {
+                Ok(Some(record)) => record,
+                Ok(None) => break,
+                Err(StateError::Io(e)) if e.kind() == ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e),
+            };
+            pos += record_len(&record);
+            match record {
+                Record::Commit { epoch } => {
+                    for (key, value) in pending.drain(..) {
+                        self.apply(key, value);
+                    }
+                    self.log_size = pos;
+                    self.committed_epoch = Some(epoch);
+                }
+                Record::Put { key, value } => pending.push((key, Some(value))),
+                Record::Delete { key } => pending.push((key, None)),
+            }
+        }
+        self.file.set_len(self.log_size)?;
+        Ok(())
+    }
+
+    /// Applies a committed write to the index.
+    fn apply(&mut self, key: Vec<u8>, value: Option<ValuePos>) {
+        let key_len = key.len() as u64;
+        let old = match value {
+            Some(value) => {
+                self.live_size += PUT_HEADER_LEN + key_len + value.len as u64;
+                self.index.insert(key, value)
+            }
+            None => self.index.remove(&key),
+        };
+        if let Some(old) = old {
+            self.live_size -= PUT_HEADER_LEN + key_len + old.len as u64;
+        }
+    }
+
+    fn read_value(&self, pos: ValuePos) -> Result<Vec<u8>, StateError> {
+        let mut value = vec![0; pos.len as usize];
+        self.file.read_exact_at(&mut value, pos.offset)?;
+        Ok(value)
+    }
+
+    fn commit(&mut self, epoch: u64) -> Result<(), StateError> {
+        let mut buf = vec![];
+        let mut writes = vec![];
+        for (key, value) in std::mem::take(&mut self.batch) {
+            match value {
+                Some(value) => {
+                    buf.put_u8(PUT);
+                    buf.put_u32_le(key.len() as u32);
+                    buf.put_u32_le(value.len() as u32);
+                    buf.put_slice(&key);
+                    let offset = self.log_size + buf.len() as u64;
+                    buf.put_slice(&value);
+                    writes.push((key, Some(ValuePos { offset, len: value.len() as u32 })));
+                }
+                None if self.index.contains_key(&key) => {
+                    buf.put_u8(DELETE);
+                    buf.put_u32_le(key.len() as u32);
+                    buf.put_slice(&key);
+                    writes.push((key, None));
+                }
+                None => {}
+            }
+        }
+        buf.put_u8(COMMIT);
+        buf.put_u64_le(epoch);
+        self.file.write_all(&buf)?;
+        self.file.sync_data()?;
+        self.log_size += buf.len() as u64;
+        for (key, value) in writes {
+            self.apply(key, value);
+        }
+        self.committed_epoch = Some(epoch);
+        if self.log_size > COMPACT_MIN_SIZE && self.log_size > 2 * self.live_size {
+            self.compact()?;
+        }
+        Ok(())
+    }
+
+    /// Rewrites the live entries to a new log, which then replaces the current one.
+    fn compact(&mut self) -> Result<(), StateError> {
+        let path = self.dir.join(COMPACT_FILE);
+        let mut writer = BufWriter::new(File::create(&path)?);
+        let mut index = BTreeMap::new();
+        let mut offset = 0;
+        for (key, pos) in &self.index {
+            let value = self.read_value(*pos)?;
+            let mut buf = vec![];
+            buf.put_u8(PUT);
+            buf.put_u32_le(key.len() as u32);
+            buf.put_u32_le(value.len() as u32);
+            buf.put_slice(key);
+            writer.write_all(&buf)?;
+            writer.write_all(&value)?;
+            index.insert(key.clone(), ValuePos { offset: offset + buf.len() as u64, len: pos.len });
+            offset += (buf.len() + value.len()) as u64;
+        }
+        let mut buf = vec![];
+        buf.put_u8(COMMIT);
+        buf.put_u64_le(self.committed_epoch.unwrap_or_default());
+        writer.write_all(&buf)?;
+        let file = writer.into_inner().map_err(|e| e.into_error())?;
+        file.sync_all()?;
+        std::fs::rename(&path, self.dir.join(LOG_FILE))?;
+
+        self.file = OpenOptions::new().read(true).append(true).open(self.dir.join(LOG_FILE))?;
+        self.index = index;
+        self.live_size = offset;
+        self.log_size = offset + buf.len() as u64;
+        Ok(())
+    }
+}
+
+/// Reads a record at `pos` of the log, skipping the value of puts.
+/// Returns `None` at the end of the log.
+fn read_record(reader: &mut BufReader<&File>, pos: u64) -> Result<Option<Record>, StateError> {
+    let mut tag = [0; 1];
+    if reader.read(&mut tag)? == 0 {
+        return Ok(None);
+    }
+    let read_u32 = |reader: &mut BufReader<&File>| -> std::io::Result<u32> {
+        let mut buf = [0; 4];
+        reader.read_exact(&mut buf)?;
+        Ok(u32::from_le_bytes(buf))
+    };
+    let record = match tag[0] {
+        PUT => {
+            let key_len = read_u32(reader)?;
+            let value_len = read_u32(reader)?;
+            let mut key = vec![0; key_len as usize];
+            reader.read_exact(&mut key)?;
+            // the value must be fully written
+            std::io::copy(&mut reader.by_ref().take(value_len as u64), &mut std::io::sink())
+                .and_then(|n| match n == value_len as u64 {
+                    true => Ok(()),
+                    false => Err(ErrorKind::UnexpectedEof.into()),
+                })?;
+            let offset = pos + PUT_HEADER_LEN + key_len as u64;
+            Record::Put { key, value: ValuePos { offset, len: value_len } }
+        }
+        DELETE => {
+            let key_len = read_u32(reader)?;
+            let mut key = vec![0; key_len as usize];
+            reader.read_exact(&mut key)?;
+            Record::Delete { key }
+        }
+        COMMIT => {
+            let mut buf = [0; 8];
+            reader.read_exact(&mut buf)?;
+            Record::Commit { epoch: u64::from_le_bytes(buf) }
+        }
+        tag => return Err(StateError::Corrupted(format!("unknown record type {} at {}", tag, pos))),
+    };
+    Ok(Some(record))
+}
+
+fn record_len(record: &Record) -> u64 {
+    match record {
+        Record::Put { key, value } => PUT_HEADER_LEN + key.len() as u64 + value.len as u64,
+        Record::Delete { key } => 5 + key.len() as u64,
+        Record::Commit { .. } => 9,
+    }
+}
+
+impl State for DiskState {
+
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, StateError> {
+        let inner = self.inner.lock().unwrap();
+        if let Some(value) = inner.batch.get(key) {
+            return Ok(value.clone());
+        }
+        match inner.index.get(key) {
+            Some(pos) => Ok(Some(inner.read_value(*pos)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn put(&self, key: Vec<u8>, value: Vec<u8>) -> Result<(), StateError> {
+        self.inner.lock().unwrap().batch.insert(key, Some(value));
+        Ok(())
+    }
+
+    fn delete(&self, key: Vec<u8>) -> Result<(), StateError> {
+        self.inner.lock().unwrap().batch.insert(key, None);
+        Ok(())
+    }
+
+    fn scan(&self, range: KeyRange) -> Result<Vec<KeyValue>, StateError> {
+        let inner = self.inner.lock().unwrap();
+        let mut entries = BTreeMap::new();
+        for (key, pos) in inner.index.range(range.clone()) {
+            if !inner.batch.contains_key(key) {
+                entries.insert(key.clone(), Some(inner.read_value(*pos)?));
+            }
+        }
+        for (key, value) in inner.batch.range(range) {
+            entries.insert(key.clone(), value.clone());
+        }
+        Ok(entries.into_iter().filter_map(|(key, value)| Some((key, value?))).collect())
+    }
+
+    fn commit(&self, epoch: u64) -> Result<(), StateError> {
+        self.inner.lock().unwrap().commit(epoch)
+    }
+
+    fn committed_epoch(&self) -> Option<u64> {
+        self.inner.lock().unwrap().committed_epoch
+    }
+
+    /// The log is append-only, so its prefix is a snapshot of the committed state.
+    /// It's still valid after compaction if the log is hard linked before.
+    fn handle(&self) -> Option<StateHandle> {
+        let inner = self.inner.lock().unwrap();
+        Some(StateHandle {
+            path: inner.dir.join(LOG_FILE),
+            len: inner.log_size,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_torn_batch_and_compact() {
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let state = DiskState::open(dir.path()).unwrap();
+            for epoch in 0..3 {
+                state.put(b"key".to_vec(), vec![epoch as u8; 1 << 20]).unwrap();
+                state.put(epoch.to_string().into_bytes(), b"v".to_vec()).unwrap();
+                state.commit(epoch).unwrap();
+            }
+            state.delete(b"0".to_vec()).unwrap();
+            state.commit(3).unwrap();
+            // compaction is triggered after writing ~5MB
+            for epoch in 4..6 {
+                state.put(b"key".to_vec(), vec![epoch as u8; 1 << 20]).unwrap();
+                state.commit(epoch).unwrap();
+            }
+            let inner = state.inner.lock().unwrap();
+            assert!(inner.log_size < 3 << 20, "log is not compacted: {}", inner.log_size);
+        }
+        // simulate a crash while writing a batch
+        let mut file = OpenOptions::new().append(true).open(dir.path().join(LOG_FILE)).unwrap();
+        file.write_all(&[PUT, 3, 0, 0, 0, 1, 0]).unwrap();
+
+        let state = DiskState::open(dir.path()).unwrap();
+        assert_eq!(state.committed_epoch(), Some(5));
+        assert_eq!(state.get(b"key").unwrap(), Some(vec![5; 1 << 20]));
+        let keys: Vec<_> = state.scan((std::ops::Bound::Unbounded, std::ops::Bound::Unbounded))
+            .unwrap().into_iter().map(|(k, _)| k).collect();
+        assert_eq!(keys, vec![b"1".to_vec(), b"2".to_vec(), b"key".to_vec()]);
+        // the torn tail is truncated, so new batches can be appended
+        state.put(b"new".to_vec(), b"v".to_vec()).unwrap();
+        let handle = state.handle().unwrap();
+        state.commit(6).unwrap();
+        assert_eq!(DiskState::open(dir.path()).unwrap().get(b"new").unwrap(), Some(b"v".to_vec()));
+
+        // restore the state before the last commit
+        let restored = DiskState::restore(dir.path().join("restored"), &handle).unwrap();
+        assert_eq!(restored.committed_epoch(), Some(5));
+        assert_eq!(restored.get(b"new").unwrap(), None);
+    }
+}
@@ -0,0 +1,56 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use crate::state::{KeyRange, KeyValue, State, StateError, StateHandle};
+
+/// A state kept in memory, which is lost once the job stops.
+#[derive(Default)]
+pub struct MemoryState {
+    inner: Mutex<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    entries: BTreeMap<Vec<u8>, Vec<u8>>,
+    committed_epoch: Option<u64>,
+}
+
+impl MemoryState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl State for MemoryState {
+
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, StateError> {
+        Ok(self.inner.lock().unwrap().entries.get(key).cloned())
+    }
+
+    fn put(&self, key: Vec<u8>, value: Vec<u8>) -> Result<(), StateError> {
+        self.inner.lock().unwrap().entries.insert(key, value);
+        Ok(())
+    }
+
+    fn delete(&self, key: Vec<u8>) -> Result<(), StateError> {
+        self.inner.lock().unwrap().entries.remove(&key);
+        Ok(())
+    }
+
+    fn scan(&self, range: KeyRange) -> Result<Vec<KeyValue>, StateError> {
+        let inner = self.inner.lock().unwrap();
+        Ok(inner.entries.range(range).map(|(k, v)| (k.clone(), v.clone())).collect())
+    }
+
+    fn commit(&self, epoch: u64) -> Result<(), StateError> {
+        self.inner.lock().unwrap().committed_epoch = Some(epoch);
+        Ok(())
+    }
+
+    fn committed_epoch(&self) -> Option<u64> {
+        self.inner.lock().unwrap().committed_epoch
+    }
+
+    fn handle(&self) -> Option<StateHandle> {
+        None
+    }
+}
@@ -1,65 +1,127 @@
-mod serde;
+pub mod serde;
+mod memory;
+mod disk;
 
-// use rocksdb::{DB, WriteOptions};
-use crate::executor::ExecuteError;
+use std::ops::Bound;
+use std::path::PathBuf;
+use std::sync::Arc;
 
+pub use self::memory::MemoryState;
+pub use self::disk::DiskState;
 
+/// The range of keys to scan.
+pub type KeyRange = (Bound<Vec<u8>>, Bound<Vec<u8>>);
 
+pub type KeyValue = (Vec<u8>, Vec<u8>);
 
-pub trait State {
+/// A key-value store of operator state.
+///
+/// Writes are buffered in a batch and visible to reads at once,
+/// but they are only durable after the batch is committed with an epoch.
+pub trait State: Send + Sync {
 
-    fn get(&self, key: Vec<u8>) -> Option<Vec<u8>>;
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, StateError>;
 
-    fn put(&self, key: Vec<u8>, value: Vec<u8>) -> Result<(), ExecuteError>;
-}
+    fn put(&self, key: Vec<u8>, value: Vec<u8>) -> Result<(), StateError>;
 
+    fn delete(&self, key: Vec<u8>) -> Result<(), StateError>;
 
-pub struct RocksDBState {
-    // db: Arc<DB>
-}
+    /// Returns the key-value pairs in the range, ordered by key.
+    fn scan(&self, range: KeyRange) -> Result<Vec<KeyValue>, StateError>;
 
-impl State for RocksDBState {
+    /// Commits the writes since the last commit as the batch of `epoch`.
+    fn commit(&self, epoch: u64) -> Result<(), StateError>;
 
-    fn get(&self, key: Vec<u8>) -> Option<Vec<u8>> {
-        // self.db.get(key).ok()?
-        None
-    }
+    /// Returns the epoch of the last committed batch.
+    fn committed_epoch(&self) -> Option<u64>;
 
-    fn put(&self, key: Vec<u8>, value: Vec<u8>) -> Result<(), ExecuteError>{
-        // self.db.put( key, value)
-        //     .map_err(|e| todo!())
-        unimplemented!()
-    }
+    /// Returns the handle of the committed state to be recorded in checkpoints,
+    /// `None` if the state is not durable.
+    fn handle(&self) -> Option<StateHandle>;
 }
 
-impl RocksDBState {
+/// The committed state in a checkpoint: the first `len` bytes of the state log at `path`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateHandle {
+    pub path: PathBuf,
+    pub len: u64,
+}
+
+pub type StateRef = Arc<dyn State>;
+
+/// The state backend of jobs, selected by `SET state.backend = 'memory' | 'disk'`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum StateBackend {
+    #[default]
+    Memory,
+    /// Stores state under `dir`, which is set by `SET state.dir = '<dir>'`.
+    /// The values are only read from disk when used, but all the keys are indexed in memory.
+    Disk { dir: PathBuf },
+}
 
-    pub fn new(path: &str) -> Self {
-        // let db = DB::open_default(
-        //     path).unwrap();
-        // RocksDBState {
-        //     db: Arc::new(db)
-        // }
-        unimplemented!()
+/// The default directory of the disk state backend.
+pub const DEFAULT_STATE_DIR: &str = "rulink-state";
+
+impl StateBackend {
+
+    /// Opens the state of an executor in a job, which is restored from `handle` if given.
+    pub fn open(&self, job_id: &str, executor_id: u32, handle: Option<&StateHandle>)
+        -> Result<StateRef, StateError> {
+        Ok(match (self, handle) {
+            (StateBackend::Memory, None) => Arc::new(MemoryState::new()),
+            (StateBackend::Memory, Some(_)) => return Err(StateError::Corrupted(
+                "restoring state requires the disk state backend".into())),
+            (StateBackend::Disk { dir }, handle) => {
+                let dir = dir.join(job_id).join(executor_id.to_string());
+                match handle {
+                    Some(handle) => Arc::new(DiskState::restore(dir, handle)?),
+                    None => Arc::new(DiskState::open(dir)?),
+                }
+            }
+        })
     }
 }
 
+#[derive(thiserror::Error, Debug)]
+pub enum StateError {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("corrupted state: {0}")]
+    Corrupted(String),
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::state::{RocksDBState, State};
+    use std::ops::Bound;
+    use crate::state::{DiskState, MemoryState, State};
+
+    fn check_state(state: &dyn State) {
+        state.put(b"k1".to_vec(), b"v1".to_vec()).unwrap();
+        state.put(b"k2".to_vec(), b"v2".to_vec()).unwrap();
+        state.put(b"k3".to_vec(), b"v3".to_vec()).unwrap();
+        state.delete(b"k2".to_vec()).unwrap();
+        assert_eq!(state.get(b"k1").unwrap(), Some(b"v1".to_vec()));
+        assert_eq!(state.get(b"k2").unwrap(), None);
+        state.commit(1).unwrap();
+        state.put(b"k1".to_vec(), b"v1'".to_vec()).unwrap();
+        let scanned = state.scan((Bound::Included(b"k1".to_vec()), Bound::Unbounded)).unwrap();
+        assert_eq!(scanned, vec![
+            (b"k1".to_vec(), b"v1'".to_vec()),
+            (b"k3".to_vec(), b"v3".to_vec()),
+        ]);
+        assert_eq!(state.committed_epoch(), Some(1));
+    }
 
     #[test]
     fn t1() {
-        let path = "/Users/luoyuxia/CLionProjects/rustflink/src/state/rocks";
-        let state = RocksDBState::new(path);
-
-        let key = "key".as_bytes();
-        let val = "val".as_bytes();
-        println!("{:?}", state.get(key.to_vec()));
-
-        state.put(key.to_vec(), val.to_vec()).expect("TODO: panic message");
-
-        println!("{:?}",
-                 String::from_utf8(state.get(key.to_vec()).expect("xx")));
+        check_state(&MemoryState::new());
+
+        let dir = tempfile::tempdir().unwrap();
+        check_state(&DiskState::open(dir.path()).unwrap());
+        // the uncommitted write is lost after reopening
+        let state = DiskState::open(dir.path()).unwrap();
+        assert_eq!(state.get(b"k1").unwrap(), Some(b"v1".to_vec()));
+        assert_eq!(state.get(b"k2").unwrap(), None);
+        assert_eq!(state.committed_epoch(), Some(1));
     }
-}
\ No newline at end of file
+}
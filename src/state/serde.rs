@@ -1,61 +1,259 @@
+//! Binary encodings of rows in state.
+//!
+//! A row is encoded as a version byte followed by the values, each of which is prefixed by a
+//! tag of its type, see [`serialize_row`].
+//!
+//! A key is encoded in a memcomparable format, that is the encoded keys compare as bytes
+//! in the same order as the original values, so they can be used for range scans,
+//! see [`serialize_key`].
+
 use bytes::{Buf, BufMut, BytesMut};
 use smallvec::SmallVec;
+use crate::state::StateError;
 use crate::types::{DataType, DataTypeKind, DataValue};
 
+/// The current version of the row encoding.
+const ROW_VERSION: u8 = 1;
+
+const TAG_NULL: u8 = 0;
+const TAG_BOOL: u8 = 1;
+const TAG_INT32: u8 = 2;
+const TAG_STRING: u8 = 3;
+const TAG_TIMESTAMP: u8 = 4;
+const TAG_INTERVAL: u8 = 5;
+
+/// Serializes a row as `[version][tag][value][tag][value]...`.
 pub fn serialize_row(row: &[DataValue]) -> Vec<u8> {
     let  buf = &mut BytesMut::new();
+    buf.put_u8(ROW_VERSION);
     for val in row {
         serialize_data_value(val, buf);
     }
     buf.to_vec()
 }
 
-pub fn deserialize_row(data_types: &[DataType],  data : &mut BytesMut) -> SmallVec<[DataValue; 12]> {
+pub fn deserialize_row(data_types: &[DataType],  data : &mut BytesMut)
+    -> Result<SmallVec<[DataValue; 12]>, StateError> {
+    ensure(data, 1)?;
+    match data.get_u8() {
+        ROW_VERSION => {}
+        version => return Err(StateError::Corrupted(format!("unknown row version {}", version))),
+    }
     let mut result = SmallVec::new();
     for data_type in data_types {
-        result.push(deserialize_data_value(data_type, data));
+        result.push(deserialize_data_value(data_type, data)?);
     }
-    result
+    Ok(result)
 }
 
 fn serialize_data_value(date_value: &DataValue, buf:  &mut BytesMut) {
     match date_value {
+        DataValue::Null => {
+            buf.put_u8(TAG_NULL);
+        }
+        DataValue::Bool(v) => {
+            buf.put_u8(TAG_BOOL);
+            buf.put_u8(*v as u8);
+        }
         DataValue::Int32(v) => {
+            buf.put_u8(TAG_INT32);
             buf.put_i32_le(*v);
         }
-        _ => {
-            todo!()
+        DataValue::String(v) => {
+            buf.put_u8(TAG_STRING);
+            buf.put_u32_le(v.len() as u32);
+            buf.put_slice(v.as_bytes());
+        }
+        DataValue::Timestamp(v) => {
+            buf.put_u8(TAG_TIMESTAMP);
+            buf.put_i64_le(*v);
+        }
+        DataValue::Interval(v) => {
+            buf.put_u8(TAG_INTERVAL);
+            buf.put_i64_le(*v);
         }
     }
 }
 
-fn deserialize_data_value(data_type: &DataType, data: &mut BytesMut) -> DataValue {
-    match data_type.kind() {
-        DataTypeKind::Int32 => {
+fn deserialize_data_value(data_type: &DataType, data: &mut BytesMut) -> Result<DataValue, StateError> {
+    ensure(data, 1)?;
+    let tag = data.get_u8();
+    let value = match (tag, data_type.kind()) {
+        (TAG_NULL, _) => DataValue::Null,
+        (TAG_BOOL, DataTypeKind::Bool) => {
+            ensure(data, 1)?;
+            DataValue::Bool(data.get_u8() != 0)
+        },
+        (TAG_INT32, DataTypeKind::Int32) => {
+            ensure(data, 4)?;
             DataValue::Int32(data.get_i32_le())
         },
-        _ => todo!()
+        (TAG_STRING, DataTypeKind::String) => {
+            ensure(data, 4)?;
+            let len = data.get_u32_le() as usize;
+            ensure(data, len)?;
+            DataValue::String(utf8(data.split_to(len).to_vec())?)
+        },
+        (TAG_TIMESTAMP, DataTypeKind::Timestamp) => {
+            ensure(data, 8)?;
+            DataValue::Timestamp(data.get_i64_le())
+        },
+        (TAG_INTERVAL, DataTypeKind::Interval) => {
+            ensure(data, 8)?;
+            DataValue::Interval(data.get_i64_le())
+        },
+        (tag, kind) => return Err(StateError::Corrupted(
+            format!("unexpected value of tag {} for type {:?}", tag, kind))),
+    };
+    Ok(value)
+}
+
+/// Serializes the values in the memcomparable format:
+/// - a null flag, so nulls come first
+/// - integers in big-endian with the sign bit flipped
+/// - strings with `0x00` escaped as `0x00 0xff`, and terminated by `0x00 0x00`
+pub fn serialize_key(key: &[DataValue]) -> Vec<u8> {
+    let buf = &mut BytesMut::new();
+    for val in key {
+        if val.is_null() {
+            buf.put_u8(0);
+            continue;
+        }
+        buf.put_u8(1);
+        match val {
+            DataValue::Null => unreachable!(),
+            DataValue::Bool(v) => buf.put_u8(*v as u8),
+            DataValue::Int32(v) => buf.put_u32((*v as u32) ^ (1 << 31)),
+            DataValue::Timestamp(v) | DataValue::Interval(v) => buf.put_u64((*v as u64) ^ (1 << 63)),
+            DataValue::String(v) => {
+                for b in v.as_bytes() {
+                    buf.put_u8(*b);
+                    if *b == 0 {
+                        buf.put_u8(0xff);
+                    }
+                }
+                buf.put_slice(&[0, 0]);
+            }
+        }
+    }
+    buf.to_vec()
+}
+
+pub fn deserialize_key(data_types: &[DataType], data: &mut BytesMut)
+    -> Result<SmallVec<[DataValue; 4]>, StateError> {
+    let mut result = SmallVec::new();
+    for data_type in data_types {
+        ensure(data, 1)?;
+        if data.get_u8() == 0 {
+            result.push(DataValue::Null);
+            continue;
+        }
+        let value = match data_type.kind() {
+            DataTypeKind::Bool => {
+                ensure(data, 1)?;
+                DataValue::Bool(data.get_u8() != 0)
+            }
+            DataTypeKind::Int32 => {
+                ensure(data, 4)?;
+                DataValue::Int32((data.get_u32() ^ (1 << 31)) as i32)
+            }
+            DataTypeKind::Timestamp => {
+                ensure(data, 8)?;
+                DataValue::Timestamp((data.get_u64() ^ (1 << 63)) as i64)
+            }
+            DataTypeKind::Interval => {
+                ensure(data, 8)?;
+                DataValue::Interval((data.get_u64() ^ (1 << 63)) as i64)
+            }
+            DataTypeKind::String => {
+                let mut bytes = vec![];
+                loop {
+                    ensure(data, 2)?;
+                    match (data.get_u8(), data[0]) {
+                        (0, 0) => {
+                            data.advance(1);
+                            break;
+                        }
+                        (0, 0xff) => {
+                            data.advance(1);
+                            bytes.push(0);
+                        }
+                        (0, _) => return Err(StateError::Corrupted("invalid escaped string".into())),
+                        (b, _) => bytes.push(b),
+                    }
+                }
+                DataValue::String(utf8(bytes)?)
+            }
+            kind => return Err(StateError::Corrupted(format!("unsupported key type {:?}", kind))),
+        };
+        result.push(value);
+    }
+    Ok(result)
+}
+
+fn ensure(data: &BytesMut, len: usize) -> Result<(), StateError> {
+    match data.remaining() >= len {
+        true => Ok(()),
+        false => Err(StateError::Corrupted("unexpected end of data".into())),
     }
 }
 
+fn utf8(bytes: Vec<u8>) -> Result<String, StateError> {
+    String::from_utf8(bytes).map_err(|e| StateError::Corrupted(e.to_string()))
+}
+
 #[cfg(test)]
 mod tests {
     
+    use bytes::BytesMut;
     use smallvec::SmallVec;
-    use crate::state::serde::{serialize_row};
-    use crate::types::{DataValue};
+    use crate::state::serde::{deserialize_key, deserialize_row, serialize_key, serialize_row};
+    use crate::types::{DataType, DataTypeKind, DataValue};
 
     #[test]
     fn t1() {
         let mut vec = SmallVec::<[DataValue; 4]>::new();
         vec.push(DataValue::Int32(32));
+        vec.push(DataValue::Null);
+        vec.push(DataValue::String("rulink".into()));
+        vec.push(DataValue::Timestamp(-1));
+        vec.push(DataValue::Bool(true));
+        vec.push(DataValue::Interval(1000));
         let t1 =
         serialize_row(vec.as_slice());
-        println!("{:?}", t1);
 
-        // let data_types = vec![DataType::new_nullable(DataTypeKind::Int(None))];
-        // let buf = &mut BytesMut::from(t1.as_slice());
-        // let f = deserialize_row(&data_types, buf);
-        // println!("{:?}", f);
+        let data_types = [DataTypeKind::Int32, DataTypeKind::Int32, DataTypeKind::String,
+            DataTypeKind::Timestamp, DataTypeKind::Bool, DataTypeKind::Interval]
+            .map(DataType::new_nullable);
+        let buf = &mut BytesMut::from(t1.as_slice());
+        let f = deserialize_row(&data_types, buf).unwrap();
+        assert_eq!(f.as_slice(), vec.as_slice());
+
+        // mismatched types and truncated data are errors
+        assert!(deserialize_row(&[DataType::new_nullable(DataTypeKind::Bool)],
+                                &mut BytesMut::from(t1.as_slice())).is_err());
+        assert!(deserialize_row(&data_types, &mut BytesMut::from(&t1[..t1.len() - 1])).is_err());
+    }
+
+    #[test]
+    fn test_memcomparable() {
+        let data_types = [DataTypeKind::Int32, DataTypeKind::String, DataTypeKind::Timestamp]
+            .map(DataType::new_nullable);
+        let keys = vec![
+            [DataValue::Null, DataValue::String("b".into()), DataValue::Timestamp(0)],
+            [DataValue::Int32(-3), DataValue::String("".into()), DataValue::Timestamp(0)],
+            [DataValue::Int32(-3), DataValue::String("a".into()), DataValue::Null],
+            [DataValue::Int32(-3), DataValue::String("a".into()), DataValue::Timestamp(-5)],
+            [DataValue::Int32(-3), DataValue::String("a\0".into()), DataValue::Timestamp(-5)],
+            [DataValue::Int32(-3), DataValue::String("ab".into()), DataValue::Timestamp(-5)],
+            [DataValue::Int32(0), DataValue::String("a".into()), DataValue::Timestamp(7)],
+            [DataValue::Int32(i32::MAX), DataValue::Null, DataValue::Timestamp(i64::MIN)],
+        ];
+        let encoded: Vec<_> = keys.iter().map(|key| serialize_key(key)).collect();
+        assert!(encoded.windows(2).all(|w| w[0] < w[1]), "keys are not ordered");
+        for (key, encoded) in keys.iter().zip(encoded) {
+            let decoded = deserialize_key(&data_types, &mut BytesMut::from(encoded.as_slice())).unwrap();
+            assert_eq!(decoded.as_slice(), key.as_slice());
+        }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,146 @@
+use std::fmt::{Display, Formatter};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use futures::Stream;
+use tokio::sync::mpsc;
+use crate::array::DataChunk;
+use crate::types::timestamp;
+use crate::Error;
+
+/// The number of output chunks buffered for the handle, beyond which the job waits
+/// for them to be consumed.
+pub const OUTPUT_BUFFER_SIZE: usize = 16;
+
+/// The status of a streaming job.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobStatus {
+    Running,
+    /// All the input is processed.
+    Finished,
+    /// The job is stopped by an error or a panic.
+    Failed(String),
+    /// The job is stopped by `KILL JOB` or restored from a checkpoint.
+    Cancelled,
+}
+
+impl Display for JobStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JobStatus::Running => write!(f, "running"),
+            JobStatus::Finished => write!(f, "finished"),
+            JobStatus::Failed(_) => write!(f, "failed"),
+            JobStatus::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+/// What is tracked about a streaming job over its lifecycle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JobInfo {
+    pub job_id: String,
+    pub sql: String,
+    pub status: JobStatus,
+    /// The time the job started in milliseconds.
+    pub start_time: i64,
+    /// The epoch of the last checkpoint written, `None` if none is written yet
+    /// or checkpointing is disabled.
+    pub last_checkpoint_epoch: Option<u64>,
+}
+
+pub type JobInfoRef = Arc<Mutex<JobInfo>>;
+
+impl JobInfo {
+    pub fn new(job_id: String, sql: String) -> Self {
+        JobInfo {
+            job_id,
+            sql,
+            status: JobStatus::Running,
+            start_time: timestamp::now(),
+            last_checkpoint_epoch: None,
+        }
+    }
+
+    /// Returns the error message if the job failed.
+    pub fn error(&self) -> Option<&str> {
+        match &self.status {
+            JobStatus::Failed(error) => Some(error),
+            _ => None,
+        }
+    }
+
+    /// Sets the status of the job when it's stopped, unless it's stopped already.
+    pub(crate) fn stop(&mut self, status: JobStatus) {
+        if self.status == JobStatus::Running {
+            self.status = status;
+        }
+    }
+}
+
+/// The handle of a streaming job, which is a stream of the output chunks of the job,
+/// ended by the error if the job fails.
+///
+/// The output is dropped if the handle is dropped.
+pub struct JobHandle {
+    output: mpsc::Receiver<Result<DataChunk, Error>>,
+    info: JobInfoRef,
+}
+
+impl JobHandle {
+    pub fn new(output: mpsc::Receiver<Result<DataChunk, Error>>, info: JobInfoRef) -> Self {
+        JobHandle { output, info }
+    }
+
+    pub fn job_id(&self) -> String {
+        self.info.lock().unwrap().job_id.clone()
+    }
+
+    pub fn status(&self) -> JobStatus {
+        self.info.lock().unwrap().status.clone()
+    }
+
+    pub fn info(&self) -> JobInfo {
+        self.info.lock().unwrap().clone()
+    }
+}
+
+impl Stream for JobHandle {
+    type Item = Result<DataChunk, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.output.poll_recv(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+    use crate::executor::ExecuteError;
+    use crate::types::DataValue;
+    use crate::Database;
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_job_handle() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("t.csv"), "1\n2\nx\n3\n").unwrap();
+        let script = format!(
+            "create table t(a int) with ('connector' = 'filesystem', 'path' = '{}', 'format' = 'csv', \
+             'source.batch-size' = '2');\n\
+             select a from t;",
+            dir.path().display());
+        let mut db = Database::new();
+        let mut results = db.run_script(&script).await;
+        let mut handle = results.pop().unwrap().1.unwrap().job.unwrap();
+
+        // the output is buffered for the handle, so it's consumed even if the job is failed already
+        let chunk = handle.next().await.unwrap().unwrap();
+        let values: Vec<_> = (0..chunk.cardinality()).map(|i| chunk.row(i).get(0)).collect();
+        assert_eq!(values, [DataValue::Int32(1), DataValue::Int32(2)]);
+        // the malformed row fails the job, which ends the output
+        assert!(matches!(handle.next().await, Some(Err(Error::Execute(ExecuteError::MalformedRow(..))))));
+        assert!(handle.next().await.is_none());
+        assert!(matches!(handle.status(), JobStatus::Failed(_)));
+        assert_eq!(db.job(&handle.job_id()).unwrap().status, handle.status());
+    }
+}
@@ -1,11 +1,12 @@
+use std::any::Any;
 use std::sync::{Arc, Mutex};
-use std::thread;
 use std::time::Duration;
-use tokio::task::JoinHandle;
-use crate::checkpoint::{BarrierManager, BarrierService};
+use tokio::sync::mpsc;
+use tokio::task::{AbortHandle, JoinHandle};
+use crate::checkpoint::{BarrierManager, BarrierService, CheckpointStore};
 use crate::Error;
 use crate::executor::BoxedExecutor;
-use crate::stream::Message;
+use crate::stream::{JobHandle, JobInfo, JobInfoRef, JobStatus, Message, OUTPUT_BUFFER_SIZE};
 use futures::{TryStreamExt};
 
 pub struct Job {
@@ -14,94 +15,234 @@ pub struct Job {
     pub result_executor: BoxedExecutor,
 
     pub is_ddl_job: bool,
+    /// The epoch of the checkpoint the job is restored from, 0 for a new job.
+    pub start_epoch: u64,
+    /// Where the completed epochs are checkpointed, `None` if checkpointing is disabled.
+    pub checkpoint_store: Option<CheckpointStore>,
+    /// The statement of the job.
+    pub sql: String,
 }
 
 pub struct StreamRunningJob {
     pub current_job_id: String,
-    pub job_running_handle: Option<JoinHandle<Result<(), Error>>>,
-    pub job_checkpoint_handle: Option<JoinHandle<()>>,
+    pub job_abort_handle: AbortHandle,
+    /// The task waiting for the job or its checkpoints to end, which records how it ends.
+    pub job_supervisor_handle: Option<JoinHandle<()>>,
+    pub info: JobInfoRef,
 }
 
 impl Job {
-    pub async fn run(self) -> Option<StreamRunningJob> {
+    /// Runs the job, returning the running job with its handle unless it's a DDL job,
+    /// which is run to the end.
+    pub async fn run(self) -> Result<Option<(StreamRunningJob, JobHandle)>, Error> {
         if self.is_ddl_job {
-            self.running_ddl_job().await;
-            None
+            self.running_ddl_job().await?;
+            Ok(None)
         } else {
-            Some(self.running_stream_job())
+            Ok(Some(self.running_stream_job()))
         }
     }
 
-    async fn running_ddl_job(self) {
+    /// Runs a DDL job to the end, returning its error or panic.
+    async fn running_ddl_job(self) -> Result<(), Error> {
         let mut executor = self.result_executor;
-        tokio::spawn(async move {
-            // may follow actor::run_consumer
-            while let Some(chunk) = executor.try_next().await? {
-                match chunk {
-                    _ => {
-                        // do nothing
-                    }
-                }
-            }
+        let result = tokio::spawn(async move {
+            // the output of a DDL job is ignored
+            while executor.try_next().await?.is_some() {}
             Ok(()) as Result<(), Error>
-        });
+        }).await;
+        match result {
+            Ok(result) => result,
+            Err(e) => Err(Error::Panic(panic_message(e.into_panic()))),
+        }
     }
 
-    fn running_stream_job(self) -> StreamRunningJob {
+    fn running_stream_job(self) -> (StreamRunningJob, JobHandle) {
         let mut executor = self.result_executor;
+        let (output_tx, output_rx) = mpsc::channel(OUTPUT_BUFFER_SIZE);
+        let info = Arc::new(Mutex::new(JobInfo::new(self.current_job_id.clone(), self.sql)));
+        let mut sender_service = BarrierService::new(self.barrier_manager.clone(), self.start_epoch);
+        let barrier_manager = self.barrier_manager.clone();
+        let checkpoint_store = self.checkpoint_store;
+        let checkpoint_info = info.clone();
+        // the task for checkpoint, which only ends by a failed checkpoint or commit
+        let mut checkpoint_task: JoinHandle<Result<(), Error>> = tokio::spawn(async move {
+            // trigger one barrier
+            sender_service.send_barrier();
+            loop {
+                let barrier = sender_service.collect_barrier().await;
+                if let Some(store) = &checkpoint_store {
+                    let report = barrier_manager.lock().unwrap().take_report(barrier.epoch);
+                    store.write(barrier.epoch, report)?;
+                    checkpoint_info.lock().unwrap().last_checkpoint_epoch = Some(barrier.epoch);
+                }
+                // the output is committed after the checkpoint
+                let commits = barrier_manager.lock().unwrap().take_commits(barrier.epoch);
+                for commit in commits {
+                    commit.await?;
+                }
+                tokio::time::sleep(Duration::from_secs(2)).await;
+                sender_service.send_barrier();
+            }
+        });
         // the task for running
-        let job_task = tokio::spawn(async move{
+        let job_output_tx = output_tx.clone();
+        let mut job_task = tokio::spawn(async move{
             // may follow actor::run_consumer
             while let Some(chunk) = executor.try_next().await? {
                 match chunk {
                     Message::Chunk(chunk) => {
-                        println!("{}", &chunk);
+                        // the output is dropped if the handle is dropped
+                        let _ = job_output_tx.send(Ok(chunk)).await;
                     }
-                    Message::Barrier(_) => {}
+                    Message::Barrier(_) | Message::Watermark(_) => {}
                 }
             }
             Ok(()) as Result<(), Error>
         });
-        // the task for checkpoint
-        let mut sender_service = BarrierService::new(self.barrier_manager.clone());
-        let checkpoint_task =
-            tokio::spawn(async move {
-                // trigger one barrier
-                sender_service.send_barrier();
-                loop {
-                    let mut min_interval = tokio::time::interval(Duration::from_secs(100));
-                    min_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
-                    tokio::select! {
-                                    barrier = sender_service.collect_barrier() => {
-                                        // println!("receive checkpoint ack of epoch {}.", barrier.epoch);
-                                        thread::sleep(Duration::from_secs(2));
-                                        sender_service.send_barrier();
-                                    }
-                                }
+        let job_abort_handle = job_task.abort_handle();
+        // the task recording how the job ends, including by a panic or a failed checkpoint
+        let job_info = info.clone();
+        let supervisor_task = tokio::spawn(async move {
+            let result = tokio::select! {
+                result = &mut job_task => result,
+                result = &mut checkpoint_task => {
+                    job_task.abort();
+                    result
+                }
+            };
+            // the job is ended, no more barriers need to be injected
+            checkpoint_task.abort();
+            let error = match result {
+                Ok(Ok(())) => {
+                    job_info.lock().unwrap().stop(JobStatus::Finished);
+                    return;
                 }
-            });
-       StreamRunningJob {
-           current_job_id: self.current_job_id,
-           job_running_handle: Some(job_task),
-           job_checkpoint_handle: Some(checkpoint_task)
-       }
+                Ok(Err(e)) => e,
+                Err(e) if e.is_panic() => Error::Panic(panic_message(e.into_panic())),
+                Err(_) => {
+                    job_info.lock().unwrap().stop(JobStatus::Cancelled);
+                    return;
+                }
+            };
+            job_info.lock().unwrap().stop(JobStatus::Failed(error.to_string()));
+            let _ = output_tx.send(Err(error)).await;
+        });
+        let handle = JobHandle::new(output_rx, info.clone());
+        let running_job = StreamRunningJob {
+            current_job_id: self.current_job_id,
+            job_abort_handle,
+            job_supervisor_handle: Some(supervisor_task),
+            info,
+        };
+        (running_job, handle)
+    }
+}
+
+/// Returns the message of the payload of a panic.
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => match payload.downcast::<&str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => "unknown panic".to_string(),
+        },
     }
 }
 
 impl StreamRunningJob {
-    pub async fn stop(self) {
-        // job thread
-        if let Some(job_running_handle) = self.job_running_handle {
-            job_running_handle.abort();
-            let result = job_running_handle.await;
+    /// Stops the job if it's running, which is then cancelled.
+    pub async fn stop(&mut self) {
+        // job thread, whose supervisor records that it's cancelled and stops the checkpoints
+        self.job_abort_handle.abort();
+        if let Some(job_supervisor_handle) = self.job_supervisor_handle.take() {
+            let result = job_supervisor_handle.await;
             assert!(result.is_ok() || result.unwrap_err().is_cancelled());
         }
+    }
 
-        // checkpoint thread
-        if let Some(job_checkpoint_handle) = self.job_checkpoint_handle {
-            job_checkpoint_handle.abort();
-            let result = job_checkpoint_handle.await;
-            assert!(result.is_ok() || result.unwrap_err().is_cancelled());
+    pub fn info(&self) -> JobInfo {
+        self.info.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{FutureExt, StreamExt};
+    use futures_async_stream::try_stream;
+    use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+    use crate::array::DataChunk;
+    use crate::stream::Barrier;
+    use crate::executor::ExecuteError;
+    use super::*;
+
+    fn new_job(barrier_manager: Arc<Mutex<BarrierManager>>, result_executor: BoxedExecutor) -> Job {
+        Job {
+            current_job_id: "job".into(),
+            barrier_manager,
+            result_executor,
+            is_ddl_job: false,
+            start_epoch: 0,
+            checkpoint_store: None,
+            sql: String::new(),
         }
     }
-}
\ No newline at end of file
+
+    /// Runs a job of the executor, returning it with its output until the handle is ended.
+    async fn run(result_executor: BoxedExecutor) -> (StreamRunningJob, Vec<Result<DataChunk, Error>>) {
+        let job = new_job(Arc::new(Mutex::new(BarrierManager::new())), result_executor);
+        let (running_job, handle) = job.run().await.unwrap().unwrap();
+        let output = handle.collect().await;
+        (running_job, output)
+    }
+
+    /// The executor of the actor 1 which completes its first barrier and never ends.
+    #[try_stream(boxed, ok = Message, error = ExecuteError)]
+    async fn actor(barrier_manager: Arc<Mutex<BarrierManager>>, mut rx: UnboundedReceiver<Barrier>) {
+        let barrier = rx.recv().await.unwrap();
+        barrier_manager.lock().unwrap().notify_barrier_complete(barrier.epoch, 1);
+        yield Message::Barrier(barrier);
+        futures::future::pending::<()>().await;
+    }
+
+    #[tokio::test]
+    async fn test_job_status() {
+        // finished
+        let chunk = || Ok(Message::Chunk(DataChunk::single_str("a")));
+        let (running_job, output) = run(futures::stream::iter([chunk()]).boxed()).await;
+        assert!(matches!(output.as_slice(), [Ok(_)]));
+        assert_eq!(running_job.info().status, JobStatus::Finished);
+
+        // failed by an error, which ends the output
+        let executor = futures::stream::iter([chunk(), Err(ExecuteError::Sink("broken".into())), chunk()]);
+        let (running_job, output) = run(executor.boxed()).await;
+        assert!(matches!(output.as_slice(), [Ok(_), Err(Error::Execute(ExecuteError::Sink(_)))]));
+        assert!(running_job.info().error().unwrap().contains("broken"));
+
+        // failed by a panic
+        let executor = async { panic!("oops") }.into_stream();
+        let (running_job, output) = run(executor.boxed()).await;
+        assert!(matches!(output.as_slice(), [Err(Error::Panic(message))] if message == "oops"));
+        assert_eq!(running_job.info().status, JobStatus::Failed(Error::Panic("oops".into()).to_string()));
+
+        // cancelled
+        let job = new_job(Arc::new(Mutex::new(BarrierManager::new())), futures::stream::pending().boxed());
+        let (mut running_job, handle) = job.run().await.unwrap().unwrap();
+        running_job.stop().await;
+        assert!(handle.collect::<Vec<_>>().await.is_empty());
+        assert_eq!(running_job.info().status, JobStatus::Cancelled);
+
+        // failed by a failed commit of the output
+        let barrier_manager = Arc::new(Mutex::new(BarrierManager::new()));
+        let (tx, rx) = unbounded_channel();
+        barrier_manager.lock().unwrap().register_actor(1);
+        barrier_manager.lock().unwrap().register_sender(1, tx);
+        barrier_manager.lock().unwrap().register_commit(
+            1, async { Err(ExecuteError::Sink("commit failed".into())) }.boxed());
+        let job = new_job(barrier_manager.clone(), actor(barrier_manager, rx));
+        let (running_job, handle) = job.run().await.unwrap().unwrap();
+        let output: Vec<_> = handle.collect().await;
+        assert!(matches!(output.as_slice(), [Err(Error::Execute(ExecuteError::Sink(_)))]));
+        assert!(running_job.info().error().unwrap().contains("commit failed"));
+    }
+}
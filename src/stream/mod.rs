@@ -1,18 +1,28 @@
 mod job;
+mod handle;
 
 use crate::array::DataChunk;
 
 pub use crate::stream::job::Job;
 pub use crate::stream::job::StreamRunningJob;
+pub use crate::stream::handle::*;
 
 #[derive(Debug, PartialEq)]
 pub enum Message {
     Chunk(DataChunk),
     Barrier(Barrier),
+    Watermark(Watermark),
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Barrier {
     pub epoch: u64,
     pub timestamp: u64,
-}
\ No newline at end of file
+}
+
+/// An event-time watermark, which means no row with an event time earlier than
+/// `timestamp` is expected anymore.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Watermark {
+    pub timestamp: i64,
+}
@@ -1,4 +1,5 @@
 pub mod native;
+pub mod timestamp;
 
 
 use std::fmt::{Display, Formatter};
@@ -6,7 +7,7 @@ use std::str::FromStr;
 
 use parse_display::Display;
 pub use self::native::*;
-use sqlparser::ast::DataType::{Char, Int, Text, Varchar, Boolean};
+use sqlparser::ast::DataType::{Char, Int, Text, Varchar, Boolean, Interval, Timestamp};
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum DataTypeKind {
@@ -109,6 +110,7 @@ impl DataTypeKind {
             (Float64, Float64 | Decimal(_, _) | String) => Some(b.clone()),
             (Decimal(_, _), Decimal(_, _) | String) => Some(b.clone()),
             (Date, Date | String) => Some(b.clone()),
+            (Timestamp, Timestamp | String) => Some(b.clone()),
             (Interval, Interval | String) => Some(b.clone()),
             (String, String | Blob) => Some(b.clone()),
             (Blob, Blob) => Some(b.clone()),
@@ -134,6 +136,8 @@ impl From<&crate::parser::DataType> for DataTypeKind {
             Char(_) | Varchar(_) | crate::parser::DataType::String | Text => Self::String,
             Int(_) => Self::Int32,
             Boolean => Self::Bool,
+            Timestamp(_, _) => Self::Timestamp,
+            Interval => Self::Interval,
             _ => todo!("not supported type: {:?}", kind)
         }
     }
@@ -157,6 +161,22 @@ impl FromStr for DataType {
 pub enum ConvertError {
     #[error("failed to convert string {0:?} to int: {1}")]
     ParseInt(String, #[source] std::num::ParseIntError),
+    #[error("no function {0}({1}, {2})")]
+    NoBinaryOp(String, DataTypeKind, DataTypeKind),
+    #[error("no function {0}({1})")]
+    NoUnaryOp(String, DataTypeKind),
+    #[error("failed to convert string {0:?} to boolean")]
+    ParseBool(String),
+    #[error("failed to convert string {0:?} to timestamp")]
+    ParseTimestamp(String),
+    #[error("failed to convert string {0:?} to interval")]
+    ParseInterval(String),
+    #[error("failed to convert {0} to a non-negative integer")]
+    NotUsize(DataValue),
+    #[error("division by zero")]
+    DivisionByZero,
+    #[error("numeric overflow")]
+    Overflow,
 }
 
 
@@ -190,6 +210,10 @@ pub enum DataValue {
     Bool(bool),
     Int32(i32),
     String(String),
+    /// Milliseconds since the unix epoch.
+    Timestamp(i64),
+    /// Milliseconds.
+    Interval(i64),
 }
 
 impl Display for DataValue {
@@ -199,6 +223,8 @@ impl Display for DataValue {
             DataValue::Bool(v) => write!(f, "{}", v.to_string()),
             DataValue::Int32(v) => write!(f, "{}", v.to_string()),
             DataValue::String(v) => write!(f, "{}", v.to_string()),
+            DataValue::Timestamp(v) => write!(f, "{}", timestamp::format_timestamp(*v)),
+            DataValue::Interval(v) => write!(f, "{}", timestamp::format_interval(*v)),
         }
     }
 }
@@ -211,6 +237,15 @@ impl FromStr for DataValue {
     }
 }
 
+/// Parses a boolean like `true`, `f` or `1`, ignoring case.
+pub fn parse_bool(s: &str) -> Result<bool, ConvertError> {
+    match s.trim().to_lowercase().as_str() {
+        "true" | "t" | "1" => Ok(true),
+        "false" | "f" | "0" => Ok(false),
+        _ => Err(ConvertError::ParseBool(s.into())),
+    }
+}
+
 macro_rules! impl_arith_for_datavalue {
     ($Trait:ident, $name:ident) => {
         impl std::ops::$Trait for &DataValue {
@@ -241,6 +276,7 @@ macro_rules! impl_arith_for_datavalue {
 }
 
 impl_arith_for_datavalue!(Add, add);
+impl_arith_for_datavalue!(Sub, sub);
 
 
 
@@ -253,6 +289,18 @@ impl DataValue {
             self + other
         }
     }
+
+    /// Subtracts a value from an agg state, which is unchanged by a null.
+    pub fn sub_nullable(self, other: Self) -> Result<Self, ConvertError> {
+        match (self, other) {
+            (state, DataValue::Null) => Ok(state),
+            (DataValue::Int32(x), DataValue::Int32(y)) => {
+                x.checked_sub(y).map(DataValue::Int32).ok_or(ConvertError::Overflow)
+            }
+            (state, other) => Ok(&state - &other),
+        }
+    }
+
    pub  fn or(self, other: Self) -> Self {
         if self.is_null() {
             other
@@ -265,12 +313,23 @@ impl DataValue {
         matches!(self, Self::Null)
     }
 
+    /// Converts the value to a non-negative `usize`, returns `None` if it is null.
+    pub fn as_usize(&self) -> Result<Option<usize>, ConvertError> {
+        match self {
+            DataValue::Null => Ok(None),
+            DataValue::Int32(v) if *v >= 0 => Ok(Some(*v as usize)),
+            _ => Err(ConvertError::NotUsize(self.clone())),
+        }
+    }
+
     pub fn datatype(&self) -> Option<DataType> {
         match self {
-            DataValue::Null => Some(DataType::new(DataTypeKind::String, false)),
+            DataValue::Null => Some(DataType::new(DataTypeKind::Null, true)),
             DataValue::Bool(_) => Some(DataType::new(DataTypeKind::Bool, false)),
             DataValue::Int32(_) => Some(DataType::new(DataTypeKind::Int32, false)),
             DataValue::String(_) => Some(DataType::new(DataTypeKind::String, false)),
+            DataValue::Timestamp(_) => Some(DataType::new(DataTypeKind::Timestamp, false)),
+            DataValue::Interval(_) => Some(DataType::new(DataTypeKind::Interval, false)),
         }
     }
 }
@@ -0,0 +1,200 @@
+//! Conversions of `TIMESTAMP` and `INTERVAL` values.
+//!
+//! A timestamp is stored as the number of milliseconds since `1970-01-01 00:00:00`,
+//! and an interval as a number of milliseconds.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use sqlparser::ast::DateTimeField;
+use crate::types::ConvertError;
+
+pub const MILLIS_PER_SECOND: i64 = 1000;
+pub const MILLIS_PER_MINUTE: i64 = 60 * MILLIS_PER_SECOND;
+pub const MILLIS_PER_HOUR: i64 = 60 * MILLIS_PER_MINUTE;
+pub const MILLIS_PER_DAY: i64 = 24 * MILLIS_PER_HOUR;
+
+static TIMESTAMP_RE: Lazy<Regex> = Lazy::new(|| Regex::new(
+    r"^(\d{4})-(\d{1,2})-(\d{1,2})(?:[ T](\d{1,2}):(\d{2})(?::(\d{2})(?:\.(\d+))?)?)?$").unwrap());
+static INTERVAL_RE: Lazy<Regex> = Lazy::new(|| Regex::new(
+    r"(\d+(?:\.\d+)?)\s*([a-zA-Z]+)").unwrap());
+
+/// Returns the current processing time.
+pub fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system time before unix epoch")
+        .as_millis() as i64
+}
+
+/// Formats a timestamp as `YYYY-MM-DD HH:MM:SS.fff`.
+pub fn format_timestamp(ts: i64) -> String {
+    let (y, m, d) = civil_from_days(ts.div_euclid(MILLIS_PER_DAY));
+    let ms = ts.rem_euclid(MILLIS_PER_DAY);
+    format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:03}",
+            y, m, d,
+            ms / MILLIS_PER_HOUR,
+            ms % MILLIS_PER_HOUR / MILLIS_PER_MINUTE,
+            ms % MILLIS_PER_MINUTE / MILLIS_PER_SECOND,
+            ms % MILLIS_PER_SECOND)
+}
+
+/// Parses a timestamp like `2024-01-01`, `2024-01-01 12:00:00` or `2024-01-01T12:00:00.123`.
+pub fn parse_timestamp(s: &str) -> Result<i64, ConvertError> {
+    let err = || ConvertError::ParseTimestamp(s.into());
+    let cap = TIMESTAMP_RE.captures(s.trim()).ok_or_else(err)?;
+    let field = |i: usize| cap.get(i).map_or(0, |m| m.as_str().parse::<i64>().unwrap());
+    let (y, m, d) = (field(1), field(2), field(3));
+    let (hh, mm, ss) = (field(4), field(5), field(6));
+    if !(1..=12).contains(&m) || d < 1 || d > days_in_month(y, m as u32) || hh > 23 || mm > 59 || ss > 59 {
+        return Err(err());
+    }
+    // only the first 3 digits of the fraction are kept
+    let millis = cap.get(7).map_or(0, |f| {
+        let digits = format!("{:0<3}", &f.as_str()[..f.as_str().len().min(3)]);
+        digits.parse::<i64>().unwrap()
+    });
+    Ok(days_from_civil(y, m as u32, d as u32) * MILLIS_PER_DAY
+        + hh * MILLIS_PER_HOUR + mm * MILLIS_PER_MINUTE + ss * MILLIS_PER_SECOND + millis)
+}
+
+/// Formats an interval as `[N day[s] ]HH:MM:SS[.fff]`.
+pub fn format_interval(interval: i64) -> String {
+    let sign = if interval < 0 { "-" } else { "" };
+    let abs = interval.unsigned_abs() as i64;
+    let days = abs / MILLIS_PER_DAY;
+    let ms = abs % MILLIS_PER_DAY;
+    let mut s = String::from(sign);
+    if days > 0 {
+        s.push_str(&format!("{} day{} ", days, if days > 1 { "s" } else { "" }));
+    }
+    s.push_str(&format!("{:02}:{:02}:{:02}",
+                        ms / MILLIS_PER_HOUR,
+                        ms % MILLIS_PER_HOUR / MILLIS_PER_MINUTE,
+                        ms % MILLIS_PER_MINUTE / MILLIS_PER_SECOND));
+    if ms % MILLIS_PER_SECOND != 0 {
+        s.push_str(&format!(".{:03}", ms % MILLIS_PER_SECOND));
+    }
+    s
+}
+
+/// Parses an interval literal, e.g. `INTERVAL '5' SECOND` or `INTERVAL '1 hour 30 minutes'`.
+///
+/// Only units of fixed length are supported, so `MONTH` and `YEAR` are rejected.
+pub fn parse_interval(value: &str, unit: Option<&DateTimeField>) -> Result<i64, ConvertError> {
+    let err = || ConvertError::ParseInterval(value.into());
+    if let Some(unit) = unit {
+        let unit = field_millis(unit).ok_or_else(err)?;
+        let value: f64 = value.trim().parse().map_err(|_| err())?;
+        return Ok((value * unit as f64).round() as i64);
+    }
+    let mut interval = 0;
+    let mut rest = value.trim();
+    while !rest.is_empty() {
+        let cap = INTERVAL_RE.captures(rest).ok_or_else(err)?;
+        let whole = cap.get(0).unwrap();
+        if whole.start() != 0 {
+            return Err(err());
+        }
+        let value: f64 = cap[1].parse().map_err(|_| err())?;
+        let unit = unit_millis(&cap[2].to_lowercase()).ok_or_else(err)?;
+        interval += (value * unit as f64).round() as i64;
+        rest = rest[whole.end()..].trim_start();
+    }
+    if value.trim().is_empty() {
+        return Err(err());
+    }
+    Ok(interval)
+}
+
+fn field_millis(field: &DateTimeField) -> Option<i64> {
+    use DateTimeField::*;
+    Some(match field {
+        Week => 7 * MILLIS_PER_DAY,
+        Day => MILLIS_PER_DAY,
+        Hour => MILLIS_PER_HOUR,
+        Minute => MILLIS_PER_MINUTE,
+        Second => MILLIS_PER_SECOND,
+        Millisecond | Milliseconds => 1,
+        _ => return None,
+    })
+}
+
+fn unit_millis(unit: &str) -> Option<i64> {
+    Some(match unit {
+        "week" | "weeks" => 7 * MILLIS_PER_DAY,
+        "day" | "days" | "d" => MILLIS_PER_DAY,
+        "hour" | "hours" | "h" => MILLIS_PER_HOUR,
+        "minute" | "minutes" | "min" | "mins" | "m" => MILLIS_PER_MINUTE,
+        "second" | "seconds" | "sec" | "secs" | "s" => MILLIS_PER_SECOND,
+        "millisecond" | "milliseconds" | "ms" => 1,
+        _ => return None,
+    })
+}
+
+/// Returns the number of days in a month of a year.
+fn days_in_month(y: i64, m: u32) -> i64 {
+    match m {
+        2 if y % 4 == 0 && (y % 100 != 0 || y % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Converts days since epoch to `(year, month, day)`.
+///
+/// See <http://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = if z >= 0 { z } else { z - 146096 } / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let y = yoe + era * 400;
+    (if m <= 2 { y + 1 } else { y }, m, d)
+}
+
+/// Converts `(year, month, day)` to days since epoch.
+///
+/// See <http://howardhinnant.github.io/date_algorithms.html#days_from_civil>.
+fn days_from_civil(y: i64, m: u32, d: u32) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400;
+    let m = m as i64;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + d as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timestamp() {
+        let ts = parse_timestamp("2024-03-10 12:34:56.7").unwrap();
+        assert_eq!(ts, 1710074096700);
+        assert_eq!(format_timestamp(ts), "2024-03-10 12:34:56.700");
+        assert_eq!(format_timestamp(parse_timestamp("1969-12-31").unwrap()), "1969-12-31 00:00:00.000");
+        assert!(parse_timestamp("2024-13-01").is_err());
+        assert_eq!(format_timestamp(parse_timestamp("2024-02-29").unwrap()), "2024-02-29 00:00:00.000");
+        assert_eq!(format_timestamp(parse_timestamp("2000-02-29").unwrap()), "2000-02-29 00:00:00.000");
+        for s in ["2024-02-30", "2024-02-31", "2023-02-29", "1900-02-29", "2023-04-31", "2024-06-31"] {
+            assert!(parse_timestamp(s).is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn test_interval() {
+        assert_eq!(parse_interval("5", Some(&DateTimeField::Second)).unwrap(), 5000);
+        assert_eq!(parse_interval("1 hour 30 minutes", None).unwrap(), 90 * MILLIS_PER_MINUTE);
+        assert!(parse_interval("1", Some(&DateTimeField::Month)).is_err());
+        assert!(parse_interval("1 fortnight", None).is_err());
+        assert_eq!(format_interval(MILLIS_PER_DAY + 1500), "1 day 00:00:01.500");
+    }
+}